@@ -0,0 +1,679 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::schedules::Schedule;
+use crate::process_monitor::{matches_process_name, ProcessEvent, ProcessInfo};
+
+pub const DEFAULT_KILL_AFTER_SECS: u64 = 30;
+
+/// What happens to a blocked app once it is running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnforcementMode {
+    #[default]
+    Overlay,
+    OverlayThenKill,
+    Kill,
+    Suspend,
+}
+
+impl EnforcementMode {
+    fn strictness(self) -> u8 {
+        match self {
+            EnforcementMode::Overlay => 0,
+            EnforcementMode::OverlayThenKill => 1,
+            // A suspended app can be resumed; a killed one has to start over.
+            EnforcementMode::Suspend => 2,
+            EnforcementMode::Kill => 3,
+        }
+    }
+}
+
+/// One `blockedApps` entry. Legacy entries are bare process-name strings;
+/// the optional fields let a rule survive the executable being renamed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedAppRule {
+    #[serde(default)]
+    pub process_name: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_name: Option<String>,
+    #[serde(default)]
+    pub enforcement: EnforcementMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kill_after_seconds: Option<u64>,
+    /// Minutes per day the app may run before it is blocked; `None` blocks
+    /// it outright.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_quota_minutes: Option<u64>,
+    /// When the rule is enforced; empty means all the time.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<Schedule>,
+}
+
+impl BlockedAppRule {
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        let mut rule = match value {
+            serde_json::Value::String(name) => BlockedAppRule { process_name: name.clone(), ..Default::default() },
+            serde_json::Value::Object(_) => serde_json::from_value(value.clone()).ok()?,
+            _ => return None,
+        };
+
+        rule.process_name = rule.process_name.trim().to_string();
+        rule.install_location = rule.install_location.filter(|dir| is_specific_install_dir(dir));
+        rule.sha256 = rule.sha256.map(|h| h.trim().to_lowercase()).filter(|h| h.len() == 64);
+        rule.publisher = rule.publisher.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        rule.product_name = rule.product_name.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+
+        if rule.display_name.trim().is_empty() {
+            rule.display_name = rule
+                .product_name
+                .clone()
+                .or_else(|| (!rule.process_name.is_empty()).then(|| rule.process_name.clone()))
+                .or_else(|| rule.publisher.clone())
+                .unwrap_or_default();
+        }
+
+        let has_identity = !rule.process_name.is_empty()
+            || rule.install_location.is_some()
+            || rule.sha256.is_some()
+            || rule.publisher.is_some();
+        if has_identity {
+            Some(rule)
+        } else {
+            None
+        }
+    }
+
+    pub fn kill_after(&self) -> Duration {
+        Duration::from_secs(self.kill_after_seconds.unwrap_or(DEFAULT_KILL_AFTER_SECS))
+    }
+
+    pub fn daily_quota(&self) -> Option<Duration> {
+        self.daily_quota_minutes.map(|m| Duration::from_secs(m * 60))
+    }
+
+    /// Whether a new daily quota keeps the rule at least as strict: removing
+    /// the quota or lowering it, but not adding or raising one.
+    pub fn allows_quota_change(&self, minutes: Option<u64>) -> bool {
+        match (minutes, self.daily_quota_minutes) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(new), Some(old)) => new <= old,
+        }
+    }
+
+    /// Whether switching to `mode` keeps the rule at least as strict, which
+    /// is allowed without going through the delay.
+    pub fn allows_enforcement_change(&self, mode: EnforcementMode, kill_after_seconds: Option<u64>) -> bool {
+        let current = self.enforcement.strictness();
+        let requested = mode.strictness();
+        if requested != current {
+            return requested > current;
+        }
+        if mode == EnforcementMode::OverlayThenKill {
+            let requested_after = kill_after_seconds.unwrap_or(DEFAULT_KILL_AFTER_SECS);
+            return requested_after <= self.kill_after().as_secs();
+        }
+        true
+    }
+
+    /// Whether this rule matches every process `other` matches: each way
+    /// `other` can match needs a counterpart here that is no narrower.
+    pub fn covers_matches_of(&self, other: &BlockedAppRule) -> bool {
+        let name_ok = other.process_name.is_empty()
+            || (!self.process_name.is_empty() && matches_process_name(&self.process_name, &other.process_name));
+
+        let location_ok = match (other.install_location.as_deref(), self.install_location.as_deref()) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(old), Some(new)) => normalize_path(old) == normalize_path(new) || is_in_install_dir(old, new),
+        };
+
+        let hash_ok = other.sha256.is_none() || self.sha256 == other.sha256;
+
+        let signature_ok = match (other.publisher.as_deref(), self.publisher.as_deref()) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(old), Some(new)) => {
+                old.eq_ignore_ascii_case(new)
+                    && match (other.product_name.as_deref(), self.product_name.as_deref()) {
+                        (_, None) => true,
+                        (None, Some(_)) => false,
+                        (Some(old), Some(new)) => old.eq_ignore_ascii_case(new),
+                    }
+            }
+        };
+
+        name_ok && location_ok && hash_ok && signature_ok
+    }
+
+    fn needs_signature(&self) -> bool {
+        self.publisher.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSignature {
+    pub publisher: Option<String>,
+    pub product_name: Option<String>,
+}
+
+/// Facts about an executable that are too slow to gather for every process,
+/// so they are only asked for when a rule needs them.
+pub trait FileInspector {
+    fn sha256(&mut self, path: &str) -> Option<String>;
+    /// `publisher` is only set when the Authenticode signature is valid.
+    fn signature(&mut self, path: &str) -> Option<FileSignature>;
+    /// Whether an answer for `path` is still being worked out, so a `None`
+    /// from the methods above is not final yet.
+    fn is_pending(&self, _path: &str) -> bool {
+        false
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim()
+        .trim_matches('"')
+        .replace('/', "\\")
+        .trim_end_matches('\\')
+        .to_lowercase()
+}
+
+// Folders that hold many apps; a rule pointing at one of them would block
+// far more than the app it was made for.
+const SHARED_APP_FOLDERS: [&str; 11] = [
+    "program files",
+    "program files (x86)",
+    "programdata",
+    "users",
+    "appdata",
+    "local",
+    "roaming",
+    "locallow",
+    "programs",
+    "common files",
+    "common",
+];
+
+pub fn is_specific_install_dir(dir: &str) -> bool {
+    let normalized = normalize_path(dir);
+    let components: Vec<&str> = normalized.split('\\').filter(|c| !c.is_empty()).collect();
+    match components.last() {
+        Some(last) => {
+            components.len() >= 2
+                && !components.contains(&"windows")
+                && !SHARED_APP_FOLDERS.contains(last)
+                && components[components.len() - 2] != "users"
+        }
+        None => false,
+    }
+}
+
+pub fn is_in_install_dir(exe_path: &str, install_dir: &str) -> bool {
+    let dir = normalize_path(install_dir);
+    let exe = normalize_path(exe_path);
+    !dir.is_empty() && exe.len() > dir.len() && exe.starts_with(&dir) && exe[dir.len()..].starts_with('\\')
+}
+
+/// Whether `process` is covered by `rule`. Names and install directories
+/// are checked first; the hash and signature are only computed when those
+/// do not already decide it.
+pub fn rule_matches(rule: &BlockedAppRule, process: &ProcessInfo, inspector: &mut dyn FileInspector) -> bool {
+    if !rule.process_name.is_empty() && matches_process_name(&rule.process_name, &process.name) {
+        return true;
+    }
+
+    let exe_path = match process.exe_path.as_deref() {
+        Some(p) if !p.is_empty() => p,
+        _ => return false,
+    };
+
+    if let Some(dir) = rule.install_location.as_deref() {
+        if is_in_install_dir(exe_path, dir) {
+            return true;
+        }
+    }
+
+    if let Some(expected) = rule.sha256.as_deref() {
+        if inspector.sha256(exe_path).as_deref() == Some(expected) {
+            return true;
+        }
+    }
+
+    if rule.needs_signature() {
+        if let Some(signature) = inspector.signature(exe_path) {
+            let publisher_matches = match (rule.publisher.as_deref(), signature.publisher.as_deref()) {
+                (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(actual),
+                _ => false,
+            };
+            let product_matches = match rule.product_name.as_deref() {
+                Some(expected) => signature.product_name.as_deref().map(|p| p.eq_ignore_ascii_case(expected)).unwrap_or(false),
+                None => true,
+            };
+            if publisher_matches && product_matches {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Remembers which running processes are blocked and by which rule, so a
+/// process is matched once when it starts instead of on every poll. The
+/// whole snapshot is re-matched when the rules change. Processes whose
+/// hash or signature was still pending are retried on later updates.
+#[derive(Default)]
+pub struct BlockedProcessIndex {
+    rules: Vec<BlockedAppRule>,
+    blocked: HashMap<u32, (ProcessInfo, usize)>,
+    undecided: HashMap<u32, ProcessInfo>,
+}
+
+impl BlockedProcessIndex {
+    pub fn update(
+        &mut self,
+        rules: Vec<BlockedAppRule>,
+        events: &[ProcessEvent],
+        running: &[ProcessInfo],
+        inspector: &mut dyn FileInspector,
+    ) {
+        if rules != self.rules {
+            self.rules = rules;
+            self.blocked.clear();
+            self.undecided.clear();
+            for process in running {
+                self.index(process, inspector);
+            }
+            return;
+        }
+
+        for event in events {
+            match event {
+                ProcessEvent::Started(process) => self.index(process, inspector),
+                ProcessEvent::Stopped(process) => {
+                    self.blocked.remove(&process.pid);
+                    self.undecided.remove(&process.pid);
+                }
+            }
+        }
+
+        let ready: Vec<ProcessInfo> = self
+            .undecided
+            .values()
+            .filter(|p| !p.exe_path.as_deref().map(|path| inspector.is_pending(path)).unwrap_or(false))
+            .cloned()
+            .collect();
+        for process in ready {
+            self.undecided.remove(&process.pid);
+            self.index(&process, inspector);
+        }
+    }
+
+    fn index(&mut self, process: &ProcessInfo, inspector: &mut dyn FileInspector) {
+        if let Some(i) = self.rules.iter().position(|rule| rule_matches(rule, process, inspector)) {
+            self.blocked.insert(process.pid, (process.clone(), i));
+        } else if process.exe_path.as_deref().map(|path| inspector.is_pending(path)).unwrap_or(false) {
+            self.undecided.insert(process.pid, process.clone());
+        }
+    }
+
+    /// Blocked processes, oldest first.
+    pub fn blocked(&self) -> Vec<(&ProcessInfo, &BlockedAppRule)> {
+        let mut blocked: Vec<(&ProcessInfo, &BlockedAppRule)> =
+            self.blocked.values().map(|(process, i)| (process, &self.rules[*i])).collect();
+        blocked.sort_by_key(|(process, _)| (process.start_time, process.pid));
+        blocked
+    }
+}
+
+pub fn sha256_file(path: &str) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The `CN=` value of an X.500 subject such as
+/// `CN=Valve Corp., O=Valve Corp., L=Bellevue, S=Washington, C=US`.
+pub fn subject_common_name(subject: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for ch in subject.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+
+    parts
+        .iter()
+        .filter_map(|part| part.trim().strip_prefix("CN="))
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+/// Parses the tab-separated `status, subject, product` line printed by
+/// `signature_script`.
+pub fn parse_signature_output(output: &str) -> Option<FileSignature> {
+    let line = output.lines().map(|l| l.trim_end_matches('\r')).find(|l| !l.trim().is_empty())?;
+    let mut fields = line.split('\t');
+    let status = fields.next()?.trim();
+    let subject = fields.next().unwrap_or("").trim();
+    let product = fields.next().unwrap_or("").trim();
+
+    Some(FileSignature {
+        publisher: if status.eq_ignore_ascii_case("Valid") { subject_common_name(subject) } else { None },
+        product_name: (!product.is_empty()).then(|| product.to_string()),
+    })
+}
+
+pub fn signature_script(path: &str) -> String {
+    let quoted = path.replace('\'', "''");
+    format!(
+        "$s = Get-AuthenticodeSignature -LiteralPath '{0}'; $v = (Get-Item -LiteralPath '{0}').VersionInfo; \
+         \"$($s.Status)`t$($s.SignerCertificate.Subject)`t$($v.ProductName)\"",
+        quoted
+    )
+}
+
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &str) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Inspection {
+    Hash,
+    Signature,
+}
+
+#[derive(Default)]
+struct InspectionCache {
+    hashes: HashMap<String, (FileStamp, Option<String>)>,
+    signatures: HashMap<String, (FileStamp, Option<FileSignature>)>,
+    pending: HashSet<(String, Inspection)>,
+}
+
+fn read_signature(path: &str) -> Option<FileSignature> {
+    let script = signature_script(path);
+    match crate::run_hidden_output("powershell", &["-NoProfile", "-NonInteractive", "-Command", &script]) {
+        Ok(output) if output.status.success() => parse_signature_output(&String::from_utf8_lossy(&output.stdout)),
+        Ok(output) => {
+            eprintln!(
+                "SystemFileInspector::signature: powershell failed for {}: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            eprintln!("SystemFileInspector::signature: {}", e);
+            None
+        }
+    }
+}
+
+fn run_inspections(requests: mpsc::Receiver<(String, String, Inspection)>, cache: Arc<Mutex<InspectionCache>>) {
+    for (key, path, kind) in requests {
+        let stamp = file_stamp(&path);
+        let (hash, signature) = match kind {
+            Inspection::Hash => (
+                sha256_file(&path).map_err(|e| eprintln!("SystemFileInspector::sha256: {}", e)).ok(),
+                None,
+            ),
+            Inspection::Signature => (None, read_signature(&path)),
+        };
+
+        let mut cache = match cache.lock() {
+            Ok(cache) => cache,
+            Err(_) => return,
+        };
+        cache.pending.remove(&(key.clone(), kind));
+        if let Some(stamp) = stamp {
+            match kind {
+                Inspection::Hash => cache.hashes.insert(key, (stamp, hash)).map(|_| ()),
+                Inspection::Signature => cache.signatures.insert(key, (stamp, signature)).map(|_| ()),
+            };
+        }
+    }
+}
+
+/// Hashes files and reads signatures through PowerShell on a background
+/// thread, so a slow lookup never holds up the process monitor. Results are
+/// cached per path until the file's size or modification time changes;
+/// until one is ready the lookup answers `None` and reports it pending.
+#[derive(Default)]
+pub struct SystemFileInspector {
+    cache: Arc<Mutex<InspectionCache>>,
+    requests: Option<Sender<(String, String, Inspection)>>,
+}
+
+impl SystemFileInspector {
+    fn request(&mut self, key: String, path: &str, kind: Inspection) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if !cache.pending.insert((key.clone(), kind)) {
+                return;
+            }
+        }
+
+        let cache = self.cache.clone();
+        let requests = self.requests.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || run_inspections(rx, cache));
+            tx
+        });
+        if requests.send((key.clone(), path.to_string(), kind)).is_err() {
+            eprintln!("SystemFileInspector: inspection thread stopped");
+            self.requests = None;
+            if let Ok(mut cache) = self.cache.lock() {
+                cache.pending.remove(&(key, kind));
+            }
+        }
+    }
+}
+
+impl FileInspector for SystemFileInspector {
+    fn sha256(&mut self, path: &str) -> Option<String> {
+        let stamp = file_stamp(path)?;
+        let key = normalize_path(path);
+        if let Ok(cache) = self.cache.lock() {
+            if let Some((cached_stamp, hash)) = cache.hashes.get(&key) {
+                if *cached_stamp == stamp {
+                    return hash.clone();
+                }
+            }
+        }
+        self.request(key, path, Inspection::Hash);
+        None
+    }
+
+    fn signature(&mut self, path: &str) -> Option<FileSignature> {
+        let stamp = file_stamp(path)?;
+        let key = normalize_path(path);
+        if let Ok(cache) = self.cache.lock() {
+            if let Some((cached_stamp, signature)) = cache.signatures.get(&key) {
+                if *cached_stamp == stamp {
+                    return signature.clone();
+                }
+            }
+        }
+        self.request(key, path, Inspection::Signature);
+        None
+    }
+
+    fn is_pending(&self, path: &str) -> bool {
+        let key = normalize_path(path);
+        match self.cache.lock() {
+            Ok(cache) => cache.pending.contains(&(key.clone(), Inspection::Hash)) || cache.pending.contains(&(key, Inspection::Signature)),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeFileInspector {
+    pub hashes: HashMap<String, String>,
+    pub signatures: HashMap<String, FileSignature>,
+    pub pending: HashSet<String>,
+}
+
+#[cfg(test)]
+impl FileInspector for FakeFileInspector {
+    fn sha256(&mut self, path: &str) -> Option<String> {
+        self.hashes.get(path).cloned()
+    }
+
+    fn signature(&mut self, path: &str) -> Option<FileSignature> {
+        self.signatures.get(path).cloned()
+    }
+
+    fn is_pending(&self, path: &str) -> bool {
+        self.pending.contains(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEAM_EXE: &str = r"C:\Games\Steam\steam.exe";
+    const STEAM_HASH: &str = "0f343b0931126a20f133d67c2b018a3b5a7ad6d4a4b2f5a1e3c4d5e6f7a8b9c0";
+
+    fn steam() -> ProcessInfo {
+        ProcessInfo::new(10, "renamed.exe", Some(STEAM_EXE.to_string()))
+    }
+
+    fn rule(value: serde_json::Value) -> BlockedAppRule {
+        BlockedAppRule::from_value(&value).unwrap()
+    }
+
+    fn signed(publisher: Option<&str>, product: Option<&str>) -> FileSignature {
+        FileSignature { publisher: publisher.map(|p| p.to_string()), product_name: product.map(|p| p.to_string()) }
+    }
+
+    #[test]
+    fn matches_by_name_and_install_location() {
+        let mut inspector = FakeFileInspector::default();
+        assert!(rule_matches(&rule(serde_json::json!("Renamed")), &steam(), &mut inspector));
+        assert!(rule_matches(&rule(serde_json::json!({ "installLocation": r"c:\games\steam\" })), &steam(), &mut inspector));
+        assert!(!rule_matches(&rule(serde_json::json!({ "installLocation": r"C:\Games\Stea" })), &steam(), &mut inspector));
+        // Shared folders are dropped from rules, so they match nothing.
+        assert!(BlockedAppRule::from_value(&serde_json::json!({ "installLocation": r"C:\Program Files" })).is_none());
+    }
+
+    #[test]
+    fn matches_by_hash() {
+        let mut inspector = FakeFileInspector::default();
+        let by_hash = rule(serde_json::json!({ "sha256": STEAM_HASH.to_uppercase() }));
+        assert!(!rule_matches(&by_hash, &steam(), &mut inspector));
+
+        inspector.hashes.insert(STEAM_EXE.to_string(), STEAM_HASH.to_string());
+        assert!(rule_matches(&by_hash, &steam(), &mut inspector));
+
+        let no_path = ProcessInfo::new(11, "renamed.exe", None);
+        assert!(!rule_matches(&by_hash, &no_path, &mut inspector));
+    }
+
+    #[test]
+    fn matches_by_publisher_and_product() {
+        let mut inspector = FakeFileInspector::default();
+        let by_publisher = rule(serde_json::json!({ "publisher": "Valve Corp." }));
+        let by_product = rule(serde_json::json!({ "publisher": "Valve Corp.", "productName": "Steam" }));
+
+        inspector.signatures.insert(STEAM_EXE.to_string(), signed(Some("VALVE CORP."), Some("Steam")));
+        assert!(rule_matches(&by_publisher, &steam(), &mut inspector));
+        assert!(rule_matches(&by_product, &steam(), &mut inspector));
+
+        inspector.signatures.insert(STEAM_EXE.to_string(), signed(Some("Valve Corp."), Some("Steam VR")));
+        assert!(rule_matches(&by_publisher, &steam(), &mut inspector));
+        assert!(!rule_matches(&by_product, &steam(), &mut inspector));
+
+        // An invalid signature carries no publisher.
+        inspector.signatures.insert(STEAM_EXE.to_string(), signed(None, Some("Steam")));
+        assert!(!rule_matches(&by_product, &steam(), &mut inspector));
+    }
+
+    #[test]
+    fn kill_ranks_above_suspend() {
+        let suspend = rule(serde_json::json!({ "processName": "steam.exe", "enforcement": "suspend" }));
+        let kill = rule(serde_json::json!({ "processName": "steam.exe", "enforcement": "kill" }));
+        assert!(suspend.allows_enforcement_change(EnforcementMode::Kill, None));
+        assert!(!kill.allows_enforcement_change(EnforcementMode::Suspend, None));
+        assert!(!suspend.allows_enforcement_change(EnforcementMode::OverlayThenKill, Some(1)));
+    }
+
+    #[test]
+    fn covers_matches_of_needs_every_match_field() {
+        let old = rule(serde_json::json!({
+            "processName": "steam.exe",
+            "installLocation": r"C:\Games\Steam",
+            "sha256": STEAM_HASH,
+            "publisher": "Valve Corp.",
+            "productName": "Steam",
+        }));
+        let wider = rule(serde_json::json!({
+            "processName": "Steam",
+            "installLocation": r"c:\games\",
+            "sha256": STEAM_HASH,
+            "publisher": "VALVE CORP.",
+        }));
+        assert!(wider.covers_matches_of(&old));
+        assert!(!old.covers_matches_of(&wider));
+
+        assert!(!rule(serde_json::json!("steam.exe")).covers_matches_of(&old));
+        let other_dir = rule(serde_json::json!({ "processName": "steam.exe", "installLocation": r"C:\Games\Epic", "sha256": STEAM_HASH, "publisher": "Valve Corp." }));
+        assert!(!other_dir.covers_matches_of(&old));
+        let other_product = rule(serde_json::json!({ "processName": "steam.exe", "installLocation": r"C:\Games\Steam", "sha256": STEAM_HASH, "publisher": "Valve Corp.", "productName": "Steam VR" }));
+        assert!(!other_product.covers_matches_of(&old));
+    }
+
+    #[test]
+    fn index_retries_processes_with_pending_lookups() {
+        let mut inspector = FakeFileInspector::default();
+        inspector.pending.insert(STEAM_EXE.to_string());
+        let rules = vec![rule(serde_json::json!({ "sha256": STEAM_HASH }))];
+        let started = vec![ProcessEvent::Started(steam())];
+
+        let mut index = BlockedProcessIndex::default();
+        index.update(rules.clone(), &[], &[], &mut inspector);
+        index.update(rules.clone(), &started, &[steam()], &mut inspector);
+        assert!(index.blocked().is_empty());
+
+        inspector.pending.clear();
+        inspector.hashes.insert(STEAM_EXE.to_string(), STEAM_HASH.to_string());
+        index.update(rules.clone(), &[], &[steam()], &mut inspector);
+        assert_eq!(index.blocked().len(), 1);
+
+        index.update(rules, &[ProcessEvent::Stopped(steam())], &[], &mut inspector);
+        assert!(index.blocked().is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use crate::blocklist_formats::{parse_blocklist, BlocklistFormat};
+
+pub struct BlockCategory {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub resource: &'static str,
+}
+
+pub const BLOCK_CATEGORIES: [BlockCategory; 4] = [
+    BlockCategory { id: "adult", name: "Adult content", resource: "resources/blocklists/adult.txt" },
+    BlockCategory { id: "gambling", name: "Gambling", resource: "resources/blocklists/gambling.txt" },
+    BlockCategory { id: "social-media", name: "Social media", resource: "resources/blocklists/social-media.txt" },
+    BlockCategory { id: "gaming", name: "Gaming", resource: "resources/blocklists/gaming.txt" },
+];
+
+pub fn find_category(id: &str) -> Option<&'static BlockCategory> {
+    BLOCK_CATEGORIES.iter().find(|c| c.id.eq_ignore_ascii_case(id.trim()))
+}
+
+pub fn load_category_domains(path: &Path) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read category list {}: {}", path.display(), e))?;
+    let parsed = parse_blocklist(&content, BlocklistFormat::Domains);
+    for rejected in parsed.rejected.iter() {
+        eprintln!(
+            "load_category_domains: {}:{} skipped '{}': {}",
+            path.display(),
+            rejected.line_number,
+            rejected.line,
+            rejected.reason
+        );
+    }
+    Ok(parsed.domains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_lists_parse_cleanly() {
+        for category in BLOCK_CATEGORIES.iter() {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(category.resource);
+            let content = fs::read_to_string(&path).unwrap();
+            let parsed = parse_blocklist(&content, BlocklistFormat::Domains);
+            assert!(parsed.rejected.is_empty(), "{}: {} rejected lines", category.id, parsed.rejected.len());
+            assert_eq!(load_category_domains(&path).unwrap(), parsed.domains);
+            assert!(!parsed.domains.is_empty(), "{}", category.id);
+        }
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let dir = std::env::temp_dir().join(format!("eagleblocker_category_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("list.txt");
+        fs::write(&path, "# EagleBlocker category: Test\r\n\r\nexample.com\r\n   \r\n# other.com\r\nExample.org\r\n").unwrap();
+
+        assert_eq!(load_category_domains(&path).unwrap(), vec!["example.com", "example.org"]);
+        assert!(load_category_domains(&dir.join("missing.txt")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_categories_by_id() {
+        assert_eq!(find_category("gambling").map(|c| c.id), Some("gambling"));
+        assert_eq!(find_category(" Social-Media ").map(|c| c.id), Some("social-media"));
+        assert!(find_category("news").is_none());
+        assert!(find_category("").is_none());
+    }
+}
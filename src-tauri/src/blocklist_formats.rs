@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use crate::domains::normalize_domain;
+
+const IGNORED_HOSTS: [&str; 6] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+];
+
+const ACCEPTED_ADBLOCK_OPTIONS: [&str; 3] = ["important", "all", "document"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlocklistFormat {
+    Hosts,
+    AdBlock,
+    Domains,
+}
+
+impl BlocklistFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_str() {
+            "hosts" => Ok(Self::Hosts),
+            "adblock" | "ublock" => Ok(Self::AdBlock),
+            "domains" | "plain" | "text" => Ok(Self::Domains),
+            other => Err(format!("unknown blocklist format '{}'", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hosts => "hosts",
+            Self::AdBlock => "adblock",
+            Self::Domains => "domains",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLine {
+    pub line_number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedBlocklist {
+    pub format: BlocklistFormat,
+    pub domains: Vec<String>,
+    pub rejected: Vec<RejectedLine>,
+    pub duplicates: usize,
+}
+
+pub fn detect_format(content: &str) -> BlocklistFormat {
+    for line in content.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('!') || line.starts_with('[') || line.starts_with("||") || line.starts_with("@@") {
+            return BlocklistFormat::AdBlock;
+        }
+        let first = line.split_whitespace().next().unwrap_or("");
+        if first.parse::<IpAddr>().is_ok() {
+            return BlocklistFormat::Hosts;
+        }
+        return BlocklistFormat::Domains;
+    }
+    BlocklistFormat::Domains
+}
+
+pub fn parse_blocklist(content: &str, format: BlocklistFormat) -> ParsedBlocklist {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut parsed = ParsedBlocklist {
+        format,
+        domains: Vec::new(),
+        rejected: Vec::new(),
+        duplicates: 0,
+    };
+
+    for (idx, raw) in content.lines().enumerate() {
+        let line = raw.trim().trim_start_matches('\u{feff}');
+        let candidates = match format {
+            BlocklistFormat::Hosts => parse_hosts_line(line),
+            BlocklistFormat::AdBlock => parse_adblock_line(line),
+            BlocklistFormat::Domains => parse_domain_line(line),
+        };
+
+        let candidates = match candidates {
+            Ok(c) => c,
+            Err(reason) => {
+                parsed.rejected.push(RejectedLine { line_number: idx + 1, line: line.to_string(), reason });
+                continue;
+            }
+        };
+
+        for candidate in candidates {
+            match normalize_domain(&candidate) {
+                Ok(domain) => {
+                    if seen.insert(domain.clone()) {
+                        parsed.domains.push(domain);
+                    } else {
+                        parsed.duplicates += 1;
+                    }
+                }
+                Err(reason) => {
+                    parsed.rejected.push(RejectedLine { line_number: idx + 1, line: line.to_string(), reason });
+                }
+            }
+        }
+    }
+
+    parsed
+}
+
+fn strip_hash_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or("").trim()
+}
+
+fn parse_hosts_line(line: &str) -> Result<Vec<String>, String> {
+    let line = strip_hash_comment(line);
+    if line.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut parts = line.split_whitespace();
+    let address = parts.next().unwrap_or("");
+    if address.parse::<IpAddr>().is_err() {
+        return Err(format!("'{}' is not an IP address", address));
+    }
+
+    Ok(parts
+        .filter(|h| !IGNORED_HOSTS.contains(&h.to_lowercase().as_str()))
+        .map(|h| h.to_string())
+        .collect())
+}
+
+fn parse_adblock_line(line: &str) -> Result<Vec<String>, String> {
+    if line.is_empty() || line.starts_with('!') || line.starts_with('[') || line.starts_with('#') {
+        return Ok(Vec::new());
+    }
+    if line.starts_with("@@") {
+        return Err("exception rules are not supported".into());
+    }
+    if line.contains("##") || line.contains("#@#") || line.contains("#?#") {
+        return Err("cosmetic rules are not supported".into());
+    }
+
+    let rule = line
+        .strip_prefix("||")
+        .ok_or_else(|| "only ||domain^ rules are supported".to_string())?;
+
+    let (pattern, options) = match rule.split_once('$') {
+        Some((p, o)) => (p, Some(o)),
+        None => (rule, None),
+    };
+
+    if let Some(options) = options {
+        let unsupported = options
+            .split(',')
+            .map(|o| o.trim().to_lowercase())
+            .find(|o| !ACCEPTED_ADBLOCK_OPTIONS.contains(&o.as_str()));
+        if let Some(option) = unsupported {
+            return Err(format!("unsupported rule option '{}'", option));
+        }
+    }
+
+    let domain = pattern.strip_suffix('^').unwrap_or(pattern);
+    if domain.contains('*') || domain.contains('/') || domain.contains('^') {
+        return Err("wildcard and path rules are not supported".into());
+    }
+
+    Ok(vec![domain.to_string()])
+}
+
+fn parse_domain_line(line: &str) -> Result<Vec<String>, String> {
+    let line = strip_hash_comment(line);
+    if line.is_empty() {
+        return Ok(Vec::new());
+    }
+    if line.split_whitespace().count() > 1 {
+        return Err("expected a single domain per line".into());
+    }
+    Ok(vec![line.to_string()])
+}
+
+pub fn render_blocklist(domains: &[String], format: BlocklistFormat) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    match format {
+        BlocklistFormat::Hosts => {
+            lines.push("# Exported by EagleBlocker".to_string());
+            lines.extend(domains.iter().map(|d| format!("0.0.0.0 {}", d)));
+        }
+        BlocklistFormat::AdBlock => {
+            lines.push("! Title: EagleBlocker export".to_string());
+            lines.extend(domains.iter().map(|d| format!("||{}^", d)));
+        }
+        BlocklistFormat::Domains => {
+            lines.extend(domains.iter().cloned());
+        }
+    }
+
+    let mut out = lines.join("\r\n");
+    out.push_str("\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domains(list: &[&str]) -> Vec<String> {
+        list.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_format_from_first_rule() {
+        assert_eq!(detect_format("# comment\n\n0.0.0.0 ads.example.com\n"), BlocklistFormat::Hosts);
+        assert_eq!(detect_format("::1 ads.example.com"), BlocklistFormat::Hosts);
+        assert_eq!(detect_format("! Title: list\n||ads.example.com^\n"), BlocklistFormat::AdBlock);
+        assert_eq!(detect_format("[Adblock Plus 2.0]\n"), BlocklistFormat::AdBlock);
+        assert_eq!(detect_format("# comment\nads.example.com\n"), BlocklistFormat::Domains);
+        assert_eq!(detect_format(""), BlocklistFormat::Domains);
+    }
+
+    #[test]
+    fn parses_hosts_lines() {
+        let content = "# header\n127.0.0.1 localhost\n0.0.0.0 Ads.Example.com tracker.example.com # inline\n\nnot-an-ip example.org\n0.0.0.0 ads.example.com\n";
+        let parsed = parse_blocklist(content, BlocklistFormat::Hosts);
+
+        assert_eq!(parsed.domains, domains(&["ads.example.com", "tracker.example.com"]));
+        assert_eq!(parsed.duplicates, 1);
+        assert_eq!(parsed.rejected.len(), 1);
+        assert_eq!(parsed.rejected[0].line_number, 5);
+    }
+
+    #[test]
+    fn parses_adblock_rules() {
+        let content = "! comment\n[Adblock Plus 2.0]\n||ads.example.com^\n||tracker.example.com^$important\n@@||allowed.example.com^\nexample.com##.banner\n||*.example.net^\n||video.example.com^$third-party\n/banner/*\n";
+        let parsed = parse_blocklist(content, BlocklistFormat::AdBlock);
+
+        assert_eq!(parsed.domains, domains(&["ads.example.com", "tracker.example.com"]));
+        let rejected: Vec<usize> = parsed.rejected.iter().map(|r| r.line_number).collect();
+        assert_eq!(rejected, vec![5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn parses_plain_domains() {
+        let content = "\u{feff}ads.example.com\n# comment\nhttps://tracker.example.com/path\ntwo domains.com\nbad_domain!\n";
+        let parsed = parse_blocklist(content, BlocklistFormat::Domains);
+
+        assert_eq!(parsed.domains, domains(&["ads.example.com", "tracker.example.com"]));
+        let rejected: Vec<usize> = parsed.rejected.iter().map(|r| r.line_number).collect();
+        assert_eq!(rejected, vec![4, 5]);
+    }
+
+    #[test]
+    fn render_then_parse_round_trips() {
+        let list = domains(&["ads.example.com", "tracker.example.com", "xn--bcher-kva.example"]);
+        for format in [BlocklistFormat::Hosts, BlocklistFormat::AdBlock, BlocklistFormat::Domains] {
+            let rendered = render_blocklist(&list, format);
+            assert_eq!(detect_format(&rendered), format, "{}", format.name());
+
+            let parsed = parse_blocklist(&rendered, format);
+            assert_eq!(parsed.domains, list, "{}", format.name());
+            assert!(parsed.rejected.is_empty(), "{}", format.name());
+        }
+    }
+}
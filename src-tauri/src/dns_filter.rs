@@ -0,0 +1,465 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+const QTYPE_A: u16 = 1;
+const QTYPE_AAAA: u16 = 28;
+const RCODE_NXDOMAIN: u8 = 3;
+const BLOCKED_TTL: u32 = 60;
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Threads forwarding queries upstream, and how many queries may wait for
+/// one. Queries beyond that are dropped; the client retries.
+const FORWARD_WORKERS: usize = 8;
+const FORWARD_QUEUE_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum DnsRule {
+    Exact(String),
+    Suffix(String),
+    Wildcard(Regex),
+    Pattern(Regex),
+}
+
+impl DnsRule {
+    pub fn parse(rule_type: &str, pattern: &str) -> Result<Self, String> {
+        let domain = pattern.trim().trim_end_matches('.').to_lowercase();
+        if domain.is_empty() {
+            return Err("empty dns rule".into());
+        }
+
+        match rule_type.trim().to_lowercase().as_str() {
+            "exact" => Ok(Self::Exact(domain)),
+            "suffix" => Ok(Self::Suffix(domain.trim_start_matches('.').to_string())),
+            "wildcard" => wildcard_to_regex(&domain).map(Self::Wildcard),
+            "regex" => Regex::new(&format!("(?i){}", pattern.trim()))
+                .map(Self::Pattern)
+                .map_err(|e| format!("invalid regex rule '{}': {}", pattern, e)),
+            other => Err(format!("unknown dns rule type '{}'", other)),
+        }
+    }
+
+    pub fn infer(pattern: &str) -> Result<Self, String> {
+        let p = pattern.trim();
+        if p.len() > 2 && p.starts_with('/') && p.ends_with('/') {
+            Self::parse("regex", &p[1..p.len() - 1])
+        } else if p.contains('*') || p.contains('?') {
+            Self::parse("wildcard", p)
+        } else if p.starts_with('.') {
+            Self::parse("suffix", p)
+        } else {
+            Self::parse("exact", p)
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        if let Some(s) = value.as_str() {
+            return Self::infer(s);
+        }
+        let pattern = value
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("dns rule without pattern: {}", value))?;
+        match value.get("type").and_then(|v| v.as_str()) {
+            Some(rule_type) => Self::parse(rule_type, pattern),
+            None => Self::infer(pattern),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(d) => name == d,
+            Self::Suffix(d) => name == d || name.ends_with(&format!(".{}", d)),
+            Self::Wildcard(re) | Self::Pattern(re) => re.is_match(name),
+        }
+    }
+}
+
+fn wildcard_to_regex(pattern: &str) -> Result<Regex, String> {
+    let mut re = String::from("^");
+    for ch in pattern.chars() {
+        match ch {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| format!("invalid wildcard rule '{}': {}", pattern, e))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DnsRuleSet {
+    rules: Vec<DnsRule>,
+}
+
+impl DnsRuleSet {
+    pub fn new(rules: Vec<DnsRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn from_block_data(block_data: &Map<String, Value>) -> (Self, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+        if let Some(arr) = block_data.get("dnsRules").and_then(|v| v.as_array()) {
+            for item in arr {
+                match DnsRule::from_value(item) {
+                    Ok(rule) => rules.push(rule),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        (Self::new(rules), errors)
+    }
+
+    pub fn is_blocked(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_lowercase();
+        self.rules.iter().any(|r| r.matches(&name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMode {
+    NxDomain,
+    ZeroAddress,
+}
+
+impl BlockMode {
+    pub fn from_name(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "zero" | "0.0.0.0" | "zero-address" => Self::ZeroAddress,
+            _ => Self::NxDomain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+    end: usize,
+}
+
+pub fn parse_question(packet: &[u8]) -> Option<DnsQuestion> {
+    if packet.len() < 12 || u16::from_be_bytes([packet[4], packet[5]]) == 0 {
+        return None;
+    }
+
+    let mut pos = 12;
+    let mut labels: Vec<String> = Vec::new();
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_lowercase());
+        pos += 1 + len;
+    }
+
+    let qtype = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]);
+    packet.get(pos + 2..pos + 4)?;
+
+    Some(DnsQuestion { name: labels.join("."), qtype, end: pos + 4 })
+}
+
+pub fn build_blocked_response(query: &[u8], question: &DnsQuestion, mode: BlockMode) -> Vec<u8> {
+    let rdata: Option<Vec<u8>> = match (mode, question.qtype) {
+        (BlockMode::ZeroAddress, QTYPE_A) => Some(vec![0; 4]),
+        (BlockMode::ZeroAddress, QTYPE_AAAA) => Some(vec![0; 16]),
+        _ => None,
+    };
+    let rcode = if mode == BlockMode::NxDomain { RCODE_NXDOMAIN } else { 0 };
+
+    let mut out = Vec::with_capacity(question.end + 32);
+    out.extend_from_slice(&query[0..2]);
+    out.push(0x80 | (query[2] & 0x79));
+    out.push(0x80 | rcode);
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&(rdata.is_some() as u16).to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&query[12..question.end]);
+
+    if let Some(rdata) = rdata {
+        out.extend_from_slice(&[0xC0, 0x0C]);
+        out.extend_from_slice(&question.qtype.to_be_bytes());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&BLOCKED_TTL.to_be_bytes());
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(&rdata);
+    }
+
+    out
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DnsFilterConfig {
+    pub bind: SocketAddr,
+    pub upstream: SocketAddr,
+    pub mode: BlockMode,
+}
+
+pub struct DnsFilterHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+    rules: Arc<RwLock<DnsRuleSet>>,
+    local_addr: SocketAddr,
+    upstream: SocketAddr,
+}
+
+impl DnsFilterHandle {
+    pub fn start(config: DnsFilterConfig, rules: DnsRuleSet) -> Result<Self, String> {
+        let socket = UdpSocket::bind(config.bind)
+            .map_err(|e| format!("failed to bind dns filter on {}: {}", config.bind, e))?;
+        socket
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(|e| format!("failed to set dns filter timeout: {}", e))?;
+        let local_addr = socket.local_addr().map_err(|e| e.to_string())?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let rules = Arc::new(RwLock::new(rules));
+
+        let stop_clone = stop.clone();
+        let rules_clone = rules.clone();
+        let thread = thread::spawn(move || serve(socket, config, rules_clone, stop_clone));
+
+        println!("dns_filter: listening on {} (upstream {})", local_addr, config.upstream);
+        Ok(Self { stop, thread: Some(thread), rules, local_addr, upstream: config.upstream })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn upstream(&self) -> SocketAddr {
+        self.upstream
+    }
+
+    pub fn set_rules(&self, rules: DnsRuleSet) {
+        if let Ok(mut guard) = self.rules.write() {
+            *guard = rules;
+        }
+    }
+
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+type ForwardJob = (Vec<u8>, SocketAddr);
+
+fn forward_worker(socket: UdpSocket, upstream: SocketAddr, jobs: Arc<Mutex<Receiver<ForwardJob>>>) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+        let (query, client) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        match forward_query(&query, upstream) {
+            Ok(response) => {
+                let _ = socket.send_to(&response, client);
+            }
+            Err(e) => eprintln!("dns_filter: forward to {} failed: {}", upstream, e),
+        }
+    }
+}
+
+fn serve(socket: UdpSocket, config: DnsFilterConfig, rules: Arc<RwLock<DnsRuleSet>>, stop: Arc<AtomicBool>) {
+    let (jobs, rx) = mpsc::sync_channel::<ForwardJob>(FORWARD_QUEUE_DEPTH);
+    let rx = Arc::new(Mutex::new(rx));
+    let mut workers = Vec::new();
+    for _ in 0..FORWARD_WORKERS {
+        match socket.try_clone() {
+            Ok(reply_socket) => {
+                let rx = rx.clone();
+                let upstream = config.upstream;
+                workers.push(thread::spawn(move || forward_worker(reply_socket, upstream, rx)));
+            }
+            Err(e) => eprintln!("dns_filter: failed to clone socket: {}", e),
+        }
+    }
+
+    let mut buf = [0u8; 4096];
+    while !stop.load(Ordering::SeqCst) {
+        let (len, client) = match socket.recv_from(&mut buf) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        let query = buf[..len].to_vec();
+
+        if let Some(question) = parse_question(&query) {
+            let blocked = rules.read().map(|r| r.is_blocked(&question.name)).unwrap_or(false);
+            if blocked {
+                println!("dns_filter: blocked {} (type {})", question.name, question.qtype);
+                let response = build_blocked_response(&query, &question, config.mode);
+                let _ = socket.send_to(&response, client);
+                continue;
+            }
+        }
+
+        match jobs.try_send((query, client)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => eprintln!("dns_filter: forward queue full, dropping query from {}", client),
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    // Workers hold clones of the listening socket; wait for them so the
+    // port is free once `stop` returns.
+    drop(jobs);
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+pub fn forward_query(query: &[u8], upstream: SocketAddr) -> Result<Vec<u8>, String> {
+    let bind: SocketAddr = if upstream.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(bind).map_err(|e| format!("bind failed: {}", e))?;
+    socket.set_read_timeout(Some(UPSTREAM_TIMEOUT)).map_err(|e| e.to_string())?;
+    socket.send_to(query, upstream).map_err(|e| format!("send failed: {}", e))?;
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).map_err(|e| format!("no response: {}", e))?;
+        if from == upstream && len >= 2 && buf[0..2] == query[0..2] {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes());
+        packet
+    }
+
+    /// An upstream that answers every query with its own question and the
+    /// QR bit set, tagged with 0xEE so tests can tell it was forwarded.
+    fn stub_upstream() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                let mut reply = buf[..len].to_vec();
+                reply[2] |= 0x80;
+                reply.push(0xEE);
+                let _ = socket.send_to(&reply, from);
+            }
+        });
+        addr
+    }
+
+    fn start_filter(mode: BlockMode) -> DnsFilterHandle {
+        let rules = DnsRuleSet::new(vec![DnsRule::infer(".blocked.test").unwrap()]);
+        let config = DnsFilterConfig { bind: "127.0.0.1:0".parse().unwrap(), upstream: stub_upstream(), mode };
+        DnsFilterHandle::start(config, rules).unwrap()
+    }
+
+    fn ask(filter: &DnsFilterHandle, packet: &[u8]) -> Vec<u8> {
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        client.send_to(packet, filter.local_addr()).unwrap();
+        let mut buf = [0u8; 512];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn parses_question() {
+        let packet = query(7, "WWW.Example.com", QTYPE_AAAA);
+        let question = parse_question(&packet).unwrap();
+        assert_eq!(question.name, "www.example.com");
+        assert_eq!(question.qtype, QTYPE_AAAA);
+        assert_eq!(question.end, packet.len());
+
+        assert!(parse_question(&packet[..packet.len() - 1]).is_none());
+        assert!(parse_question(&packet[..10]).is_none());
+    }
+
+    #[test]
+    fn answers_blocked_names_with_nxdomain() {
+        let filter = start_filter(BlockMode::NxDomain);
+        let packet = query(0x1234, "ads.blocked.test", QTYPE_A);
+        let response = ask(&filter, &packet);
+
+        assert_eq!(response[0..2], [0x12, 0x34]);
+        assert_eq!(response[2] & 0x80, 0x80);
+        assert_eq!(response[3] & 0x0F, RCODE_NXDOMAIN);
+        assert_eq!(parse_question(&response).unwrap().name, "ads.blocked.test");
+        assert_eq!(response, build_blocked_response(&packet, &parse_question(&packet).unwrap(), BlockMode::NxDomain));
+        filter.stop();
+    }
+
+    #[test]
+    fn answers_blocked_names_with_zero_address() {
+        let filter = start_filter(BlockMode::ZeroAddress);
+        let packet = query(9, "blocked.test", QTYPE_A);
+        let response = ask(&filter, &packet);
+
+        assert_eq!(response[3] & 0x0F, 0);
+        assert_eq!(response[6..8], [0, 1]);
+        assert_eq!(response[response.len() - 6..], [0, 4, 0, 0, 0, 0]);
+        filter.stop();
+    }
+
+    #[test]
+    fn forwards_other_names_upstream() {
+        let filter = start_filter(BlockMode::NxDomain);
+        let response = ask(&filter, &query(42, "example.com", QTYPE_A));
+
+        assert_eq!(response[0..2], 42u16.to_be_bytes());
+        assert_eq!(response.last(), Some(&0xEE));
+        assert_eq!(parse_question(&response).unwrap().name, "example.com");
+        filter.stop();
+    }
+
+    #[test]
+    fn forwards_more_queries_than_workers() {
+        let filter = start_filter(BlockMode::NxDomain);
+        let clients: Vec<UdpSocket> = (0..FORWARD_WORKERS * 4)
+            .map(|i| {
+                let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+                client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                client.send_to(&query(i as u16, "example.com", QTYPE_A), filter.local_addr()).unwrap();
+                client
+            })
+            .collect();
+
+        for (i, client) in clients.iter().enumerate() {
+            let mut buf = [0u8; 512];
+            let (len, _) = client.recv_from(&mut buf).unwrap();
+            assert_eq!(buf[0..2], (i as u16).to_be_bytes());
+            assert_eq!(buf[len - 1], 0xEE);
+        }
+        filter.stop();
+    }
+}
@@ -0,0 +1,451 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv6Addr};
+
+pub const DEFAULT_STRICT_PROVIDER: &str = "cleanbrowsing-family";
+pub const DEFAULT_LENIENT_PROVIDER: &str = "opendns-familyshield";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsTransport {
+    Plain,
+    Doh,
+    Dot,
+}
+
+impl DnsTransport {
+    pub fn from_name(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "doh" | "https" => Self::Doh,
+            "dot" | "tls" => Self::Dot,
+            _ => Self::Plain,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Doh => "doh",
+            Self::Dot => "dot",
+        }
+    }
+}
+
+/// A protective DNS service. `strictness` runs from 1 (adult content only)
+/// to 3 (also blocks mixed content, proxies and VPNs).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsProvider {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub strictness: u8,
+    pub ipv4_primary: String,
+    #[serde(default)]
+    pub ipv4_secondary: Option<String>,
+    #[serde(default)]
+    pub ipv6_primary: Option<String>,
+    #[serde(default)]
+    pub ipv6_secondary: Option<String>,
+    #[serde(default)]
+    pub doh_template: Option<String>,
+    #[serde(default)]
+    pub dot_host: Option<String>,
+}
+
+impl DnsProvider {
+    #[allow(clippy::too_many_arguments)]
+    fn builtin(
+        id: &str,
+        name: &str,
+        description: &str,
+        strictness: u8,
+        ipv4: (&str, &str),
+        ipv6: (&str, &str),
+        doh_template: &str,
+        dot_host: Option<&str>,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            strictness,
+            ipv4_primary: ipv4.0.to_string(),
+            ipv4_secondary: Some(ipv4.1.to_string()),
+            ipv6_primary: Some(ipv6.0.to_string()),
+            ipv6_secondary: Some(ipv6.1.to_string()),
+            doh_template: Some(doh_template.to_string()),
+            dot_host: dot_host.map(|h| h.to_string()),
+        }
+    }
+
+    pub fn ipv4_servers(&self) -> Vec<&str> {
+        std::iter::once(self.ipv4_primary.as_str())
+            .chain(self.ipv4_secondary.as_deref())
+            .collect()
+    }
+
+    pub fn ipv6_servers(&self) -> Vec<&str> {
+        self.ipv6_primary.iter().chain(self.ipv6_secondary.iter()).map(|s| s.as_str()).collect()
+    }
+
+    /// IPv6 resolvers on the interface that do not belong to this provider.
+    pub fn unapproved_ipv6(&self, configured: &[Ipv6Addr]) -> Vec<Ipv6Addr> {
+        let approved: Vec<Ipv6Addr> = self.ipv6_servers().iter().filter_map(|s| s.parse().ok()).collect();
+        configured.iter().filter(|ip| !approved.contains(ip)).copied().collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() || self.name.trim().is_empty() {
+            return Err("dns provider needs an id and a name".into());
+        }
+        if !(1..=3).contains(&self.strictness) {
+            return Err(format!("strictness must be between 1 and 3, got {}", self.strictness));
+        }
+
+        let v4 = std::iter::once(Some(&self.ipv4_primary)).chain([self.ipv4_secondary.as_ref()]);
+        for ip in v4.flatten() {
+            match ip.parse::<IpAddr>() {
+                Ok(IpAddr::V4(_)) => {}
+                _ => return Err(format!("'{}' is not an IPv4 address", ip)),
+            }
+        }
+
+        for ip in [self.ipv6_primary.as_ref(), self.ipv6_secondary.as_ref()].into_iter().flatten() {
+            match ip.parse::<IpAddr>() {
+                Ok(IpAddr::V6(_)) => {}
+                _ => return Err(format!("'{}' is not an IPv6 address", ip)),
+            }
+        }
+
+        if let Some(template) = self.doh_template.as_ref() {
+            if !template.starts_with("https://") {
+                return Err(format!("DoH template '{}' must start with https://", template));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// IPv4 resolvers on the interface that do not belong to this provider.
+    pub fn unapproved_ipv4(&self, configured: &[String]) -> Vec<String> {
+        let approved = self.ipv4_servers();
+        configured.iter().filter(|s| !approved.contains(&s.as_str())).cloned().collect()
+    }
+
+    /// True when every IPv4 server of this provider is among `configured`
+    /// and nothing else is; Windows may send lookups to any listed server.
+    pub fn matches_servers(&self, configured: &[String]) -> bool {
+        self.ipv4_servers().iter().all(|s| configured.iter().any(|c| c == s)) && self.unapproved_ipv4(configured).is_empty()
+    }
+
+    /// True when this provider publishes an endpoint for `transport`.
+    pub fn supports(&self, transport: DnsTransport) -> bool {
+        match transport {
+            DnsTransport::Plain => true,
+            DnsTransport::Doh => self.doh_template.is_some(),
+            DnsTransport::Dot => self.dot_host.is_some(),
+        }
+    }
+
+    /// Builds the netsh command that registers `server` for encrypted lookups.
+    /// Any previous registration is deleted first so re-enabling stays idempotent.
+    /// Returns `None` for plain DNS and for transports the provider lacks, so
+    /// the server is still set, just without encryption.
+    pub fn encryption_command(&self, server: &str, transport: DnsTransport) -> Option<String> {
+        let target = match transport {
+            DnsTransport::Plain => return None,
+            DnsTransport::Doh => format!("dohtemplate={}", self.doh_template.as_ref()?),
+            DnsTransport::Dot => format!("dothost={}:853", self.dot_host.as_ref()?),
+        };
+
+        Some(format!(
+            "(netsh dns delete encryption server={server} >nul 2>&1 & netsh dns add encryption server={server} {target} autoupgrade=yes udpfallback=no)",
+            server = server,
+            target = target
+        ))
+    }
+}
+
+pub fn builtin_providers() -> Vec<DnsProvider> {
+    vec![
+        DnsProvider::builtin(
+            "cleanbrowsing-family",
+            "CleanBrowsing Family",
+            "Blocks adult content, proxies, VPNs and mixed content; enforces SafeSearch.",
+            3,
+            ("185.228.168.168", "185.228.169.168"),
+            ("2a0d:2a00:1::", "2a0d:2a00:2::"),
+            "https://doh.cleanbrowsing.org/doh/family-filter/",
+            Some("family-filter-dns.cleanbrowsing.org"),
+        ),
+        DnsProvider::builtin(
+            "cleanbrowsing-adult",
+            "CleanBrowsing Adult",
+            "Blocks adult content only; mixed-content sites such as Reddit stay reachable.",
+            1,
+            ("185.228.168.10", "185.228.169.11"),
+            ("2a0d:2a00:1::1", "2a0d:2a00:2::1"),
+            "https://doh.cleanbrowsing.org/doh/adult-filter/",
+            Some("adult-filter-dns.cleanbrowsing.org"),
+        ),
+        DnsProvider::builtin(
+            "opendns-familyshield",
+            "OpenDNS FamilyShield",
+            "Blocks adult content, proxies and phishing with Cisco's preconfigured filter.",
+            2,
+            ("208.67.222.123", "208.67.220.123"),
+            ("2620:119:35::123", "2620:119:53::123"),
+            "https://doh.familyshield.opendns.com/dns-query",
+            None,
+        ),
+        DnsProvider::builtin(
+            "cloudflare-families",
+            "Cloudflare for Families",
+            "Blocks malware and adult content.",
+            2,
+            ("1.1.1.3", "1.0.0.3"),
+            ("2606:4700:4700::1113", "2606:4700:4700::1003"),
+            "https://family.cloudflare-dns.com/dns-query",
+            Some("family.cloudflare-dns.com"),
+        ),
+        DnsProvider::builtin(
+            "adguard-family",
+            "AdGuard Family Protection",
+            "Blocks ads, trackers and adult content; enforces SafeSearch.",
+            3,
+            ("94.140.14.15", "94.140.15.16"),
+            ("2a10:50c0::bad1:ff", "2a10:50c0::bad2:ff"),
+            "https://family.adguard-dns.com/dns-query",
+            Some("family.adguard-dns.com"),
+        ),
+    ]
+}
+
+pub fn find_provider<'a>(providers: &'a [DnsProvider], id: &str) -> Option<&'a DnsProvider> {
+    providers.iter().find(|p| p.id.eq_ignore_ascii_case(id.trim()))
+}
+
+/// The strictness a provider can be trusted to have. Custom providers
+/// declare their own, so they count as the least strict.
+pub fn trusted_strictness(provider: &DnsProvider) -> u8 {
+    find_provider(&builtin_providers(), &provider.id).map(|builtin| builtin.strictness).unwrap_or(0)
+}
+
+/// Whether moving from `current` (`None` when protective DNS is off) to
+/// `next` keeps DNS at least as strict, so it needs no delay.
+pub fn is_dns_switch_tightening(current: Option<&DnsProvider>, next: &DnsProvider) -> bool {
+    match current {
+        None => true,
+        Some(current) if current.id == next.id => true,
+        Some(current) => trusted_strictness(next) > 0 && trusted_strictness(next) >= trusted_strictness(current),
+    }
+}
+
+pub fn parse_encryption_transport(netsh_output: &str) -> DnsTransport {
+    let mut transport = DnsTransport::Plain;
+    for line in netsh_output.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((k, v)) => (k.trim().to_lowercase(), v.trim()),
+            None => continue,
+        };
+        if value.is_empty() {
+            continue;
+        }
+        if key.contains("over-https") {
+            transport = DnsTransport::Doh;
+        } else if key.contains("over-tls") && transport == DnsTransport::Plain {
+            transport = DnsTransport::Dot;
+        }
+    }
+    transport
+}
+
+/// Collects every address listed in `netsh interface ipv4|ipv6 show dnsservers`
+/// output. IPv6 zone suffixes such as `%12` are dropped.
+pub fn parse_dns_server_addresses(netsh_output: &str) -> Vec<IpAddr> {
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    for token in netsh_output.split_whitespace() {
+        let token = token.split('%').next().unwrap_or(token);
+        if let Ok(ip) = token.parse::<IpAddr>() {
+            if !addresses.contains(&ip) {
+                addresses.push(ip);
+            }
+        }
+    }
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn provider(id: &str) -> DnsProvider {
+        find_provider(&builtin_providers(), id).unwrap().clone()
+    }
+
+    fn custom(strictness: u8) -> DnsProvider {
+        DnsProvider {
+            id: "my-dns".to_string(),
+            name: "My DNS".to_string(),
+            description: String::new(),
+            strictness,
+            ipv4_primary: "10.0.0.53".to_string(),
+            ipv4_secondary: None,
+            ipv6_primary: Some("fd00::53".to_string()),
+            ipv6_secondary: None,
+            doh_template: None,
+            dot_host: None,
+        }
+    }
+
+    #[test]
+    fn builtin_registry_is_valid() {
+        let providers = builtin_providers();
+        for provider in providers.iter() {
+            assert_eq!(provider.validate(), Ok(()), "{}", provider.id);
+            assert_eq!(provider.ipv4_servers().len(), 2, "{}", provider.id);
+            assert_eq!(provider.ipv6_servers().len(), 2, "{}", provider.id);
+            assert_eq!(trusted_strictness(provider), provider.strictness);
+            let same_id = providers.iter().filter(|p| p.id == provider.id).count();
+            assert_eq!(same_id, 1, "{}", provider.id);
+        }
+        assert!(find_provider(&providers, DEFAULT_STRICT_PROVIDER).is_some());
+        assert!(find_provider(&providers, DEFAULT_LENIENT_PROVIDER).is_some());
+        assert_eq!(find_provider(&providers, " AdGuard-Family ").map(|p| p.id.as_str()), Some("adguard-family"));
+        assert!(find_provider(&providers, "google").is_none());
+    }
+
+    #[test]
+    fn validates_custom_providers() {
+        assert_eq!(custom(2).validate(), Ok(()));
+
+        type Mutation = fn(&mut DnsProvider);
+        let cases: Vec<(&str, Mutation)> = vec![
+            ("blank id", |p| p.id = "  ".to_string()),
+            ("blank name", |p| p.name = String::new()),
+            ("strictness 0", |p| p.strictness = 0),
+            ("strictness 4", |p| p.strictness = 4),
+            ("bad ipv4", |p| p.ipv4_primary = "10.0.0".to_string()),
+            ("ipv6 as ipv4", |p| p.ipv4_secondary = Some("fd00::53".to_string())),
+            ("hostname as ipv4", |p| p.ipv4_primary = "dns.example.com".to_string()),
+            ("ipv4 as ipv6", |p| p.ipv6_primary = Some("10.0.0.53".to_string())),
+            ("bad ipv6", |p| p.ipv6_secondary = Some("fd00:::53".to_string())),
+            ("plain http doh", |p| p.doh_template = Some("http://dns.example.com/dns-query".to_string())),
+        ];
+        for (name, mutate) in cases {
+            let mut provider = custom(2);
+            mutate(&mut provider);
+            assert!(provider.validate().is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn custom_providers_are_not_trusted_with_their_strictness() {
+        let claims_strict = custom(3);
+        assert_eq!(claims_strict.validate(), Ok(()));
+        assert_eq!(trusted_strictness(&claims_strict), 0);
+
+        let lenient = provider("cleanbrowsing-adult");
+        assert!(!is_dns_switch_tightening(Some(&lenient), &claims_strict));
+        assert!(is_dns_switch_tightening(None, &claims_strict));
+        assert!(is_dns_switch_tightening(Some(&claims_strict), &claims_strict));
+
+        // A custom provider reusing a built-in id is judged by the built-in.
+        let mut impostor = custom(3);
+        impostor.id = "cleanbrowsing-adult".to_string();
+        assert_eq!(trusted_strictness(&impostor), 1);
+    }
+
+    #[test]
+    fn switch_tightening_follows_builtin_strictness() {
+        let family = provider("cleanbrowsing-family");
+        let adult = provider("cleanbrowsing-adult");
+        let opendns = provider("opendns-familyshield");
+        let cloudflare = provider("cloudflare-families");
+
+        assert!(is_dns_switch_tightening(Some(&adult), &family));
+        assert!(!is_dns_switch_tightening(Some(&family), &adult));
+        assert!(is_dns_switch_tightening(Some(&opendns), &cloudflare));
+        assert!(!is_dns_switch_tightening(Some(&family), &custom(3)));
+    }
+
+    #[test]
+    fn matches_exactly_the_provider_servers() {
+        let cleanbrowsing = provider("cleanbrowsing-family");
+        let cases: [(&[&str], bool); 5] = [
+            (&["185.228.168.168", "185.228.169.168"], true),
+            (&["185.228.169.168", "185.228.168.168"], true),
+            (&["185.228.168.168"], false),
+            (&[], false),
+            (&["185.228.168.10", "185.228.169.11"], false),
+        ];
+        for (configured, expected) in cases {
+            assert_eq!(cleanbrowsing.matches_servers(&servers(configured)), expected, "{:?}", configured);
+        }
+
+        // Without a secondary, the primary alone is enough.
+        assert!(custom(2).matches_servers(&servers(&["10.0.0.53"])));
+    }
+
+    #[test]
+    fn lists_unapproved_ipv6_resolvers() {
+        let cleanbrowsing = provider("cleanbrowsing-family");
+        let configured: Vec<Ipv6Addr> = ["2a0d:2a00:1::", "2001:4860:4860::8888", "2a0d:2a00:2::"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(cleanbrowsing.unapproved_ipv6(&configured), vec!["2001:4860:4860::8888".parse::<Ipv6Addr>().unwrap()]);
+        assert!(cleanbrowsing.unapproved_ipv6(&configured[..1]).is_empty());
+        // A provider without IPv6 servers approves none.
+        let mut v4_only = custom(2);
+        v4_only.ipv6_primary = None;
+        assert_eq!(v4_only.unapproved_ipv6(&configured).len(), 3);
+    }
+
+    #[test]
+    fn builds_encryption_commands() {
+        let cleanbrowsing = provider("cleanbrowsing-family");
+        assert_eq!(cleanbrowsing.encryption_command("185.228.168.168", DnsTransport::Plain), None);
+        assert_eq!(
+            cleanbrowsing.encryption_command("185.228.168.168", DnsTransport::Doh),
+            Some(
+                "(netsh dns delete encryption server=185.228.168.168 >nul 2>&1 & netsh dns add encryption server=185.228.168.168 dohtemplate=https://doh.cleanbrowsing.org/doh/family-filter/ autoupgrade=yes udpfallback=no)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            cleanbrowsing.encryption_command("2a0d:2a00:1::", DnsTransport::Dot),
+            Some(
+                "(netsh dns delete encryption server=2a0d:2a00:1:: >nul 2>&1 & netsh dns add encryption server=2a0d:2a00:1:: dothost=family-filter-dns.cleanbrowsing.org:853 autoupgrade=yes udpfallback=no)"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(custom(2).encryption_command("10.0.0.53", DnsTransport::Plain), None);
+    }
+
+    #[test]
+    fn missing_encryption_endpoint_falls_back_to_plain() {
+        let opendns = provider("opendns-familyshield");
+        assert!(opendns.supports(DnsTransport::Plain));
+        assert!(!opendns.supports(DnsTransport::Dot));
+        assert_eq!(opendns.encryption_command("208.67.222.123", DnsTransport::Dot), None);
+
+        let custom = custom(2);
+        assert!(!custom.supports(DnsTransport::Doh));
+        assert_eq!(custom.encryption_command("10.0.0.53", DnsTransport::Doh), None);
+    }
+
+    #[test]
+    fn extra_ipv4_resolvers_do_not_match() {
+        let cleanbrowsing = provider("cleanbrowsing-family");
+        let configured = servers(&["185.228.168.168", "185.228.169.168", "8.8.8.8"]);
+
+        assert!(!cleanbrowsing.matches_servers(&configured));
+        assert_eq!(cleanbrowsing.unapproved_ipv4(&configured), vec!["8.8.8.8"]);
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::app_rules::EnforcementMode;
+
+/// How long to wait before trying again after a kill or suspend failed,
+/// e.g. because the process runs elevated.
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnforcementAction {
+    /// `kill_in` is set when the overlay is counting down to a kill.
+    ShowOverlay { kill_in: Option<Duration> },
+    Kill,
+    Suspend,
+    Resume,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedProcess {
+    pub pid: u32,
+    pub mode: EnforcementMode,
+    pub kill_after: Duration,
+}
+
+/// Decides what to do with each blocked process from one tick to the next.
+/// Time is passed in so the decisions can be replayed without sleeping.
+#[derive(Default)]
+pub struct EnforcementTracker {
+    first_seen: HashMap<u32, Instant>,
+    last_attempt: HashMap<u32, Instant>,
+    // Suspended PID -> the blocked process whose tree it was suspended with.
+    suspended: HashMap<u32, u32>,
+}
+
+impl EnforcementTracker {
+    pub fn plan(&mut self, blocked: &[BlockedProcess], running: &HashSet<u32>, now: Instant) -> Vec<(u32, EnforcementAction)> {
+        let blocked_pids: HashSet<u32> = blocked.iter().map(|b| b.pid).collect();
+        self.first_seen.retain(|pid, _| blocked_pids.contains(pid));
+        self.last_attempt.retain(|pid, _| blocked_pids.contains(pid));
+        self.suspended.retain(|pid, _| running.contains(pid));
+
+        let mut actions = Vec::new();
+        for process in blocked {
+            let first_seen = *self.first_seen.entry(process.pid).or_insert(now);
+            let can_attempt = match self.last_attempt.get(&process.pid) {
+                Some(t) => now.duration_since(*t) >= RETRY_INTERVAL,
+                None => true,
+            };
+
+            match process.mode {
+                EnforcementMode::Overlay => actions.push((process.pid, EnforcementAction::ShowOverlay { kill_in: None })),
+                EnforcementMode::OverlayThenKill => {
+                    let elapsed = now.duration_since(first_seen);
+                    if elapsed >= process.kill_after && can_attempt {
+                        actions.push((process.pid, EnforcementAction::Kill));
+                    } else {
+                        let kill_in = process.kill_after.saturating_sub(elapsed);
+                        actions.push((process.pid, EnforcementAction::ShowOverlay { kill_in: Some(kill_in) }));
+                    }
+                }
+                EnforcementMode::Kill => {
+                    if can_attempt {
+                        actions.push((process.pid, EnforcementAction::Kill));
+                    }
+                }
+                EnforcementMode::Suspend => {
+                    if !self.suspended.contains_key(&process.pid) && can_attempt {
+                        actions.push((process.pid, EnforcementAction::Suspend));
+                    }
+                }
+            }
+        }
+
+        // Unblocked (rule removed or schedule ended) or the blocked root exited.
+        let mut resumes: Vec<u32> = self
+            .suspended
+            .iter()
+            .filter(|(_, root)| !blocked_pids.contains(root))
+            .map(|(pid, _)| *pid)
+            .collect();
+        resumes.sort_unstable();
+        actions.extend(resumes.into_iter().map(|pid| (pid, EnforcementAction::Resume)));
+
+        actions
+    }
+
+    pub fn record_attempt(&mut self, pid: u32, now: Instant) {
+        self.last_attempt.insert(pid, now);
+    }
+
+    pub fn mark_suspended(&mut self, root: u32, pids: &[u32]) {
+        for pid in pids {
+            self.suspended.insert(*pid, root);
+        }
+    }
+
+    pub fn mark_resumed(&mut self, pid: u32) {
+        self.suspended.remove(&pid);
+    }
+
+    /// Everything still suspended, e.g. to resume it when protection stops.
+    pub fn take_suspended(&mut self) -> Vec<u32> {
+        self.suspended.drain().map(|(pid, _)| pid).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(pid: u32, mode: EnforcementMode) -> BlockedProcess {
+        BlockedProcess { pid, mode, kill_after: Duration::from_secs(30) }
+    }
+
+    fn running(pids: &[u32]) -> HashSet<u32> {
+        pids.iter().copied().collect()
+    }
+
+    #[test]
+    fn overlay_counts_down_to_kill() {
+        let mut tracker = EnforcementTracker::default();
+        let start = Instant::now();
+        let list = [blocked(1, EnforcementMode::OverlayThenKill)];
+
+        assert_eq!(
+            tracker.plan(&list, &running(&[1]), start),
+            vec![(1, EnforcementAction::ShowOverlay { kill_in: Some(Duration::from_secs(30)) })]
+        );
+        assert_eq!(
+            tracker.plan(&list, &running(&[1]), start + Duration::from_secs(20)),
+            vec![(1, EnforcementAction::ShowOverlay { kill_in: Some(Duration::from_secs(10)) })]
+        );
+        assert_eq!(tracker.plan(&list, &running(&[1]), start + Duration::from_secs(30)), vec![(1, EnforcementAction::Kill)]);
+
+        // A process that left the blocked list starts a fresh countdown.
+        tracker.plan(&[], &running(&[1]), start + Duration::from_secs(31));
+        assert_eq!(
+            tracker.plan(&list, &running(&[1]), start + Duration::from_secs(32)),
+            vec![(1, EnforcementAction::ShowOverlay { kill_in: Some(Duration::from_secs(30)) })]
+        );
+    }
+
+    #[test]
+    fn failed_kills_are_retried_after_the_interval() {
+        let mut tracker = EnforcementTracker::default();
+        let start = Instant::now();
+        let list = [blocked(1, EnforcementMode::Kill)];
+
+        assert_eq!(tracker.plan(&list, &running(&[1]), start), vec![(1, EnforcementAction::Kill)]);
+        tracker.record_attempt(1, start);
+        assert!(tracker.plan(&list, &running(&[1]), start + RETRY_INTERVAL / 2).is_empty());
+        assert_eq!(tracker.plan(&list, &running(&[1]), start + RETRY_INTERVAL), vec![(1, EnforcementAction::Kill)]);
+
+        // Past the deadline, a throttled kill keeps the overlay up instead.
+        let list = [blocked(2, EnforcementMode::OverlayThenKill)];
+        let later = start + Duration::from_secs(30);
+        tracker.plan(&list, &running(&[2]), start);
+        tracker.record_attempt(2, later);
+        assert_eq!(
+            tracker.plan(&list, &running(&[2]), later + Duration::from_secs(1)),
+            vec![(2, EnforcementAction::ShowOverlay { kill_in: Some(Duration::ZERO) })]
+        );
+    }
+
+    #[test]
+    fn suspended_trees_resume_when_the_rule_goes() {
+        let mut tracker = EnforcementTracker::default();
+        let now = Instant::now();
+        let list = [blocked(1, EnforcementMode::Suspend)];
+
+        assert_eq!(tracker.plan(&list, &running(&[1, 2]), now), vec![(1, EnforcementAction::Suspend)]);
+        tracker.record_attempt(1, now);
+        tracker.mark_suspended(1, &[1, 2]);
+        assert!(tracker.plan(&list, &running(&[1, 2]), now + RETRY_INTERVAL).is_empty());
+
+        assert_eq!(
+            tracker.plan(&[], &running(&[1, 2]), now + RETRY_INTERVAL),
+            vec![(1, EnforcementAction::Resume), (2, EnforcementAction::Resume)]
+        );
+        tracker.mark_resumed(1);
+        tracker.mark_resumed(2);
+        assert!(tracker.plan(&[], &running(&[1, 2]), now + RETRY_INTERVAL).is_empty());
+        assert!(tracker.take_suspended().is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app_rules::BlockedAppRule;
+
+/// Key stamped on the `blockedApps` entries a session adds, holding its
+/// `started_at`, so ending it does not touch a rule for the same app that
+/// the user added meanwhile.
+pub const FOCUS_SESSION_MARKER: &str = "focusSession";
+
+/// An extra set of apps and websites blocked for the length of a focus
+/// session, stored under `focusProfiles` in blockData.json. Unlike a
+/// `BlockProfile`, it adds to the current lists instead of replacing them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusProfile {
+    /// `blockedApps`-style entries: process-name strings or rule objects.
+    #[serde(default)]
+    pub apps: Vec<Value>,
+    #[serde(default)]
+    pub websites: Vec<String>,
+}
+
+/// A running session, stored under `focusSession` in blockData.json so it
+/// outlives a restart. Only what the session itself added is recorded, so
+/// ending it leaves rules that were already there alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSession {
+    pub profile: String,
+    /// Epoch milliseconds; also identifies the session.
+    pub started_at: u64,
+    pub ends_at: u64,
+    #[serde(default)]
+    pub added_apps: Vec<String>,
+    #[serde(default)]
+    pub added_websites: Vec<String>,
+}
+
+impl FocusSession {
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.ends_at
+    }
+
+    pub fn remaining_ms(&self, now_ms: u64) -> u64 {
+        self.ends_at.saturating_sub(now_ms)
+    }
+}
+
+/// The profile's app rules that are not blocked already. Legacy string
+/// entries in the profile become full rules.
+pub fn apps_to_add(profile: &FocusProfile, blocked: &[BlockedAppRule]) -> Vec<BlockedAppRule> {
+    let mut added: Vec<BlockedAppRule> = Vec::new();
+    for rule in profile.apps.iter().filter_map(BlockedAppRule::from_value) {
+        if rule.process_name.is_empty() {
+            continue;
+        }
+        let known = blocked
+            .iter()
+            .chain(added.iter())
+            .any(|r| r.process_name.eq_ignore_ascii_case(&rule.process_name));
+        if !known {
+            added.push(rule);
+        }
+    }
+    added
+}
+
+/// Stamps `rule`, an app rule added by session `session_id`, as its own.
+pub fn mark_added_rule(rule: &mut Value, session_id: u64) {
+    if let Value::Object(fields) = rule {
+        fields.insert(FOCUS_SESSION_MARKER.to_string(), Value::from(session_id));
+    }
+}
+
+/// Drops the `blockedApps` entries stamped by session `session_id` and
+/// returns how many there were.
+pub fn remove_added_rules(apps: &mut Vec<Value>, session_id: u64) -> usize {
+    let before = apps.len();
+    apps.retain(|item| item.get(FOCUS_SESSION_MARKER).and_then(|v| v.as_u64()) != Some(session_id));
+    before - apps.len()
+}
+
+/// The profile's websites that are not blocked already.
+pub fn websites_to_add(profile: &FocusProfile, blocked: &[String]) -> Vec<String> {
+    let mut added: Vec<String> = Vec::new();
+    for site in profile.websites.iter().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()) {
+        if !blocked.iter().chain(added.iter()).any(|d| d.eq_ignore_ascii_case(&site)) {
+            added.push(site);
+        }
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn focus(apps: Value, websites: &[&str]) -> FocusProfile {
+        FocusProfile {
+            apps: serde_json::from_value(apps).unwrap(),
+            websites: websites.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn rules(apps: Value) -> Vec<BlockedAppRule> {
+        apps.as_array().unwrap().iter().filter_map(BlockedAppRule::from_value).collect()
+    }
+
+    fn names(rules: &[BlockedAppRule]) -> Vec<&str> {
+        rules.iter().map(|r| r.process_name.as_str()).collect()
+    }
+
+    #[test]
+    fn adds_only_apps_not_blocked_yet() {
+        let profile = focus(
+            json!(["Steam.exe", { "processName": "discord.exe", "sha256": "0f343b0931126a20f133d67c2b018a3b5a7ad6d4a4b2f5a1e3c4d5e6f7a8b9c0" }, "slack.exe", "SLACK.EXE"]),
+            &[],
+        );
+        let blocked = rules(json!([{ "processName": "steam.exe" }, "Discord.exe"]));
+
+        let added = apps_to_add(&profile, &blocked);
+        assert_eq!(names(&added), vec!["slack.exe"]);
+        // Legacy string entries become full rules.
+        assert_eq!(added[0].display_name, "slack.exe");
+    }
+
+    #[test]
+    fn skips_app_entries_without_a_process_name() {
+        let profile = focus(json!([{ "publisher": "Valve Corp." }, "  ", 42, "  game.exe  "]), &[]);
+        assert_eq!(names(&apps_to_add(&profile, &[])), vec!["game.exe"]);
+    }
+
+    #[test]
+    fn adds_only_websites_not_blocked_yet() {
+        let profile = focus(json!([]), &["Reddit.com", " youtube.com ", "", "REDDIT.COM", "news.ycombinator.com"]);
+        let blocked = vec!["YouTube.com".to_string()];
+        assert_eq!(websites_to_add(&profile, &blocked), vec!["reddit.com", "news.ycombinator.com"]);
+        assert!(websites_to_add(&focus(json!([]), &[]), &blocked).is_empty());
+    }
+
+    #[test]
+    fn expires_at_its_end_time() {
+        let session = FocusSession { profile: "Deep work".into(), started_at: 1_000, ends_at: 61_000, ..Default::default() };
+        assert!(!session.is_expired(1_000));
+        assert_eq!(session.remaining_ms(1_000), 60_000);
+        assert!(!session.is_expired(60_999));
+        assert_eq!(session.remaining_ms(60_999), 1);
+        assert!(session.is_expired(61_000));
+        assert_eq!(session.remaining_ms(61_000), 0);
+        assert_eq!(session.remaining_ms(u64::MAX), 0);
+    }
+
+    #[test]
+    fn removes_only_the_rules_the_session_added() {
+        let mut added = json!({ "processName": "steam.exe", "displayName": "steam.exe" });
+        mark_added_rule(&mut added, 1_000);
+        let mut apps = vec![
+            json!("discord.exe"),
+            added,
+            // Added by the user during the session, for the same app.
+            json!({ "processName": "steam.exe", "displayName": "Steam" }),
+            json!({ "processName": "game.exe", "focusSession": 999 }),
+        ];
+
+        assert_eq!(remove_added_rules(&mut apps, 1_000), 1);
+        assert_eq!(
+            apps,
+            vec![
+                json!("discord.exe"),
+                json!({ "processName": "steam.exe", "displayName": "Steam" }),
+                json!({ "processName": "game.exe", "focusSession": 999 }),
+            ]
+        );
+        assert_eq!(remove_added_rules(&mut apps, 1_000), 0);
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(any(test, not(windows)))]
+use std::sync::Mutex;
+
+/// Titles that mean the user is trying to reach something that can
+/// disable or uninstall the blocker.
+pub const FLAGGED_WINDOW_TITLES: [&str; 4] = [
+    "eagleblocker uninstall",
+    "task manager",
+    "task scheduler",
+    "apps & features",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForegroundWindow {
+    pub title: String,
+    pub pid: u32,
+    pub exe_path: Option<String>,
+}
+
+impl ForegroundWindow {
+    pub fn flagged_title(&self) -> Option<&'static str> {
+        let title = self.title.to_lowercase();
+        FLAGGED_WINDOW_TITLES.iter().copied().find(|flagged| title.contains(flagged))
+    }
+}
+
+pub trait ForegroundWindowSource: Send + Sync {
+    /// `Ok(None)` when no window has focus, e.g. on the lock screen.
+    fn foreground_window(&self) -> Result<Option<ForegroundWindow>, String>;
+}
+
+/// The foreground window if its title is flagged.
+pub fn flagged_foreground_window(source: &dyn ForegroundWindowSource) -> Result<Option<ForegroundWindow>, String> {
+    Ok(source.foreground_window()?.filter(|w| w.flagged_title().is_some()))
+}
+
+#[cfg(windows)]
+pub struct NativeForegroundWindow;
+
+#[cfg(windows)]
+impl ForegroundWindowSource for NativeForegroundWindow {
+    fn foreground_window(&self) -> Result<Option<ForegroundWindow>, String> {
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetForegroundWindow, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId,
+        };
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() {
+                return Ok(None);
+            }
+
+            let len = GetWindowTextLengthW(hwnd).max(0) as usize;
+            let mut buf = vec![0u16; len + 1];
+            let copied = GetWindowTextW(hwnd, &mut buf).max(0) as usize;
+            let title = String::from_utf16_lossy(&buf[..copied]);
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32));
+
+            let exe_path = if pid == 0 { None } else { process_image_path(pid) };
+            Ok(Some(ForegroundWindow { title, pid, exe_path }))
+        }
+    }
+}
+
+#[cfg(windows)]
+pub fn process_image_path(pid: u32) -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buf = vec![0u16; 1024];
+        let mut size = buf.len() as u32;
+        let result = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut size);
+        let _ = CloseHandle(handle);
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buf[..size as usize]))
+    }
+}
+
+/// Returns whatever window was last set; the stand-in on non-Windows builds.
+#[cfg(any(test, not(windows)))]
+#[derive(Default)]
+pub struct FakeForegroundWindow {
+    window: Mutex<Option<ForegroundWindow>>,
+}
+
+#[cfg(test)]
+impl FakeForegroundWindow {
+    pub fn set(&self, window: Option<ForegroundWindow>) {
+        if let Ok(mut guard) = self.window.lock() {
+            *guard = window;
+        }
+    }
+}
+
+#[cfg(any(test, not(windows)))]
+impl ForegroundWindowSource for FakeForegroundWindow {
+    fn foreground_window(&self) -> Result<Option<ForegroundWindow>, String> {
+        self.window.lock().map(|g| g.clone()).map_err(|e| e.to_string())
+    }
+}
+
+pub fn default_source() -> Box<dyn ForegroundWindowSource> {
+    #[cfg(windows)]
+    {
+        Box::new(NativeForegroundWindow)
+    }
+    #[cfg(not(windows))]
+    {
+        Box::new(FakeForegroundWindow::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str) -> Option<ForegroundWindow> {
+        Some(ForegroundWindow { title: title.to_string(), pid: 42, exe_path: None })
+    }
+
+    #[test]
+    fn flags_titles_case_insensitively() {
+        let source = FakeForegroundWindow::default();
+        source.set(window("TASK MANAGER"));
+        let flagged = flagged_foreground_window(&source).unwrap().unwrap();
+        assert_eq!(flagged.flagged_title(), Some("task manager"));
+        assert_eq!(flagged.pid, 42);
+
+        source.set(window("Settings - Apps & Features"));
+        assert!(flagged_foreground_window(&source).unwrap().is_some());
+    }
+
+    #[test]
+    fn ignores_other_windows() {
+        let source = FakeForegroundWindow::default();
+        source.set(window("Inbox - Mail"));
+        assert!(flagged_foreground_window(&source).unwrap().is_none());
+
+        source.set(window(""));
+        assert!(flagged_foreground_window(&source).unwrap().is_none());
+    }
+
+    #[test]
+    fn no_foreground_window_is_not_flagged() {
+        let source = FakeForegroundWindow::default();
+        assert!(flagged_foreground_window(&source).unwrap().is_none());
+
+        source.set(window("Task Scheduler"));
+        source.set(None);
+        assert!(flagged_foreground_window(&source).unwrap().is_none());
+    }
+}
@@ -0,0 +1,379 @@
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+pub const SECTION_BEGIN: &str = "# BEGIN EagleBlocker";
+pub const SECTION_END: &str = "# END EagleBlocker";
+
+/// The SafeSearch lines versions before the managed section appended to the
+/// end of the hosts file.
+pub const LEGACY_LINES: [&str; 7] = [
+    "216.239.38.120 www.google.com",
+    "216.239.38.120 google.com",
+    "204.79.197.220 bing.com",
+    "204.79.197.220 www.bing.com",
+    "213.180.193.56 yandex.ru",
+    "213.180.204.92 www.yandex.com",
+    "127.0.0.1 yandex.com/images",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostsEntry {
+    pub address: String,
+    pub host: String,
+}
+
+impl HostsEntry {
+    pub fn new(address: &str, host: &str) -> Self {
+        Self {
+            address: address.trim().to_string(),
+            host: host.trim().to_lowercase(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        format!("{} {}", self.address, self.host)
+    }
+
+    /// Whether the entry points its host at nowhere (loopback or `0.0.0.0`).
+    pub fn is_block(&self) -> bool {
+        match self.address.parse::<IpAddr>() {
+            Ok(ip) => ip.is_loopback() || ip.is_unspecified(),
+            Err(_) => false,
+        }
+    }
+}
+
+enum ParseState {
+    Before,
+    Inside,
+    After,
+}
+
+/// The hosts file split into the lines we don't own and the entries of the
+/// `# BEGIN EagleBlocker` / `# END EagleBlocker` section. Only the section is
+/// ever rewritten; every other line is rendered back byte-for-byte.
+#[derive(Debug, Clone)]
+pub struct HostsFile {
+    path: PathBuf,
+    prefix: Vec<String>,
+    entries: Vec<HostsEntry>,
+    suffix: Vec<String>,
+    line_ending: &'static str,
+}
+
+impl HostsFile {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let content = if path.exists() {
+            fs::read_to_string(&path).map_err(|e| format!("failed to read hosts file {}: {}", path.display(), e))?
+        } else {
+            String::new()
+        };
+        Ok(Self::parse(path, &content))
+    }
+
+    pub fn parse(path: impl Into<PathBuf>, content: &str) -> Self {
+        let line_ending = if content.is_empty() || content.contains("\r\n") { "\r\n" } else { "\n" };
+
+        let mut prefix = Vec::new();
+        let mut entries = Vec::new();
+        let mut suffix = Vec::new();
+        let mut pending_entries: Vec<HostsEntry> = Vec::new();
+        let mut state = ParseState::Before;
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+            match state {
+                ParseState::Before => {
+                    if trimmed == SECTION_BEGIN {
+                        state = ParseState::Inside;
+                    } else {
+                        prefix.push(line.to_string());
+                    }
+                }
+                ParseState::Inside => {
+                    if trimmed == SECTION_END {
+                        entries.append(&mut pending_entries);
+                        state = ParseState::After;
+                    } else {
+                        pending_entries.extend(parse_entries(trimmed));
+                    }
+                }
+                ParseState::After => {
+                    if trimmed == SECTION_BEGIN {
+                        state = ParseState::Inside;
+                    } else {
+                        suffix.push(line.to_string());
+                    }
+                }
+            }
+        }
+
+        // A section missing its end marker (e.g. a truncated write) runs to
+        // the end of the file. Its entries stay ours, so the next render
+        // closes the section instead of leaving them behind unmanaged.
+        if let ParseState::Inside = state {
+            entries.append(&mut pending_entries);
+        }
+
+        let mut hosts = Self {
+            path: path.into(),
+            prefix,
+            entries: Vec::new(),
+            suffix,
+            line_ending,
+        };
+        for entry in entries {
+            hosts.add(&entry.address, &entry.host);
+        }
+        hosts
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, address: &str, host: &str) -> bool {
+        let probe = HostsEntry::new(address, host);
+        self.entries.contains(&probe)
+    }
+
+    pub fn add(&mut self, address: &str, host: &str) -> bool {
+        let entry = HostsEntry::new(address, host);
+        if entry.address.is_empty() || entry.host.is_empty() || self.entries.contains(&entry) {
+            return false;
+        }
+        self.entries.push(entry);
+        true
+    }
+
+    pub fn remove(&mut self, address: &str, host: &str) -> bool {
+        let probe = HostsEntry::new(address, host);
+        let before = self.entries.len();
+        self.entries.retain(|e| *e != probe);
+        self.entries.len() != before
+    }
+
+    /// Removes every entry of our section matching `predicate` and returns them.
+    pub fn remove_matching(&mut self, predicate: impl Fn(&HostsEntry) -> bool) -> Vec<HostsEntry> {
+        let (removed, kept): (Vec<HostsEntry>, Vec<HostsEntry>) = self.entries.drain(..).partition(|e| predicate(e));
+        self.entries = kept;
+        removed
+    }
+
+    /// Moves the lines older versions appended outside any section (exact
+    /// matches of `LEGACY_LINES`) into it. Anything else outside the section,
+    /// including a user's own block of the same host, is left alone.
+    pub fn adopt_legacy_lines(&mut self) -> Vec<HostsEntry> {
+        let mut adopted: Vec<HostsEntry> = Vec::new();
+        for lines in [&mut self.prefix, &mut self.suffix] {
+            lines.retain(|line| {
+                let trimmed = line.trim();
+                if !LEGACY_LINES.contains(&trimmed) {
+                    return true;
+                }
+                adopted.extend(parse_entries(trimmed));
+                false
+            });
+        }
+        for entry in adopted.iter() {
+            self.add(&entry.address, &entry.host);
+        }
+        adopted
+    }
+
+    /// The expected entries that are absent from our section, in order.
+    pub fn missing_entries(&self, expected: &[HostsEntry]) -> Vec<HostsEntry> {
+        let mut missing: Vec<HostsEntry> = Vec::new();
+        for entry in expected {
+            if !self.entries.contains(entry) && !missing.contains(entry) {
+                missing.push(entry.clone());
+            }
+        }
+        missing
+    }
+
+    /// Adds back every missing expected entry and returns what was restored.
+    pub fn restore_missing(&mut self, expected: &[HostsEntry]) -> Vec<HostsEntry> {
+        let missing = self.missing_entries(expected);
+        for entry in missing.iter() {
+            self.add(&entry.address, &entry.host);
+        }
+        missing
+    }
+
+    /// Block entries are written ahead of the rest of the section, so a
+    /// blocked host stays blocked even when something else (e.g. SafeSearch)
+    /// also pins it; the resolver uses the first match.
+    pub fn render(&self) -> String {
+        let mut lines: Vec<String> = self.prefix.clone();
+
+        if !self.entries.is_empty() {
+            let (blocks, others): (Vec<&HostsEntry>, Vec<&HostsEntry>) = self.entries.iter().partition(|e| e.is_block());
+            lines.push(SECTION_BEGIN.to_string());
+            lines.extend(blocks.iter().chain(others.iter()).map(|e| e.render()));
+            lines.push(SECTION_END.to_string());
+        }
+
+        lines.extend(self.suffix.iter().cloned());
+
+        if lines.is_empty() {
+            return String::new();
+        }
+
+        let mut out = lines.join(self.line_ending);
+        out.push_str(self.line_ending);
+        out
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.render()).map_err(|e| format!("failed to write hosts file {}: {}", path.display(), e))
+    }
+}
+
+fn parse_entries(line: &str) -> Vec<HostsEntry> {
+    let without_comment = line.split('#').next().unwrap_or("").trim();
+    let mut parts = without_comment.split_whitespace();
+    let address = match parts.next() {
+        Some(a) => a,
+        None => return Vec::new(),
+    };
+    parts.map(|host| HostsEntry::new(address, host)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "# Copyright (c) Microsoft Corp.\r\n127.0.0.1 localhost\r\n# BEGIN EagleBlocker\r\n127.0.0.1 example.com\r\n::1 example.com\r\n# END EagleBlocker\r\n10.0.0.5 nas.local   # my nas\r\n";
+
+    #[test]
+    fn parse_and_render_round_trip() {
+        let hosts = HostsFile::parse("hosts", HOSTS);
+        assert!(hosts.contains("127.0.0.1", "example.com"));
+        assert!(hosts.contains("::1", "EXAMPLE.com"));
+        assert!(!hosts.contains("127.0.0.1", "localhost"));
+        assert_eq!(hosts.render(), HOSTS);
+    }
+
+    #[test]
+    fn keeps_lf_line_endings() {
+        let content = "127.0.0.1 localhost\n# BEGIN EagleBlocker\n127.0.0.1 example.com\n# END EagleBlocker\n";
+        assert_eq!(HostsFile::parse("hosts", content).render(), content);
+    }
+
+    #[test]
+    fn add_remove_and_contains() {
+        let mut hosts = HostsFile::parse("hosts", "127.0.0.1 localhost\n");
+        assert!(hosts.add("127.0.0.1", "Example.com "));
+        assert!(!hosts.add("127.0.0.1", "example.com"));
+        assert!(!hosts.add("127.0.0.1", ""));
+        assert!(hosts.contains("127.0.0.1", "example.com"));
+        assert_eq!(
+            hosts.render(),
+            "127.0.0.1 localhost\n# BEGIN EagleBlocker\n127.0.0.1 example.com\n# END EagleBlocker\n"
+        );
+
+        assert!(hosts.remove("127.0.0.1", "example.com"));
+        assert!(!hosts.remove("127.0.0.1", "example.com"));
+        assert!(!hosts.contains("127.0.0.1", "example.com"));
+        assert_eq!(hosts.render(), "127.0.0.1 localhost\n");
+    }
+
+    #[test]
+    fn renders_blocks_before_other_entries() {
+        let mut hosts = HostsFile::parse("hosts", "");
+        hosts.add("216.239.38.120", "www.youtube.com");
+        hosts.add("2001:4860:4802:32::78", "www.youtube.com");
+        hosts.add("127.0.0.1", "www.youtube.com");
+        hosts.add("::1", "www.youtube.com");
+
+        let rendered = HostsFile::parse("hosts", &hosts.render()).render();
+        assert_eq!(
+            rendered,
+            "# BEGIN EagleBlocker\r\n127.0.0.1 www.youtube.com\r\n::1 www.youtube.com\r\n216.239.38.120 www.youtube.com\r\n2001:4860:4802:32::78 www.youtube.com\r\n# END EagleBlocker\r\n"
+        );
+    }
+
+    #[test]
+    fn unterminated_section_stays_managed() {
+        let content = "127.0.0.1 localhost\n# BEGIN EagleBlocker\n127.0.0.1 example.com\n127.0.0.1 other.com\n";
+        let mut hosts = HostsFile::parse("hosts", content);
+        assert!(hosts.contains("127.0.0.1", "example.com"));
+
+        assert!(hosts.add("127.0.0.1", "new.com"));
+        assert!(hosts.remove("127.0.0.1", "other.com"));
+        let rendered = hosts.render();
+        assert_eq!(
+            rendered,
+            "127.0.0.1 localhost\n# BEGIN EagleBlocker\n127.0.0.1 example.com\n127.0.0.1 new.com\n# END EagleBlocker\n"
+        );
+        assert_eq!(HostsFile::parse("hosts", &rendered).render(), rendered);
+    }
+
+    #[test]
+    fn adopts_only_legacy_lines() {
+        let content = "127.0.0.1 localhost\n216.239.38.120 google.com\n216.239.38.120 google.com keep.me\n# 216.239.38.120 google.com\n127.0.0.1 yandex.com/images\n10.0.0.5 youtube.com\n";
+        let mut hosts = HostsFile::parse("hosts", content);
+        let adopted = hosts.adopt_legacy_lines();
+
+        assert_eq!(
+            adopted,
+            vec![HostsEntry::new("216.239.38.120", "google.com"), HostsEntry::new("127.0.0.1", "yandex.com/images")]
+        );
+        assert!(hosts.adopt_legacy_lines().is_empty());
+        hosts.remove_matching(|_| true);
+        assert_eq!(
+            hosts.render(),
+            "127.0.0.1 localhost\n216.239.38.120 google.com keep.me\n# 216.239.38.120 google.com\n10.0.0.5 youtube.com\n"
+        );
+    }
+
+    #[test]
+    fn user_block_outside_section_survives_restore_and_unblock() {
+        let content = "127.0.0.1 localhost\n127.0.0.1 example.com\n";
+        let mut hosts = HostsFile::parse("hosts", content);
+
+        // Integrity pass: example.com is blocked by us too, so our copy is
+        // written into the section next to the user's line.
+        let expected = vec![HostsEntry::new("127.0.0.1", "example.com"), HostsEntry::new("::1", "example.com")];
+        assert!(hosts.adopt_legacy_lines().is_empty());
+        assert_eq!(hosts.restore_missing(&expected), expected);
+
+        // Unblock removes only our entries.
+        let removed = hosts.remove_matching(|e| e.host == "example.com");
+        assert_eq!(removed, expected);
+        assert_eq!(hosts.render(), content);
+    }
+
+    #[test]
+    fn restore_missing_only_touches_the_managed_section() {
+        let dir = std::env::temp_dir().join(format!("eagleblocker_hosts_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+
+        // Someone deleted one of our entries and added a line of their own.
+        let tampered = "# user header\r\n10.0.0.5 nas.local\r\n# BEGIN EagleBlocker\r\n127.0.0.1 example.com\r\n# END EagleBlocker\r\n192.168.1.1 router.local\r\n";
+        fs::write(&path, tampered).unwrap();
+
+        let expected = vec![
+            HostsEntry::new("127.0.0.1", "example.com"),
+            HostsEntry::new("::1", "example.com"),
+            HostsEntry::new("127.0.0.1", "other.com"),
+        ];
+        let mut hosts = HostsFile::load(&path).unwrap();
+        let restored = hosts.restore_missing(&expected);
+        assert_eq!(restored, expected[1..].to_vec());
+        hosts.save_to(&path).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            written,
+            "# user header\r\n10.0.0.5 nas.local\r\n# BEGIN EagleBlocker\r\n127.0.0.1 example.com\r\n::1 example.com\r\n127.0.0.1 other.com\r\n# END EagleBlocker\r\n192.168.1.1 router.local\r\n"
+        );
+        assert!(HostsFile::load(&path).unwrap().restore_missing(&expected).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,7 +9,6 @@ use tauri::api::path::app_data_dir;
 use serde_json::{Map, Value}; 
 use std::process::Command;
 use std::str;
-use regex::Regex;
 use std::io::Write;
 use std::env;
 use std::sync::{Mutex, Arc, atomic::{AtomicBool, Ordering}};
@@ -23,7 +22,6 @@ use serde_json::json;
 use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
 use winreg::RegKey;
 use std::collections::HashMap;
-use std::fs::File;
 use std::time::{SystemTime, UNIX_EPOCH};
 use once_cell::sync::Lazy;
 use std::collections::hash_map::DefaultHasher;
@@ -38,12 +36,73 @@ use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINF
 #[cfg(windows)]
 use windows::Win32::System::SystemInformation::GetTickCount64;
 
+mod app_rules;
+mod block_categories;
+mod blocklist_formats;
 mod browser_detector;
+mod dns_filter;
+mod dns_providers;
+mod domains;
+mod enforcement;
+mod focus_session;
+mod foreground_window;
+mod hosts_file;
+mod network_events;
+mod network_interfaces;
+mod process_control;
+mod process_monitor;
+mod profiles;
+mod safe_search;
+mod schedules;
+mod usage_quota;
+use app_rules::{is_specific_install_dir, BlockedAppRule, EnforcementMode, BlockedProcessIndex, SystemFileInspector};
+use block_categories::{find_category, load_category_domains, BlockCategory, BLOCK_CATEGORIES};
+use blocklist_formats::{detect_format, parse_blocklist, render_blocklist, BlocklistFormat, ParsedBlocklist};
 use browser_detector::BrowserDetector;
+use dns_filter::{BlockMode, DnsFilterConfig, DnsFilterHandle, DnsRule, DnsRuleSet};
+use dns_providers::{
+    builtin_providers, find_provider, is_dns_switch_tightening, parse_dns_server_addresses, parse_encryption_transport, trusted_strictness, DnsProvider,
+    DnsTransport,
+    DEFAULT_LENIENT_PROVIDER, DEFAULT_STRICT_PROVIDER,
+};
+use enforcement::{BlockedProcess, EnforcementAction, EnforcementTracker};
+use focus_session::{apps_to_add, mark_added_rule, remove_added_rules, websites_to_add, FocusProfile, FocusSession};
+use domains::{expand_subdomain_variants, normalize_domain, DEFAULT_SUBDOMAIN_VARIANTS};
+use foreground_window::{default_source, flagged_foreground_window, ForegroundWindowSource};
+use hosts_file::{HostsEntry, HostsFile};
+use network_interfaces::connected_interface_names;
+use process_control::{kill_process_tree, resume_process, suspend_process_tree, TreeOutcome};
+use profiles::{is_at_least_as_strict, BlockProfile, DEFAULT_PROFILE};
+use process_monitor::{matches_process_name, ProcessInfo, ProcessMonitor, ProcessSource, SysinfoSource};
+use safe_search::{
+    all_safe_search_hosts, build_entries, find_engine, refresh_addresses, settle_pending_removal, ResolvedTarget,
+    SafeSearchResolver, SystemResolver, PENDING_SAFE_SEARCH_REMOVAL_KEY, SAFE_SEARCH_ENGINES, SAFE_SEARCH_TARGETS,
+};
+use schedules::{covers, format_schedules, is_active, next_boundary, parse_schedule_spec, Schedule, SyncBackoff};
+use usage_quota::{is_exhausted, remaining, usage_key, UsageLedger, UsageSampler};
 static BROWSER_DETECTOR: Lazy<BrowserDetector> = Lazy::new(|| BrowserDetector::new());
+static FOREGROUND_WINDOW: Lazy<Box<dyn ForegroundWindowSource>> = Lazy::new(default_source);
 static OVERLAY_OPEN: Lazy<std::sync::atomic::AtomicBool> = Lazy::new(|| std::sync::atomic::AtomicBool::new(false));
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 const DELAY_TIMEOUT_KEY: &str = "delayTimeOutAtTimeOfChange";
+const SCHEDULE_SETTING_PREFIX: &str = "applySchedule::";
+const PENDING_SCHEDULES_KEY: &str = "pendingSchedules";
+const QUOTA_SETTING_PREFIX: &str = "applyQuota::";
+const PENDING_QUOTAS_KEY: &str = "pendingQuotas";
+const USAGE_FILE: &str = "appUsage.json";
+const USAGE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+const FOCUS_SESSION_KEY: &str = "focusSession";
+// Focus profiles only add blocks for a session and never replace the
+// lists; they are kept apart from the block profiles under PROFILES_KEY,
+// which swap the whole set of lists, so a name can mean one of each.
+const FOCUS_PROFILES_KEY: &str = "focusProfiles";
+const FOCUS_END_SETTING_PREFIX: &str = "endFocusSession::";
+const FOCUS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const PROFILES_KEY: &str = "profiles";
+const ACTIVE_PROFILE_KEY: &str = "activeProfile";
+const PROFILE_SWITCH_SETTING_PREFIX: &str = "switchProfile::";
+const PENDING_PROFILE_SWITCH_KEY: &str = "pendingProfileSwitch";
+const PROFILE_SWITCH_RETRY_INTERVAL: Duration = Duration::from_secs(60);
 
 static DNS_SAFE_CACHE: Lazy<Mutex<Option<(bool, std::time::Instant)>>> =Lazy::new(|| Mutex::new(None));
 const DNS_SAFE_TTL: std::time::Duration = std::time::Duration::from_secs(120);
@@ -78,18 +137,61 @@ static UNSUPPORTED_BROWSER_PROCS: Lazy<HashSet<String>> = Lazy::new(|| {
 static VPN_EXT_CACHE: Lazy<Mutex<Option<(Vec<serde_json::Value>, std::time::Instant)>>> = Lazy::new(|| Mutex::new(None));
 const VPN_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(45);
 
+static DNS_FILTER: Lazy<Mutex<Option<DnsFilterHandle>>> = Lazy::new(|| Mutex::new(None));
+const DNS_FILTER_BIND: &str = "127.0.0.1:53";
+const DNS_FILTER_ADDRESS: &str = "127.0.0.1";
+const DNS_PROVIDER_KEY: &str = "dnsProvider";
+const CUSTOM_DNS_PROVIDERS_KEY: &str = "customDnsProviders";
+const PENDING_DNS_PROVIDERS_KEY: &str = "pendingDnsProviders";
+const PENDING_DNS_SWITCHES_KEY: &str = "pendingDnsSwitches";
+const DNS_PROVIDER_SETTING_PREFIX: &str = "saveDnsProvider::";
+const DNS_SWITCH_SETTING_PREFIX: &str = "switchDnsProvider::";
+
+static APP_USAGE: Lazy<Mutex<Option<UsageLedger>>> = Lazy::new(|| Mutex::new(None));
+static FOCUS_SESSION_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+// Held while a focus session starts or ends or a profile switch is applied,
+// so one cannot run between the other's checks and its writes.
+static FOCUS_START_LOCK: Lazy<tauri::async_runtime::Mutex<()>> = Lazy::new(|| tauri::async_runtime::Mutex::new(()));
+static PROFILE_SWITCH_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+static SCHEDULE_WORKER_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+static NETWORK_WATCHER_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+static SCHEDULE_SYNC_BACKOFF: Lazy<Mutex<SyncBackoff>> = Lazy::new(|| Mutex::new(SyncBackoff::default()));
+const SCHEDULE_MAX_SLEEP: Duration = Duration::from_secs(60);
+const NETWORK_SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+const AUDIT_LOG_FILE: &str = "auditLog.json";
+const AUDIT_LOG_MAX_EVENTS: usize = 1000;
+static AUDIT_LOG_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+const DNS_TAMPER_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const DNS_RESTORE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+static DNS_RESTORE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static DNS_RESTORE_LAST_ATTEMPT: Lazy<Mutex<Option<std::time::Instant>>> = Lazy::new(|| Mutex::new(None));
+
 static VPN_WORKER_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
 static VPN_WORKER_STOP: Lazy<Mutex<Option<Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(None));
 
-const REQUIRED_ENTRIES : [&str; 7]= [
-    "216.239.38.120 www.google.com",
-    "216.239.38.120 google.com",
-    "204.79.197.220 bing.com",
-    "204.79.197.220 www.bing.com",
-    "213.180.193.56 yandex.ru",
-    "213.180.204.92 www.yandex.com",
-    "127.0.0.1 yandex.com/images",
-];
+static PROCESS_MONITOR_HANDLE: Lazy<Mutex<Option<std::thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+static PROCESS_MONITOR_STOP: Lazy<Mutex<Option<Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(None));
+// Latest processes seen by the monitor; `None` while it is not running.
+static RUNNING_PROCESSES: Lazy<Mutex<Option<Vec<ProcessInfo>>>> = Lazy::new(|| Mutex::new(None));
+// PIDs the monitor matched to a blocked-app rule on its last poll.
+static BLOCKED_PIDS: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// Apps with a daily quota that the monitor found running, by rule process
+// name; `None` while it is not running.
+static QUOTA_APPS_RUNNING: Lazy<Mutex<Option<HashSet<String>>>> = Lazy::new(|| Mutex::new(None));
+// What the monitor wants the overlay to show for blocked apps, if anything.
+static BLOCKED_APP_OVERLAY: Lazy<Mutex<Option<Value>>> = Lazy::new(|| Mutex::new(None));
+const PROCESS_MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+// How long the overlay reports a kill or suspend before closing.
+const ENFORCEMENT_NOTICE_DURATION: Duration = Duration::from_secs(10);
+
+// Older versions wrote this line; hosts entries cannot hold paths, so it
+// never worked and is only kept to be cleaned up.
+const LEGACY_SAFE_SEARCH_HOSTS: [&str; 1] = ["yandex.com/images"];
+const SAFE_SEARCH_ENGINES_KEY: &str = "safeSearchEngines";
+const SAFE_SEARCH_ADDRESSES_KEY: &str = "safeSearchAddresses";
+const HOSTS_LEGACY_MIGRATED_KEY: &str = "hostsLegacyLinesMigrated";
 
 const PROTECTED_SYSTEM_APPS : [(&str, &[&str]); 3] = [
     ("Task Manager", &["taskmgr.exe"]),
@@ -99,6 +201,8 @@ const PROTECTED_SYSTEM_APPS : [(&str, &[&str]); 3] = [
 
 const TASK_NAME: &str = "Eagle Task Schedule";
 const HOSTS_PATH: &str = r"C:\Windows\System32\drivers\etc\hosts";
+const BLOCK_ADDRESSES: [&str; 2] = ["127.0.0.1", "::1"];
+const SUBDOMAIN_VARIANTS_KEY: &str = "websiteSubdomainVariants";
 const DELAY_SETTINGS: &str = "delayTimeOut";
 const UNINSTALL_OVERLAY_DISPLAY: &str = "Uninstaller";
 
@@ -256,6 +360,7 @@ fn write_json_map(path: &PathBuf, map: &Map<String, Value>) -> Result<(), String
                     *guard = Some(map.clone());
                     println!("write_json_map: updated blockData cache");
                 }
+                refresh_dns_filter_rules(map);
             }
             "savedPreferences.json" => {
                 if let Ok(mut guard) = SAVED_PREFERENCES_CACHE.lock() {
@@ -303,8 +408,8 @@ fn save_block_data(data: Map<String, Value>, app_handle: tauri::AppHandle) -> Re
 }
 
 #[tauri::command]
-fn is_dns_made_safe() -> Result<bool, String> {
-    is_dns_made_safe_cached()
+fn is_dns_made_safe(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    is_dns_made_safe_cached(&app_handle)
 }
 
 fn dns_cache_set(value: bool) {
@@ -313,29 +418,298 @@ fn dns_cache_set(value: bool) {
     }
 }
 
-fn is_dns_made_safe_cached() -> Result<bool, String> {
+fn is_dns_made_safe_cached(app_handle: &tauri::AppHandle) -> Result<bool, String> {
     let now = std::time::Instant::now();
+    let selected = get_selected_dns_provider(app_handle);
     if let Ok(mut g) = DNS_SAFE_CACHE.lock() {
         if let Some((val, ts)) = g.as_ref().copied() {
             if now.duration_since(ts) < DNS_SAFE_TTL {
                 return Ok(val);
             }
         }
-        let interface_name = get_active_interface_name()?;
-        let fresh = is_safe_dns(&interface_name)?;
+        let fresh = are_all_interfaces_safe(&selected)?;
         *g = Some((fresh, now));
         return Ok(fresh);
     }
-    let interface_name = get_active_interface_name()?;
-    is_safe_dns(&interface_name)
+    are_all_interfaces_safe(&selected)
+}
+
+fn are_all_interfaces_safe(selected: &DnsProvider) -> Result<bool, String> {
+    for interface_name in get_connected_interface_names()? {
+        if !is_safe_dns(&interface_name, selected)?.is_safe {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_protective_dns_status(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let selected = get_selected_dns_provider(&app_handle);
+    let mut all_safe = true;
+    let mut adapters: Vec<Value> = Vec::new();
+
+    for interface_name in get_connected_interface_names()? {
+        let adapter = match is_safe_dns(&interface_name, &selected) {
+            Ok(status) => {
+                all_safe &= status.is_safe;
+                json!({
+                    "interface": interface_name,
+                    "safe": status.is_safe,
+                    "provider": status.provider,
+                    "transport": status.transport,
+                    "unapprovedIpv4": status.unapproved_ipv4,
+                    "unapprovedIpv6": status.unapproved_ipv6
+                })
+            }
+            Err(e) => {
+                all_safe = false;
+                json!({ "interface": interface_name, "safe": false, "error": e })
+            }
+        };
+        adapters.push(adapter);
+    }
+    dns_cache_set(all_safe);
+
+    // Set when the preferred encryption had to fall back to plain DNS.
+    let transport_error = (get_preferred_dns_transport(&app_handle) != DnsTransport::Plain && !*DNS_ENCRYPTION_SUPPORTED)
+        .then_some("dns-encryption-unsupported");
+
+    Ok(json!({
+        "safe": all_safe,
+        "adapters": adapters,
+        "transportError": transport_error
+    }))
+}
+
+fn get_custom_dns_providers(app_handle: &tauri::AppHandle) -> Vec<DnsProvider> {
+    let prefs = match get_app_file_path(app_handle, "savedPreferences.json").and_then(|p| read_json_map(&p)) {
+        Ok(p) => p,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut providers = Vec::new();
+    if let Some(arr) = prefs.get(CUSTOM_DNS_PROVIDERS_KEY).and_then(|v| v.as_array()) {
+        for item in arr {
+            match serde_json::from_value::<DnsProvider>(item.clone()) {
+                Ok(provider) if provider.validate().is_ok() => providers.push(provider),
+                Ok(provider) => eprintln!("get_custom_dns_providers: skipping invalid provider '{}'", provider.id),
+                Err(e) => eprintln!("get_custom_dns_providers: skipping unreadable provider: {}", e),
+            }
+        }
+    }
+    providers
+}
+
+fn get_dns_providers_for(app_handle: &tauri::AppHandle) -> Vec<DnsProvider> {
+    let mut providers = builtin_providers();
+    providers.extend(get_custom_dns_providers(app_handle));
+    providers
+}
+
+fn resolve_dns_provider(app_handle: &tauri::AppHandle, provider_id: &str) -> Result<DnsProvider, String> {
+    find_provider(&get_dns_providers_for(app_handle), provider_id)
+        .cloned()
+        .ok_or_else(|| format!("unknown dns provider '{}'", provider_id))
+}
+
+/// The provider chosen in the last `turn_on_dns`, falling back to the
+/// provider implied by the older `dnsFilterStrict` flag.
+fn get_selected_dns_provider(app_handle: &tauri::AppHandle) -> DnsProvider {
+    let selected = get_app_file_path(app_handle, "savedPreferences.json")
+        .and_then(|p| read_json_map(&p))
+        .ok()
+        .and_then(|prefs| prefs.get(DNS_PROVIDER_KEY).and_then(|v| v.as_str()).map(|s| s.to_string()));
+
+    let provider_id = selected.unwrap_or_else(|| {
+        if read_preferences_for_key(app_handle, "dnsFilterStrict").unwrap_or(false) {
+            DEFAULT_STRICT_PROVIDER.to_string()
+        } else {
+            DEFAULT_LENIENT_PROVIDER.to_string()
+        }
+    });
+
+    resolve_dns_provider(app_handle, &provider_id).unwrap_or_else(|e| {
+        eprintln!("get_selected_dns_provider: {}, using {}", e, DEFAULT_STRICT_PROVIDER);
+        let builtins = builtin_providers();
+        find_provider(&builtins, DEFAULT_STRICT_PROVIDER).cloned().expect("default dns provider is built in")
+    })
+}
+
+#[tauri::command]
+fn get_dns_providers(app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let selected = get_selected_dns_provider(&app_handle).id;
+    let builtin_ids: Vec<String> = builtin_providers().into_iter().map(|p| p.id).collect();
+
+    get_dns_providers_for(&app_handle)
+        .into_iter()
+        .map(|provider| {
+            let mut value = serde_json::to_value(&provider).map_err(|e| e.to_string())?;
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("builtin".into(), Value::Bool(builtin_ids.contains(&provider.id)));
+                obj.insert("selected".into(), Value::Bool(provider.id == selected));
+            }
+            Ok(value)
+        })
+        .collect()
+}
+
+/// Stores a DNS change under `key` in savedPreferences.json until its delay runs out.
+fn store_pending_dns_change(app_handle: &tauri::AppHandle, key: &str, id: &str, change: Value) -> Result<(), String> {
+    let prefs = read_json_map(&get_app_file_path(app_handle, "savedPreferences.json")?)?;
+    let mut pending = match prefs.get(key) {
+        Some(Value::Object(pending)) => pending.clone(),
+        _ => Map::new(),
+    };
+    pending.insert(id.to_string(), change);
+    save_preference(key.to_string(), Value::Object(pending), app_handle.clone())
+}
+
+fn take_pending_dns_change(app_handle: &tauri::AppHandle, key: &str, id: &str) -> Result<Option<Value>, String> {
+    let prefs = read_json_map(&get_app_file_path(app_handle, "savedPreferences.json")?)?;
+    let mut pending = match prefs.get(key) {
+        Some(Value::Object(pending)) => pending.clone(),
+        _ => return Ok(None),
+    };
+    let change = pending.remove(id);
+    if change.is_some() {
+        save_preference(key.to_string(), Value::Object(pending), app_handle.clone())?;
+    }
+    Ok(change)
+}
+
+/// Custom providers declare their own strictness and addresses, so adding or
+/// editing one always waits for the delay.
+#[tauri::command]
+fn save_custom_dns_provider(provider: DnsProvider, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let mut provider = provider;
+    provider.id = provider.id.trim().to_lowercase();
+    provider.validate()?;
+
+    if find_provider(&builtin_providers(), &provider.id).is_some() {
+        return Err(format!("'{}' is a built-in dns provider", provider.id));
+    }
+
+    let value = serde_json::to_value(&provider).map_err(|e| format!("failed to serialize dns provider: {}", e))?;
+    store_pending_dns_change(&app_handle, PENDING_DNS_PROVIDERS_KEY, &provider.id, value)?;
+
+    let setting_id = format!("{}{}", DNS_PROVIDER_SETTING_PREFIX, provider.id);
+    println!("save_custom_dns_provider: delaying '{}' (setting_id='{}')", provider.id, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("save_custom_dns_provider: failed to start timer: {}", e))?;
+
+    Ok(json!({ "applied": false, "settingId": setting_id }))
+}
+
+/// Saves a custom provider that was waiting for the delay to run out.
+fn apply_pending_dns_provider(app_handle: &tauri::AppHandle, provider_id: &str) -> Result<(), String> {
+    let provider = match take_pending_dns_change(app_handle, PENDING_DNS_PROVIDERS_KEY, provider_id)? {
+        Some(value) => serde_json::from_value::<DnsProvider>(value).map_err(|e| format!("failed to read pending dns provider: {}", e))?,
+        None => {
+            println!("apply_pending_dns_provider: nothing pending for '{}'", provider_id);
+            return Ok(());
+        }
+    };
+
+    let mut custom = get_custom_dns_providers(app_handle);
+    match custom.iter_mut().find(|p| p.id == provider.id) {
+        Some(existing) => *existing = provider,
+        None => custom.push(provider),
+    }
+
+    let value = serde_json::to_value(&custom).map_err(|e| format!("failed to serialize dns providers: {}", e))?;
+    save_preference(CUSTOM_DNS_PROVIDERS_KEY.to_string(), value, app_handle.clone())?;
+    println!("apply_pending_dns_provider: saved '{}'", provider_id);
+
+    // The adapters still carry the old addresses if the edited provider is in use.
+    if read_preferences_for_key(app_handle, "enableProtectiveDNS").unwrap_or(false)
+        && get_selected_dns_provider(app_handle).id == provider_id
+    {
+        let app = app_handle.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = tauri::async_runtime::block_on(reapply_protective_dns(&app)) {
+                eprintln!("apply_pending_dns_provider: failed to reapply dns: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn remove_custom_dns_provider(provider_id: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let provider_id = provider_id.trim().to_lowercase();
+    if read_preferences_for_key(&app_handle, "enableProtectiveDNS").unwrap_or(false)
+        && get_selected_dns_provider(&app_handle).id == provider_id
+    {
+        return Err("dns-provider-in-use".into());
+    }
+
+    let mut custom = get_custom_dns_providers(&app_handle);
+    let before = custom.len();
+    custom.retain(|p| p.id != provider_id);
+    if custom.len() == before {
+        return Ok(false);
+    }
+
+    let value = serde_json::to_value(&custom).map_err(|e| format!("failed to serialize dns providers: {}", e))?;
+    save_preference(CUSTOM_DNS_PROVIDERS_KEY.to_string(), value, app_handle)?;
+    Ok(true)
 }
 
+/// Switches protective DNS to `provider_id`. Switching to a less strict
+/// provider, or away from the local filter, waits for the delay.
 #[tauri::command]
-async fn turn_on_dns(is_strict: bool, app_handle: tauri::AppHandle) -> Result<(), String> {
-    let interface_name = get_active_interface_name()?;
+async fn turn_on_dns(provider_id: String, use_local_filter: Option<bool>, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let use_local_filter = use_local_filter.unwrap_or(false);
+    let provider = resolve_dns_provider(&app_handle, &provider_id)?;
+
+    let current = if read_preferences_for_key(&app_handle, "enableProtectiveDNS").unwrap_or(false) {
+        Some(get_selected_dns_provider(&app_handle))
+    } else {
+        None
+    };
+    let leaves_filter = current.is_some() && !use_local_filter && read_preferences_for_key(&app_handle, "enableDnsFilter").unwrap_or(false);
+
+    if is_dns_switch_tightening(current.as_ref(), &provider) && !leaves_filter {
+        apply_dns_provider(&provider, use_local_filter, &app_handle).await?;
+        return Ok(json!({ "applied": true }));
+    }
+
+    store_pending_dns_change(&app_handle, PENDING_DNS_SWITCHES_KEY, &provider.id, json!({ "useLocalFilter": use_local_filter }))?;
+    let setting_id = format!("{}{}", DNS_SWITCH_SETTING_PREFIX, provider.id);
+    println!("turn_on_dns: '{}' loosens protection, delaying (setting_id='{}')", provider.id, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("turn_on_dns: failed to start timer: {}", e))?;
+
+    Ok(json!({ "applied": false, "settingId": setting_id }))
+}
+
+/// Applies a provider switch that was waiting for the delay to run out.
+async fn apply_pending_dns_switch(app_handle: &tauri::AppHandle, provider_id: &str) -> Result<(), String> {
+    let use_local_filter = match take_pending_dns_change(app_handle, PENDING_DNS_SWITCHES_KEY, provider_id)? {
+        Some(change) => change.get("useLocalFilter").and_then(|v| v.as_bool()).unwrap_or(false),
+        None => {
+            println!("apply_pending_dns_switch: nothing pending for '{}'", provider_id);
+            return Ok(());
+        }
+    };
+    let provider = resolve_dns_provider(app_handle, provider_id)?;
+    apply_dns_provider(&provider, use_local_filter, app_handle).await
+}
+
+async fn apply_dns_provider(provider: &DnsProvider, use_local_filter: bool, app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let interface_names = get_connected_interface_names()?;
+    let transport = get_preferred_dns_transport(app_handle);
 
-    configure_safe_dns(&interface_name, is_strict).await.map_err(|e| {
-        eprintln!("turn_on_dns: configure_safe_dns failed: {}", e);
+    let configured = if use_local_filter {
+        start_dns_filter_for(app_handle, provider)?;
+        configure_dns_servers(&interface_names, &[DNS_FILTER_ADDRESS], &[]).await
+    } else {
+        configure_safe_dns(&interface_names, provider, transport).await
+    };
+
+    configured.map_err(|e| {
+        eprintln!("apply_dns_provider: configure_safe_dns failed: {}", e);
         let elow = e.to_lowercase();
         if elow.contains("elevation canceled") || elow.contains("canceled by the user") || elow.contains("operation was canceled") {
             "elevation-canceled-by-user".to_string()
@@ -344,6 +718,14 @@ async fn turn_on_dns(is_strict: bool, app_handle: tauri::AppHandle) -> Result<()
         }
     })?;
 
+    if !use_local_filter {
+        if let Ok(mut guard) = DNS_FILTER.lock() {
+            if let Some(filter) = guard.take() {
+                filter.stop();
+            }
+        }
+    }
+
     dns_cache_set(true);
 
     save_preference(
@@ -351,9 +733,11 @@ async fn turn_on_dns(is_strict: bool, app_handle: tauri::AppHandle) -> Result<()
         serde_json::Value::Bool(true),
         app_handle.clone(),
     )?;
+    save_preference("enableDnsFilter".to_string(), serde_json::Value::Bool(use_local_filter), app_handle.clone())?;
+    save_preference(DNS_PROVIDER_KEY.to_string(), serde_json::Value::String(provider.id.clone()), app_handle.clone())?;
 
     let _ = tauri::Manager::emit_all(
-        &app_handle,
+        app_handle,
         "preferences-updated",
         serde_json::json!({}),
     );
@@ -361,8 +745,8 @@ async fn turn_on_dns(is_strict: bool, app_handle: tauri::AppHandle) -> Result<()
     Ok(())
 }
 
-fn get_active_interface_name() -> Result<String, String> {
-    let output = run_hidden_output("netsh", &["interface", "show", "interface"]) 
+fn get_connected_interface_names() -> Result<Vec<String>, String> {
+    let output = run_hidden_output("netsh", &["interface", "show", "interface"])
         .map_err(|e| format!("Failed to execute netsh command: {}", e))?;
 
     if !output.status.success() {
@@ -370,41 +754,108 @@ fn get_active_interface_name() -> Result<String, String> {
     }
 
     let stdout = str::from_utf8(&output.stdout).map_err(|e| format!("Invalid UTF-8 output: {}", e))?;
-    let re = Regex::new(r"\s{2,}").map_err(|e| e.to_string())?;
-
-    for line in stdout.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        if trimmed.contains("Connected") && trimmed.contains("Enabled") {
-            let parts: Vec<&str> = re.split(trimmed).map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
-            if let Some(interface_name) = parts.last() {
-                return Ok(interface_name.to_string());
-            }
-        }
+    let names = connected_interface_names(stdout);
+    if names.is_empty() {
+        return Err("No active interface found".to_string());
     }
+    Ok(names)
+}
 
-    Err("No active interface found".to_string())
+struct SafeDnsStatus {
+    is_safe: bool,
+    provider: Option<String>,
+    transport: String,
+    unapproved_ipv4: Vec<String>,
+    unapproved_ipv6: Vec<String>,
 }
 
-fn is_safe_dns(interface_name: &str) -> Result<bool, String> {
+// `netsh dns ... encryption` only exists on Windows 11 and later; older
+// versions answer "The following command was not found".
+static DNS_ENCRYPTION_SUPPORTED: Lazy<bool> = Lazy::new(|| {
+    matches!(run_hidden_output("netsh", &["dns", "show", "encryption"]), Ok(output) if output.status.success())
+});
+
+fn get_dns_transport(server: &str) -> DnsTransport {
+    let server_arg = format!("server={}", server);
+    match run_hidden_output("netsh", &["dns", "show", "encryption", &server_arg]) {
+        Ok(output) if output.status.success() => parse_encryption_transport(&String::from_utf8_lossy(&output.stdout)),
+        _ => DnsTransport::Plain,
+    }
+}
+
+fn get_interface_dns_servers(interface_name: &str, family: &str) -> Result<Vec<std::net::IpAddr>, String> {
     let output = run_hidden_output(
         "netsh",
-        &["interface", "ipv4", "show", "dnsservers", "name=", interface_name],
+        &["interface", family, "show", "dnsservers", "name=", interface_name],
     )
     .map_err(|e| format!("Failed to execute netsh command: {}", e))?;
 
     if !output.status.success() {
-        return Err("Failed to retrieve DNS settings".to_string());
+        return Err(format!("Failed to retrieve {} DNS settings", family));
     }
 
     let stdout = str::from_utf8(&output.stdout).map_err(|e| format!("Invalid UTF-8 output: {}", e))?;
+    Ok(parse_dns_server_addresses(stdout))
+}
+
+/// Whether the adapter uses the selected provider (or the local filter).
+/// Other providers do not count, even known ones, so a switch that skipped
+/// the delay shows up as tampering.
+fn is_safe_dns(interface_name: &str, selected: &DnsProvider) -> Result<SafeDnsStatus, String> {
+    let configured: Vec<String> = get_interface_dns_servers(interface_name, "ipv4")?
+        .into_iter()
+        .filter(|ip| ip.is_ipv4())
+        .map(|ip| ip.to_string())
+        .collect();
+    // netsh fails here when IPv6 is unbound from the adapter, which leaves nothing to bypass.
+    let configured_v6: Vec<std::net::Ipv6Addr> = get_interface_dns_servers(interface_name, "ipv6")
+        .unwrap_or_else(|e| {
+            println!("is_safe_dns: no IPv6 dns settings for {}: {}", interface_name, e);
+            Vec::new()
+        })
+        .into_iter()
+        .filter_map(|ip| match ip {
+            std::net::IpAddr::V6(v6) => Some(v6),
+            _ => None,
+        })
+        .collect();
+
+    // The local filter only listens on IPv4, so any IPv6 resolver bypasses it.
+    if configured.iter().any(|s| s == DNS_FILTER_ADDRESS) && is_dns_filter_running() {
+        let unapproved: Vec<String> = configured.iter().filter(|s| *s != DNS_FILTER_ADDRESS).cloned().collect();
+        let unapproved_v6: Vec<String> = configured_v6.iter().map(|ip| ip.to_string()).collect();
+        return Ok(SafeDnsStatus {
+            is_safe: unapproved.is_empty() && unapproved_v6.is_empty(),
+            provider: None,
+            transport: "local-filter".to_string(),
+            unapproved_ipv4: unapproved,
+            unapproved_ipv6: unapproved_v6,
+        });
+    }
+
+    let unapproved: Vec<String> = selected.unapproved_ipv4(&configured);
+    let unapproved_v6: Vec<String> = selected.unapproved_ipv6(&configured_v6).iter().map(|ip| ip.to_string()).collect();
+    if !unapproved.is_empty() || !unapproved_v6.is_empty() {
+        println!("is_safe_dns: {} has non-approved resolvers: {:?} {:?}", interface_name, unapproved, unapproved_v6);
+    }
 
-    let has_strict_dns = stdout.contains("185.228.168.168") && stdout.contains("185.228.169.168");
-    let has_lenient_dns = stdout.contains("208.67.222.123") && stdout.contains("208.67.220.123");
+    if selected.matches_servers(&configured) {
+        return Ok(SafeDnsStatus {
+            is_safe: unapproved_v6.is_empty(),
+            provider: Some(selected.id.clone()),
+            transport: get_dns_transport(&selected.ipv4_primary).name().to_string(),
+            unapproved_ipv4: unapproved,
+            unapproved_ipv6: unapproved_v6,
+        });
+    }
 
-    Ok(has_strict_dns || has_lenient_dns)
+    Ok(SafeDnsStatus {
+        is_safe: false,
+        provider: None,
+        transport: DnsTransport::Plain.name().to_string(),
+        unapproved_ipv4: unapproved,
+        unapproved_ipv6: unapproved_v6,
+    })
 }
 
 async fn run_elevated_command(cmd: String) -> Result<(), String> {
@@ -452,1792 +903,4162 @@ async fn run_elevated_command(cmd: String) -> Result<(), String> {
     .map_err(|e| format!("spawn_blocking join error: {}", e))?
 }
 
-async fn configure_safe_dns(interface_name: &str, is_strict: bool) -> Result<(), String> {
-    let (primary_dns, secondary_dns) = if is_strict {
-        ("185.228.168.168", "185.228.169.168")
-    } else {
-        ("208.67.222.123", "208.67.220.123")
-    };
+fn get_preferred_dns_transport(app_handle: &tauri::AppHandle) -> DnsTransport {
+    get_app_file_path(app_handle, "savedPreferences.json")
+        .and_then(|p| read_json_map(&p))
+        .ok()
+        .and_then(|prefs| prefs.get("dnsTransport").and_then(|v| v.as_str()).map(DnsTransport::from_name))
+        .unwrap_or(DnsTransport::Plain)
+}
 
-    let netsh_command = format!(
-        "netsh interface ipv4 set dns name=\"{}\" static {} primary && netsh interface ipv4 add dns name=\"{}\" {} index=2",
-        interface_name, primary_dns, interface_name, secondary_dns
+fn build_dns_servers_command(interface_name: &str, family: &str, servers: &[&str]) -> Result<String, String> {
+    let primary_dns = match servers.first() {
+        Some(primary) => primary,
+        None if family == "ipv6" => {
+            return Ok(format!(
+                "netsh interface ipv6 set dnsservers name=\"{}\" source=static address=none register=primary",
+                interface_name
+            ))
+        }
+        None => return Err("no dns servers given".into()),
+    };
+    let mut netsh_command = format!(
+        "netsh interface {} set dns name=\"{}\" static {} primary",
+        family, interface_name, primary_dns
     );
+    for (idx, server) in servers.iter().enumerate().skip(1) {
+        netsh_command.push_str(&format!(
+            " && netsh interface {} add dns name=\"{}\" {} index={}",
+            family, interface_name, server, idx + 1
+        ));
+    }
+    Ok(netsh_command)
+}
+
+/// IPv6 is always set statically, to `ipv6` or to no servers at all, so
+/// resolvers advertised by the router cannot bypass the IPv4 settings.
+fn build_dual_stack_dns_command(interface_name: &str, ipv4: &[&str], ipv6: &[&str]) -> Result<String, String> {
+    Ok(format!(
+        "{} && {}",
+        build_dns_servers_command(interface_name, "ipv4", ipv4)?,
+        build_dns_servers_command(interface_name, "ipv6", ipv6)?
+    ))
+}
 
+fn build_all_interfaces_dns_command(interface_names: &[String], ipv4: &[&str], ipv6: &[&str]) -> Result<String, String> {
+    let commands = interface_names
+        .iter()
+        .map(|name| build_dual_stack_dns_command(name, ipv4, ipv6))
+        .collect::<Result<Vec<String>, String>>()?;
+    if commands.is_empty() {
+        return Err("no interfaces to configure".into());
+    }
+    Ok(commands.join(" && "))
+}
+
+async fn configure_dns_servers(interface_names: &[String], ipv4: &[&str], ipv6: &[&str]) -> Result<(), String> {
+    let netsh_command = build_all_interfaces_dns_command(interface_names, ipv4, ipv6)?;
     println!("Command: {}", &netsh_command);
     run_elevated_command(netsh_command).await
 }
 
-#[tauri::command]
-fn is_safe_search_enabled() -> Result<bool, String> {
-
-    match std::fs::read_to_string(HOSTS_PATH) {
-        Ok(content) => {
-            let all_present = REQUIRED_ENTRIES.iter().all(|entry| content.contains(entry));
-            Ok(all_present)
-        }
-        Err(e) => {
-            eprintln!("Error reading hosts file ({}): {}", HOSTS_PATH, e);
-            Ok(false)
+async fn configure_safe_dns(interface_names: &[String], provider: &DnsProvider, transport: DnsTransport) -> Result<(), String> {
+    // Chaining an unknown netsh command would fail the whole elevated
+    // command, so the servers are still set, just without encryption.
+    let transport = if transport != DnsTransport::Plain && !*DNS_ENCRYPTION_SUPPORTED {
+        eprintln!(
+            "configure_safe_dns: DNS-over-{} needs Windows 11 (netsh dns add encryption is not available), using plain DNS",
+            if transport == DnsTransport::Doh { "HTTPS" } else { "TLS" }
+        );
+        DnsTransport::Plain
+    } else if !provider.supports(transport) {
+        eprintln!(
+            "configure_safe_dns: {} does not offer DNS-over-{}, using plain DNS",
+            provider.name,
+            if transport == DnsTransport::Doh { "HTTPS" } else { "TLS" }
+        );
+        DnsTransport::Plain
+    } else {
+        transport
+    };
+    let servers = provider.ipv4_servers();
+    let servers_v6 = provider.ipv6_servers();
+    let mut netsh_command = build_all_interfaces_dns_command(interface_names, &servers, &servers_v6)?;
+
+    for server in servers.iter().chain(servers_v6.iter()) {
+        if let Some(encryption) = provider.encryption_command(server, transport) {
+            netsh_command.push_str(" && ");
+            netsh_command.push_str(&encryption);
         }
     }
+
+    println!("Command: {}", &netsh_command);
+    run_elevated_command(netsh_command).await
 }
 
-#[tauri::command]
-async fn enable_safe_search(app_handle: tauri::AppHandle) -> Result<bool, String>{
-    let current = match std::fs::read_to_string(HOSTS_PATH) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("add_safe_search_hosts: failed to read hosts file: {}", e);
-            String::new()
-        }
-    };
+fn is_dns_filter_running() -> bool {
+    DNS_FILTER.lock().map(|g| g.is_some()).unwrap_or(false)
+}
 
-    let mut to_add: Vec<&str> = Vec::new();
-    for entry in &REQUIRED_ENTRIES {
-        if !current.contains(entry) {
-            to_add.push(entry);
-        }
-    }
+fn dns_filter_upstream(app_handle: &tauri::AppHandle, provider: &DnsProvider) -> Result<std::net::SocketAddr, String> {
+    let prefs = get_app_file_path(app_handle, "savedPreferences.json").and_then(|p| read_json_map(&p))?;
+    let configured = prefs.get("dnsFilterUpstream").and_then(|v| v.as_str()).map(|s| s.trim().to_string());
+    let upstream = configured.unwrap_or_else(|| provider.ipv4_primary.clone());
 
-    if to_add.is_empty() {
-        return Ok(false);
-    }
+    upstream
+        .parse::<std::net::SocketAddr>()
+        .or_else(|_| upstream.parse::<std::net::IpAddr>().map(|ip| std::net::SocketAddr::new(ip, 53)))
+        .map_err(|e| format!("invalid dns filter upstream '{}': {}", upstream, e))
+}
 
-    let mut temp_path = env::temp_dir();
-    temp_path.push("eagleblocker_hosts_tmp");
-    let temp_path_str = temp_path.to_string_lossy().into_owned();
-    
-    let mut file = std::fs::File::create(&temp_path).map_err(|e| format!("failed to create temp hosts file: {}", e))?;
-    file.write_all(current.as_bytes()).map_err(|e| format!("failed to write current hosts to temp file: {}", e))?;
-    
-    if !current.ends_with('\n') {
-        file.write_all(b"\n").map_err(|e| format!("failed to write newline to temp file: {}", e))?;
+fn start_dns_filter_for(app_handle: &tauri::AppHandle, provider: &DnsProvider) -> Result<(), String> {
+    let upstream = dns_filter_upstream(app_handle, provider)?;
+    let prefs = get_app_file_path(app_handle, "savedPreferences.json").and_then(|p| read_json_map(&p))?;
+    let mode = BlockMode::from_name(prefs.get("dnsFilterBlockMode").and_then(|v| v.as_str()).unwrap_or("nxdomain"));
+
+    let (rules, errors) = DnsRuleSet::from_block_data(&load_block_data(app_handle)?);
+    for e in errors.iter() {
+        eprintln!("start_dns_filter_for: skipping rule: {}", e);
     }
-    
-    for entry in to_add.iter() {
-        file.write_all(entry.as_bytes()).map_err(|e| format!("failed to append entry to temp file: {}", e))?;
-        file.write_all(b"\r\n").map_err(|e| format!("failed to append newline to temp file: {}", e))?;
+
+    let mut guard = DNS_FILTER.lock().map_err(|e| e.to_string())?;
+    if let Some(existing) = guard.take() {
+        existing.stop();
     }
 
-    let cmd = format!("move /Y \"{}\" \"{}\"", temp_path_str.replace('"', ""), HOSTS_PATH);
+    let bind = DNS_FILTER_BIND.parse().map_err(|e| format!("invalid dns filter address: {}", e))?;
+    *guard = Some(DnsFilterHandle::start(DnsFilterConfig { bind, upstream, mode }, rules)?);
+    Ok(())
+}
 
-    run_elevated_command(cmd).await.map_err(|e| format!("elevated move failed: {}", e))?;
-    
-    if let Err(e) = save_preference(
-        "enforceSafeSearch".to_string(),
-        serde_json::Value::Bool(true),
-        app_handle,
-    ) {
-        eprintln!("add_safe_search_hosts: failed to save preference: {}", e);
+fn refresh_dns_filter_rules(block_data: &Map<String, Value>) {
+    if let Ok(guard) = DNS_FILTER.lock() {
+        if let Some(filter) = guard.as_ref() {
+            let (rules, errors) = DnsRuleSet::from_block_data(block_data);
+            for e in errors.iter() {
+                eprintln!("refresh_dns_filter_rules: skipping rule: {}", e);
+            }
+            filter.set_rules(rules);
+        }
     }
+}
 
-    Ok(true)
+async fn reapply_protective_dns(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let interface_names = get_connected_interface_names()?;
+    if read_preferences_for_key(app_handle, "enableDnsFilter").unwrap_or(false) && is_dns_filter_running() {
+        configure_dns_servers(&interface_names, &[DNS_FILTER_ADDRESS], &[]).await?;
+    } else {
+        let provider = get_selected_dns_provider(app_handle);
+        configure_safe_dns(&interface_names, &provider, get_preferred_dns_transport(app_handle)).await?;
+    }
+    dns_cache_set(true);
+    Ok(())
 }
 
-static WINDOW_TITLE_SCRIPT: Lazy<String> = Lazy::new(|| {
-    r#"
-Add-Type @"
-    using System;
-    using System.Runtime.InteropServices;
-    using System.Text;
-    public class Win32 {
-        [DllImport("user32.dll")]
-        public static extern IntPtr GetForegroundWindow();
-        [DllImport("user32.dll")]
-        public static extern int GetWindowText(IntPtr hWnd, StringBuilder text, int count);
-    }
-"@
-$hwnd = [Win32]::GetForegroundWindow()
-$title = New-Object System.Text.StringBuilder 256
-[Win32]::GetWindowText($hwnd, $title, 256)
-$title.ToString()
-"#.to_string()
-});
+fn record_audit_event(app_handle: &tauri::AppHandle, kind: &str, details: Value) {
+    let res: Result<(), String> = (|| {
+        let _guard = AUDIT_LOG_LOCK.lock().map_err(|e| e.to_string())?;
+        let path = get_app_file_path(app_handle, AUDIT_LOG_FILE)?;
+        let mut log = read_json_map(&path)?;
 
-fn get_active_window_title_fast() -> Result<String, String> {
-    let output = run_hidden_output("powershell", &[
-        "-NoProfile", "-NonInteractive", "-WindowStyle", "Hidden", 
-        "-Command", &WINDOW_TITLE_SCRIPT
-    ]).map_err(|e| format!("powershell spawn failed: {}", e))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
 
-    if output.status.success() {
-        let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        Ok(title)
-    } else {
-        Err("Failed to get active window title".into())
+        let events = log.entry("events".to_string()).or_insert(Value::Array(Vec::new()));
+        if !events.is_array() {
+            *events = Value::Array(Vec::new());
+        }
+        let arr = events.as_array_mut().ok_or("audit log events is not an array")?;
+        arr.push(json!({ "timestamp": timestamp, "kind": kind, "details": details }));
+        if arr.len() > AUDIT_LOG_MAX_EVENTS {
+            let excess = arr.len() - AUDIT_LOG_MAX_EVENTS;
+            arr.drain(0..excess);
+        }
+
+        write_json_map(&path, &log)
+    })();
+
+    if let Err(e) = res {
+        eprintln!("record_audit_event: failed to record '{}': {}", kind, e);
     }
 }
 
-fn check_active_window_for_flags() -> Result<bool, String> {
-    let start_time = std::time::Instant::now();
-    
-    let flagged_titles = [
-        "eagleblocker uninstall",
-        "task manager",
-        "task scheduler", 
-        "apps & features"
-    ];
+#[tauri::command]
+fn get_audit_log(app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let path = get_app_file_path(&app_handle, AUDIT_LOG_FILE)?;
+    let log = read_json_map(&path)?;
+    Ok(log.get("events").and_then(|v| v.as_array()).cloned().unwrap_or_default())
+}
 
-    let title = get_active_window_title_fast()?;
-    let title_lower = title.to_lowercase();
-    
-    for flagged in &flagged_titles {
-        if title_lower.contains(flagged) {
-            let duration = start_time.elapsed();
-            println!("check_active_window_for_flags: found flagged title '{}' contains '{}' in {:?}", title, flagged, duration);
-            return Ok(true);
+fn get_unsafe_interfaces(selected: &DnsProvider) -> Result<Vec<Value>, String> {
+    let mut unsafe_interfaces = Vec::new();
+    for interface_name in get_connected_interface_names()? {
+        match is_safe_dns(&interface_name, selected) {
+            Ok(status) if status.is_safe => {}
+            Ok(status) => unsafe_interfaces.push(json!({
+                "interface": interface_name,
+                "provider": status.provider,
+                "unapprovedIpv4": status.unapproved_ipv4,
+                "unapprovedIpv6": status.unapproved_ipv6
+            })),
+            Err(e) => unsafe_interfaces.push(json!({ "interface": interface_name, "error": e })),
         }
     }
-    
-    let duration = start_time.elapsed();
-    println!("check_active_window_for_flags: completed in {:?} (no flagged titles found)", duration);
-    Ok(false)
+    Ok(unsafe_interfaces)
 }
 
-fn get_running_process_names() -> Result<HashSet<String>, String> {
-    let start_time = std::time::Instant::now();
-    
-    // Remove /V flag - much faster!
-    let output = run_hidden_output("tasklist", &["/FO", "CSV", "/NH"])
-        .map_err(|e| format!("tasklist spawn failed: {}", e))?;
+/// Claims the single DNS restore slot, so the tamper check and the network
+/// watcher never run elevated netsh at the same time or prompt twice.
+fn begin_dns_restore() -> bool {
+    let mut last = match DNS_RESTORE_LAST_ATTEMPT.lock() {
+        Ok(g) => g,
+        Err(_) => return false,
+    };
+    if matches!(*last, Some(t) if t.elapsed() < DNS_RESTORE_RETRY_INTERVAL) {
+        return false;
+    }
+    if DNS_RESTORE_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    *last = Some(std::time::Instant::now());
+    true
+}
 
-    if !output.status.success() {
-        return Err("tasklist failed".into());
+/// Re-applies protective DNS in a slot claimed by `begin_dns_restore` and
+/// records the outcome.
+async fn run_dns_restore(app_handle: &tauri::AppHandle, trigger: &str) -> Result<(), String> {
+    let result = reapply_protective_dns(app_handle).await;
+    match result.as_ref() {
+        Ok(()) => println!("run_dns_restore: protective dns restored after {}", trigger),
+        Err(e) => eprintln!("run_dns_restore: restore after {} failed: {}", trigger, e),
     }
+    record_audit_event(
+        app_handle,
+        "dns-restore",
+        json!({ "trigger": trigger, "success": result.is_ok(), "error": result.as_ref().err() }),
+    );
+    DNS_RESTORE_IN_PROGRESS.store(false, Ordering::SeqCst);
+    result
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut set = HashSet::new();
+fn restore_tampered_dns(app_handle: &tauri::AppHandle) {
+    if !begin_dns_restore() {
+        return;
+    }
 
-    for line in stdout.lines() {
-        if line.trim().is_empty() { continue; }
-        
-        let fields = parse_csv_line(line);
-        if !fields.is_empty() {
-            let process_name = fields[0].trim_matches('"').to_lowercase();
-            if !process_name.is_empty() {
-                set.insert(process_name);
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let _ = run_dns_restore(&app_handle, "tampering").await;
+    });
+}
+
+/// Re-checks every adapter and returns whether DNS is currently tampered.
+/// The audit entry is written only when the state flips to tampered.
+fn check_dns_tampering(app_handle: &tauri::AppHandle, was_tampered: bool) -> bool {
+    let selected = get_selected_dns_provider(app_handle);
+    let unsafe_interfaces = match get_unsafe_interfaces(&selected) {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("check_dns_tampering: verification failed: {}", e);
+            return was_tampered;
+        }
+    };
+
+    let tampered = !unsafe_interfaces.is_empty();
+    dns_cache_set(!tampered);
+
+    if tampered {
+        if !was_tampered {
+            println!("check_dns_tampering: dns drift detected on {:?}", unsafe_interfaces);
+            record_audit_event(app_handle, "dns-tampered", json!({ "interfaces": unsafe_interfaces }));
+        }
+        restore_tampered_dns(app_handle);
+    }
+
+    tampered
+}
+
+fn handle_network_change(app_handle: &tauri::AppHandle) {
+    let selected = get_selected_dns_provider(app_handle);
+    let safe = are_all_interfaces_safe(&selected).unwrap_or(false);
+    dns_cache_set(safe);
+
+    let mut reapplied = false;
+    let mut error: Option<String> = None;
+    if !safe {
+        if begin_dns_restore() {
+            println!("handle_network_change: protective dns lost, re-applying");
+            match tauri::async_runtime::block_on(run_dns_restore(app_handle, "network-change")) {
+                Ok(()) => reapplied = true,
+                Err(e) => error = Some(e),
             }
+        } else {
+            println!("handle_network_change: protective dns lost, a restore is already pending");
         }
     }
 
-    let duration = start_time.elapsed();
-    println!("get_running_process_names: completed in {:?} ({} processes)", duration, set.len());
-    Ok(set)
+    let _ = tauri::Manager::emit_all(
+        app_handle,
+        "network-changed",
+        json!({ "safe": safe || reapplied, "reapplied": reapplied, "error": error }),
+    );
 }
 
-fn parse_csv_line(line: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    let mut cur = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-    while let Some(ch) = chars.next() {
-        match ch {
-            '"' => {
-                if in_quotes {
-                    if matches!(chars.peek(), Some('"')) {
-                        cur.push('"');
-                        let _ = chars.next();
-                    } else {
-                        in_quotes = false;
-                    }
-                } else {
-                    in_quotes = true;
-                }
+/// Drops notifications that arrive within `NETWORK_SETTLE_DELAY` of the last.
+fn wait_for_network_to_settle(changes: &mpsc::Receiver<()>) {
+    while changes.recv_timeout(NETWORK_SETTLE_DELAY).is_ok() {}
+}
+
+fn start_network_watcher(app_handle: tauri::AppHandle) {
+    let mut guard = match NETWORK_WATCHER_HANDLE.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    if guard.is_some() {
+        return;
+    }
+
+    *guard = Some(std::thread::spawn(move || {
+        let (sender, changes) = mpsc::channel();
+        let _subscription = match network_events::subscribe(sender) {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                eprintln!("start_network_watcher: {}; relying on the tamper check", e);
+                return;
             }
-            ',' if !in_quotes => {
-                out.push(cur.trim().to_string());
-                cur.clear();
+        };
+
+        while changes.recv().is_ok() {
+            // DHCP usually rewrites resolvers a moment after the address appears.
+            wait_for_network_to_settle(&changes);
+            if !read_preferences_for_key(&app_handle, "enableProtectiveDNS").unwrap_or(false) {
+                continue;
             }
-            _ => cur.push(ch),
-        }
-    }
-    out.push(cur.trim().to_string());
-    out
-}
 
-fn load_block_data(app_handle: &tauri::AppHandle) -> Result<serde_json::Map<String, serde_json::Value>, String> {
-    let path = get_app_file_path(app_handle, "blockData.json")?;
-    read_json_map(&path)
+            println!("start_network_watcher: network changed");
+            handle_network_change(&app_handle);
+            // Re-applied DNS fires notifications of its own; they are not a change.
+            wait_for_network_to_settle(&changes);
+        }
+    }));
 }
 
-fn collect_blocked_apps(map: &serde_json::Map<String, serde_json::Value>) -> Vec<(String, String)> {
-    let mut out = Vec::new();
-    if let Some(serde_json::Value::Array(arr)) = map.get("blockedApps") {
-        for (_i, item) in arr.iter().enumerate() {
-            let proc_opt = item.as_str().map(|s| s.to_string()).or_else(|| {
-                item.get("processName").and_then(|v| v.as_str()).map(|s| s.to_string())
-            });
-            let disp_opt = item.get("displayName").and_then(|v| v.as_str()).map(|s| s.to_string())
-                .or_else(|| proc_opt.clone());
+fn stop_dns_filter_after_restoring_dns(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let provider = get_selected_dns_provider(&app_handle);
+        let restored = match get_connected_interface_names() {
+            Ok(interface_names) => {
+                let transport = get_preferred_dns_transport(&app_handle);
+                configure_safe_dns(&interface_names, &provider, transport).await
+            }
+            Err(e) => Err(e),
+        };
 
-            match proc_opt {
-                Some(proc_name) => {
-                    let display = disp_opt.unwrap_or_else(|| proc_name.clone());
-                    out.push((proc_name, display));
-                }
-                None => {
-                    println!("collect_blocked_apps: skipping entry (no processName): {}", item);
+        match restored {
+            Ok(()) => {
+                if let Ok(mut guard) = DNS_FILTER.lock() {
+                    if let Some(filter) = guard.take() {
+                        filter.stop();
+                    }
                 }
+                dns_cache_set(true);
+                println!("stop_dns_filter_after_restoring_dns: local dns filter stopped");
+            }
+            Err(e) => {
+                // Adapters still point at the filter, so it keeps its last rules.
+                eprintln!("stop_dns_filter_after_restoring_dns: keeping filter running, dns restore failed: {}", e);
             }
         }
-    }
-    out
+    });
 }
 
-fn process_matches_running(proc_name: &str, running: &std::collections::HashSet<String>) -> bool {
-    let norm = proc_name.to_lowercase();
-    let norm_exe = if norm.ends_with(".exe") { norm.clone() } else { format!("{}.exe", norm) };
-    running.iter().any(|r| {
-        let rnorm = r.to_lowercase();
-        rnorm == norm || rnorm == norm_exe
+#[tauri::command]
+fn get_dns_filter_status(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let (_, errors) = DnsRuleSet::from_block_data(&load_block_data(&app_handle)?);
+    let guard = DNS_FILTER.lock().map_err(|e| e.to_string())?;
+
+    Ok(match guard.as_ref() {
+        Some(filter) => json!({
+            "running": true,
+            "listen": filter.local_addr().to_string(),
+            "upstream": filter.upstream().to_string(),
+            "ruleErrors": errors
+        }),
+        None => json!({ "running": false, "ruleErrors": errors }),
     })
 }
 
-fn is_embedded_webview(process_name: &str) -> bool {
-    let p = process_name.to_lowercase();
-    p.contains("msedgewebview2") || p.contains("webview2") || p.contains("bravecrashhandler")
-}
+#[tauri::command]
+fn add_dns_rule(pattern: String, rule_type: Option<String>, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let pattern = pattern.trim().to_string();
+    match rule_type.as_deref() {
+        Some(t) => DnsRule::parse(t, &pattern)?,
+        None => DnsRule::infer(&pattern)?,
+    };
 
-fn has_any_of_flagged_titles() -> Result<bool, String> {
-    let start_time = std::time::Instant::now();
-    
-    let flagged_titles = [
-        "eagleblocker uninstall",
-        "task manager",
-        "task scheduler", 
-        "apps & features"
-    ];
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let arr_val = block_data
+        .entry("dnsRules".to_string())
+        .or_insert(Value::Array(Vec::new()));
 
-    let output = run_hidden_output("tasklist", &["/FO", "CSV", "/NH", "/V"])
-        .map_err(|e| format!("tasklist /V spawn failed: {}", e))?;
-    
-    if !output.status.success() {
-        let duration = start_time.elapsed();
-        println!("has_any_of_flagged_titles: completed in {:?} (tasklist failed)", duration);
-        return Ok(false);
-    }
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    for line in stdout.lines() {
-        if line.trim().is_empty() { continue; }
-        let cols = parse_csv_line(line);
-        if cols.is_empty() { continue; }
-        
-        let window_title = cols.last().map(|s| s.trim_matches('"')).unwrap_or("");
-        if window_title.is_empty() { continue; }
-        
-        let title_lower = window_title.to_lowercase();
-        
-        for flagged in &flagged_titles {
-            if title_lower.contains(flagged) {
-                let duration = start_time.elapsed();
-                println!("has_any_of_flagged_titles: found flagged title '{}' contains '{}' in {:?}", window_title, flagged, duration);
-                return Ok(true);
-            }
+    let entry = match rule_type {
+        Some(t) => json!({ "type": t.trim().to_lowercase(), "pattern": pattern }),
+        None => Value::String(pattern.clone()),
+    };
+
+    if let Value::Array(ref mut arr) = arr_val {
+        if arr.iter().any(|v| dns_rule_pattern(v) == Some(pattern.as_str())) {
+            return Ok(false);
         }
+        arr.push(entry);
+    } else {
+        *arr_val = Value::Array(vec![entry]);
     }
-    
-    let duration = start_time.elapsed();
-    println!("has_any_of_flagged_titles: completed in {:?} (no flagged titles found)", duration);
-    Ok(false)
+
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(&app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "dnsRules", "item": pattern }),
+    );
+    Ok(true)
 }
 
-fn should_flag_window_title(title: &str) -> bool {
-    let title_lower = title.to_lowercase();
-    let flagged_titles = [
-        "task manager", 
-        "task scheduler", 
-        "control panel", 
-        "registry editor", 
-        "hosts",
-        "eagleblocker uninstall", 
-        "apps & features" 
-    ];
-    
-    flagged_titles.iter().any(|flagged| title_lower.contains(flagged))
+fn dns_rule_pattern(item: &Value) -> Option<&str> {
+    item.as_str().or_else(|| item.get("pattern").and_then(|v| v.as_str()))
 }
 
-#[cfg(windows)]
-fn get_idle_millis() -> Result<u64, String> {
-    unsafe {
-        let mut lii = LASTINPUTINFO {
-            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
-            dwTime: 0,
-        };
-        if !GetLastInputInfo(&mut lii).as_bool() {
-            return Err("GetLastInputInfo failed".into());
-        }
-        let now = GetTickCount64();
-        let idle_ms = now.saturating_sub(lii.dwTime as u64);
-        Ok(idle_ms)
+#[tauri::command]
+fn remove_dns_rule(pattern: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let pattern = pattern.trim().to_string();
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+
+    let is_primed = block_data
+        .get("allowedForRemoveDnsRules")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().any(|v| v.as_str() == Some(pattern.as_str())))
+        .unwrap_or(false);
+    if !is_primed {
+        return Err("dns-rule-not-primed-for-deletion".into());
     }
-}
 
-fn is_pc_idle(threshold_ms: u64) -> Result<bool, String> {
-    #[cfg(windows)]
-    {
-        let idle = get_idle_millis()?;
-        return Ok(idle >= threshold_ms);
+    if let Some(Value::Array(ref mut arr)) = block_data.get_mut("dnsRules") {
+        arr.retain(|v| dns_rule_pattern(v) != Some(pattern.as_str()));
     }
-    #[cfg(not(windows))]
-    {
-        Ok(false)
+    if let Some(Value::Array(ref mut arr)) = block_data.get_mut("allowedForRemoveDnsRules") {
+        arr.retain(|v| v.as_str() != Some(pattern.as_str()));
     }
+
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(&app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "dnsRules", "item": pattern }),
+    );
+    Ok(true)
 }
 
-fn flag_protected_apps(app_handle: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
-    for (display, procs) in PROTECTED_SYSTEM_APPS.iter() {
-        if procs.iter().any(|p| running.contains(*p)) {
-            let arguments = serde_json::json!({
-                "displayName": display,
-                "processName": procs[0],
-                "code" : "protected-system-app"
-            });
+fn enabled_safe_search_engines(app_handle: &tauri::AppHandle) -> Vec<String> {
+    let prefs = get_app_file_path(app_handle, "savedPreferences.json")
+        .and_then(|p| read_json_map(&p))
+        .unwrap_or_default();
+    let flags = prefs.get(SAFE_SEARCH_ENGINES_KEY).and_then(|v| v.as_object());
 
-            let _ = show_overlay(&app_handle, arguments);
-            return Ok(true);
-        }
-    }
-    
-    Ok(false)
+    SAFE_SEARCH_ENGINES
+        .iter()
+        .filter(|engine| {
+            flags
+                .and_then(|f| f.get(engine.id))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(engine.default_enabled)
+        })
+        .map(|engine| engine.id.to_string())
+        .collect()
 }
 
-fn flag_blocked_apps(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
-    if let Ok(block_map) = load_block_data(&app_clone) {
-        let blocked_apps = collect_blocked_apps(&block_map);
-        for (proc_name, display_name) in blocked_apps.iter() {
-            if process_matches_running(proc_name, &running) {
-                let arguments = serde_json::json!({
-                    "displayName": display_name,
-                    "processName": proc_name,
-                    "code" : "blocked-app"
-                });
-                let _ = show_overlay(&app_clone, arguments);
-                return Ok(true);
-            }
-        }
-    }
-    Ok(false)
+fn stored_safe_search_addresses(app_handle: &tauri::AppHandle) -> HashMap<String, ResolvedTarget> {
+    get_app_file_path(app_handle, "savedPreferences.json")
+        .and_then(|p| read_json_map(&p))
+        .ok()
+        .and_then(|prefs| prefs.get(SAFE_SEARCH_ADDRESSES_KEY).cloned())
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
 }
 
-fn flag_proxies_and_dns_bypassers(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
-    for process_name in UNSUPPORTED_BROWSER_PROCS.iter() {
-        if running.contains(process_name) && !is_embedded_webview(process_name) {
-            let arguments = serde_json::json!({
-                "displayName": process_name,
-                "processName": process_name,
-                "code" : "unsupported_browser"
-            });
+/// Resolves the SafeSearch CNAME of every enabled engine and stores the
+/// result as the last known good addresses. Returns whether any changed.
+fn resolve_safe_search_addresses(app_handle: &tauri::AppHandle, resolver: &dyn SafeSearchResolver) -> Result<bool, String> {
+    let enabled = enabled_safe_search_engines(app_handle);
+    let cnames: Vec<&str> = SAFE_SEARCH_TARGETS
+        .iter()
+        .filter(|t| enabled.iter().any(|e| e == t.engine))
+        .map(|t| t.cname)
+        .collect();
 
-            let _ = show_overlay(&app_clone, arguments);
-            return Ok(true);
-        }
+    let (addresses, changed) = refresh_addresses(resolver, &cnames, &stored_safe_search_addresses(app_handle));
+    if !changed {
+        return Ok(false);
     }
 
-    Ok(false)
+    // Written directly: save_preference would close any open confirmation dialog.
+    let path = get_app_file_path(app_handle, "savedPreferences.json")?;
+    let mut prefs = read_json_map(&path)?;
+    let value = serde_json::to_value(&addresses).map_err(|e| format!("failed to serialize safe search addresses: {}", e))?;
+    prefs.insert(SAFE_SEARCH_ADDRESSES_KEY.to_string(), value);
+    write_json_map(&path, &prefs)?;
+    Ok(true)
 }
 
-fn is_supported_browser_running(running: HashSet<String>) -> Result<bool, String> {
-    let result = running.contains(&"brave.exe".to_string()) || running.contains(&"chrome.exe".to_string()) || running.contains(&"msedge.exe".to_string());
-    Ok(result)
+fn sync_safe_search_addresses(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if !read_preferences_for_key(app_handle, "enforceSafeSearch").unwrap_or(false) {
+        return Ok(());
+    }
+    if !resolve_safe_search_addresses(app_handle, &SystemResolver)? {
+        return Ok(());
+    }
+
+    println!("sync_safe_search_addresses: safe search addresses changed, updating hosts file");
+    tauri::async_runtime::block_on(apply_safe_search_entries(&current_safe_search_entries(app_handle)))?;
+    Ok(())
 }
 
-fn flag_supported_browser_with_vpn_extension(app_clone: tauri::AppHandle, running: HashSet<String>, extensions: Vec<serde_json::Value>) -> Result<bool, String> {
-    let mut browsers: HashSet<String> = HashSet::new();
+fn current_safe_search_entries(app_handle: &tauri::AppHandle) -> Vec<HostsEntry> {
+    build_entries(&enabled_safe_search_engines(app_handle), &stored_safe_search_addresses(app_handle))
+}
 
-    for ext in extensions.iter() {
-        let is_vpn = ext.get("is_vpn").and_then(|v| v.as_bool()).unwrap_or(false);
-        let has_proxy = ext.get("has_proxy_permission").and_then(|v| v.as_bool()).unwrap_or(false);
-        if is_vpn && has_proxy {
-            if let Some(bname) = ext.get("browser").and_then(|v| v.as_str()) {
-                browsers.insert(bname.to_string());
-            }
-        }
-    }
+/// Our SafeSearch lines are the ones pinning a SafeSearch host to anything
+/// other than a block address, so a user's own website block survives.
+fn is_safe_search_entry(entry: &HostsEntry, safe_search_hosts: &HashSet<String>) -> bool {
+    LEGACY_SAFE_SEARCH_HOSTS.contains(&entry.host.as_str())
+        || (safe_search_hosts.contains(&entry.host) && !BLOCK_ADDRESSES.contains(&entry.address.as_str()))
+}
 
-    let map_proc = |b: &str| -> Option<(&'static str, &'static str)> {
-        match b {
-            "Chrome" => Some(("Google Chrome", "chrome.exe")),
-            "Edge" => Some(("Microsoft Edge", "msedge.exe")),
-            _ => None,
-        }
-    };
+/// Makes the SafeSearch lines in the hosts file exactly `entries`. Returns
+/// whether the file had to be rewritten.
+async fn apply_safe_search_entries(entries: &[HostsEntry]) -> Result<bool, String> {
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
 
-    for b in browsers {
-        if let Some((display, exe)) = map_proc(&b) {
-            if running.contains(&exe.to_lowercase()) {
-                let _ = show_overlay(
-                    &app_clone,
-                    serde_json::json!({
-                        "displayName": display,
-                        "processName": exe,
-                        "code": "browser-with-vpn"
-                    }),
-                );
-                return Ok(true);
-            }
-        }
+    let safe_search_hosts = all_safe_search_hosts();
+    let stale = hosts.remove_matching(|e| is_safe_search_entry(e, &safe_search_hosts) && !entries.contains(e));
+    let added = hosts.restore_missing(entries);
+
+    if stale.is_empty() && added.is_empty() {
+        return Ok(false);
     }
 
-    Ok(false)
+    println!("apply_safe_search_entries: removing {} stale and adding {} entries", stale.len(), added.len());
+    write_hosts_file_elevated(&hosts).await?;
+    Ok(true)
 }
 
-fn vpn_cache_set(items: Vec<serde_json::Value>) {
-    if let Ok(mut g) = VPN_EXT_CACHE.lock() {
-        *g = Some((items, std::time::Instant::now()));
+#[tauri::command]
+fn is_safe_search_enabled(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let entries = current_safe_search_entries(&app_handle);
+    match HostsFile::load(HOSTS_PATH) {
+        Ok(hosts) => Ok(hosts.missing_entries(&entries).is_empty()),
+        Err(e) => {
+            eprintln!("Error reading hosts file ({}): {}", HOSTS_PATH, e);
+            Ok(false)
+        }
     }
 }
 
-fn get_vpn_extensions_cached() -> Vec<serde_json::Value> {
-    let now = std::time::Instant::now();
-    if let Ok(g) = VPN_EXT_CACHE.lock() {
-        if let Some((items, ts)) = g.as_ref() {
-            if now.duration_since(*ts) < VPN_CACHE_TTL {
-                return items.clone();
-            }
-        }
+async fn write_hosts_file_elevated(hosts: &HostsFile) -> Result<(), String> {
+    let mut tmp_path = env::temp_dir();
+    let suffix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis().to_string())
+        .unwrap_or_else(|_| "tmp".into());
+    tmp_path.push(format!("eagleblocker_hosts_{}.tmp", suffix));
+    let tmp_path_str = tmp_path.to_string_lossy().into_owned();
+
+    hosts.save_to(&tmp_path)?;
+
+    let cmd = format!(
+        "move /Y \"{}\" \"{}\"",
+        tmp_path_str.replace('"', ""),
+        hosts.path().to_string_lossy().replace('"', "")
+    );
+    println!("write_hosts_file_elevated: requesting elevation to run: {}", cmd);
+
+    if let Err(e) = run_elevated_command(cmd).await {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(format!("elevated hosts update failed: {}", e));
     }
-    Vec::new()
+
+    Ok(())
 }
 
-fn start_vpn_detector_worker() {
-    if let Ok(g) = VPN_WORKER_HANDLE.lock() {
-        if g.is_some() {
-            return;
-        }
+#[tauri::command]
+async fn enable_safe_search(app_handle: tauri::AppHandle) -> Result<bool, String>{
+    if let Err(e) = resolve_safe_search_addresses(&app_handle, &SystemResolver) {
+        eprintln!("enable_safe_search: using stored addresses: {}", e);
     }
 
-    let stop = Arc::new(AtomicBool::new(false));
-    if let Ok(mut gs) = VPN_WORKER_STOP.lock() {
-        *gs = Some(stop.clone());
-    }
+    let has_added = apply_safe_search_entries(&current_safe_search_entries(&app_handle)).await?;
 
-    let handle = std::thread::spawn(move || {
-        if let Ok(items) = detect_vpn_proxy_all_browsers() {
-            vpn_cache_set(items);
-        }
-        loop {
-            if stop.load(Ordering::SeqCst) {
-                break;
-            }
-            std::thread::sleep(VPN_CACHE_TTL);
-            if stop.load(Ordering::SeqCst) {
-                break;
-            }
+    if let Err(e) = save_preference(
+        "enforceSafeSearch".to_string(),
+        serde_json::Value::Bool(true),
+        app_handle,
+    ) {
+        eprintln!("enable_safe_search: failed to save preference: {}", e);
+    }
 
-            if let Ok(items) = detect_vpn_proxy_all_browsers() {
-                vpn_cache_set(items);
-            }
-        }
-    });
+    Ok(has_added)
+}
 
-    if let Ok(mut gh) = VPN_WORKER_HANDLE.lock() {
-        *gh = Some(handle);
+#[tauri::command]
+async fn refresh_safe_search(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    resolve_safe_search_addresses(&app_handle, &SystemResolver)?;
+    if !read_preferences_for_key(&app_handle, "enforceSafeSearch").unwrap_or(false) {
+        return Ok(false);
     }
+    apply_safe_search_entries(&current_safe_search_entries(&app_handle)).await
 }
 
-fn stop_vpn_detector_worker() {
-    if let Ok(mut gs) = VPN_WORKER_STOP.lock() {
-        if let Some(flag) = gs.take() {
-            flag.store(true, Ordering::SeqCst);
-        }
+#[tauri::command]
+fn get_safe_search_engines(app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let enabled = enabled_safe_search_engines(&app_handle);
+    Ok(SAFE_SEARCH_ENGINES
+        .iter()
+        .map(|engine| {
+            json!({
+                "id": engine.id,
+                "name": engine.name,
+                "enabled": enabled.iter().any(|e| e == engine.id)
+            })
+        })
+        .collect())
+}
+
+/// Engines can be added at any time; removing one while SafeSearch is
+/// enforced would skip the delay, so that has to wait until it is off.
+#[tauri::command]
+async fn set_safe_search_engine(engine_id: String, enabled: bool, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let engine = find_engine(&engine_id).ok_or_else(|| format!("unknown safe search engine '{}'", engine_id))?;
+    let enforced = read_preferences_for_key(&app_handle, "enforceSafeSearch").unwrap_or(false);
+    if !enabled && enforced {
+        return Err("safe-search-enforced".into());
     }
-    
-    if let Ok(mut gh) = VPN_WORKER_HANDLE.lock() {
-        if let Some(h) = gh.take() {
-            let _ = h.join();
-        }
+
+    let prefs = get_app_file_path(&app_handle, "savedPreferences.json").and_then(|p| read_json_map(&p))?;
+    let mut flags = prefs
+        .get(SAFE_SEARCH_ENGINES_KEY)
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+    flags.insert(engine.id.to_string(), Value::Bool(enabled));
+    save_preference(SAFE_SEARCH_ENGINES_KEY.to_string(), Value::Object(flags), app_handle.clone())?;
+
+    if enforced {
+        resolve_safe_search_addresses(&app_handle, &SystemResolver)?;
+        apply_safe_search_entries(&current_safe_search_entries(&app_handle)).await?;
     }
+    Ok(())
 }
 
-fn flag_vpn_proxy(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
-    let extensions = get_vpn_extensions_cached();
-    if extensions.is_empty() {
+async fn remove_safe_search_hosts() -> Result<bool, String> {
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+
+    let safe_search_hosts = all_safe_search_hosts();
+    let removed = hosts.remove_matching(|e| is_safe_search_entry(e, &safe_search_hosts));
+    if removed.is_empty() {
         return Ok(false);
     }
 
-    let result = flag_supported_browser_with_vpn_extension(app_clone.clone(), 
-        running.clone(), extensions).unwrap_or(false);
-    Ok(result)
+    write_hosts_file_elevated(&hosts).await?;
+    Ok(true)
 }
 
-#[tauri::command]
-fn turn_on_settings_and_app_protection(app_handle: tauri::AppHandle) -> Result<bool, String> {
-    let start_time = std::time::Instant::now();
-    
-    let mut guard = PROTECTION_HANDLE.lock().map_err(|e| e.to_string())?;
-    if guard.is_some() {
-        return Ok(true);
+/// Runs when the `enforceSafeSearch` countdown expires. Not a command, so
+/// the only way to turn SafeSearch off is through the delay. The removal is
+/// recorded as pending first, so sync recovery retries it if it fails.
+fn disable_safe_search(app_handle: tauri::AppHandle) {
+    if let Err(e) = set_safe_search_removal_pending(&app_handle) {
+        eprintln!("disable_safe_search: failed to record pending removal: {}", e);
     }
+    std::thread::spawn(move || {
+        if let Err(e) = retry_safe_search_removal(&app_handle) {
+            eprintln!("disable_safe_search: failed to remove safe search entries: {}", e);
+        }
+        let _ = tauri::Manager::emit_all(&app_handle, "preferences-updated", serde_json::json!({}));
+    });
+}
 
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    *PROTECTION_STOP.lock().map_err(|e| e.to_string())? = Some(stop_flag.clone());
+fn set_safe_search_removal_pending(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    block_data.insert(PENDING_SAFE_SEARCH_REMOVAL_KEY.to_string(), Value::Bool(true));
+    write_json_map(&path, &block_data)
+}
 
-    let is_settings_protection_on = read_preferences_for_key(&app_handle.clone(), "blockSettingsSwitch").unwrap_or(false);
+/// Removes the SafeSearch lines if a disable is still pending.
+fn retry_safe_search_removal(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let enforced = read_preferences_for_key(app_handle, "enforceSafeSearch").unwrap_or(false);
+    let settled = settle_pending_removal(&mut block_data, enforced, || {
+        let removed = tauri::async_runtime::block_on(remove_safe_search_hosts())?;
+        println!("retry_safe_search_removal: safe search entries removed: {}", removed);
+        Ok(removed)
+    })?;
+    if settled {
+        // Re-read: the removal may have waited on an elevation prompt.
+        let mut block_data = read_json_map(&path)?;
+        block_data.remove(PENDING_SAFE_SEARCH_REMOVAL_KEY);
+        write_json_map(&path, &block_data)?;
+    }
+    Ok(())
+}
 
-    if !is_settings_protection_on {
-        return Ok(false);
+fn check_active_window_for_flags() -> Result<bool, String> {
+    let start_time = std::time::Instant::now();
+
+    match flagged_foreground_window(FOREGROUND_WINDOW.as_ref())? {
+        Some(window) => {
+            println!(
+                "check_active_window_for_flags: found flagged title '{}' (pid {}, {:?}) in {:?}",
+                window.title,
+                window.pid,
+                window.exe_path,
+                start_time.elapsed()
+            );
+            Ok(true)
+        }
+        None => Ok(false),
     }
+}
 
-    let app_clone = app_handle.clone();
-    let handle = std::thread::spawn(move || {
-        let interval = Duration::from_secs(2);
-        let sync_interval = Duration::from_secs(90);
-        let mut last_sync = std::time::Instant::now();
-        
-        loop {
-            let loop_start_time = std::time::Instant::now();
-            
-            if stop_flag.load(Ordering::SeqCst) {
-                break;
-            }
+fn get_running_process_names() -> Result<HashSet<String>, String> {
+    if let Ok(g) = RUNNING_PROCESSES.lock() {
+        if let Some(running) = g.as_ref() {
+            return Ok(running.iter().map(|p| p.name.clone()).collect());
+        }
+    }
 
-            if is_pc_idle(2 * 60 * 1000).unwrap_or(false) {
-                std::thread::sleep(interval);
-                continue;
-            }
+    let start_time = std::time::Instant::now();
+    
+    // Remove /V flag - much faster!
+    let output = run_hidden_output("tasklist", &["/FO", "CSV", "/NH"])
+        .map_err(|e| format!("tasklist spawn failed: {}", e))?;
 
-            if last_sync.elapsed() >= sync_interval {
-                let sync_start = std::time::Instant::now();
-                if let Err(e) = perform_sync_recovery(&app_clone) {
-                    eprintln!("protection thread: sync/recovery failed: {}", e);
-                }
-                last_sync = std::time::Instant::now();
-            }
+    if !output.status.success() {
+        return Err("tasklist failed".into());
+    }
 
-            let is_dns_protection_on = read_preferences_for_key(&app_clone, "enableProtectiveDNS").unwrap_or(false);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut set = HashSet::new();
 
-            let mut has_flagged = false;
-            
-            if !has_flagged {
-                let titles_start = std::time::Instant::now();
-                if check_active_window_for_flags().unwrap_or(false) {
-                    let arguments = serde_json::json!({
-                        "displayName": UNINSTALL_OVERLAY_DISPLAY,
-                        "processName": "uninstaller",
-                        "code" : "uninstaller-window-detected"
-                    });
-                    let _ = show_overlay(&app_clone, arguments);
-                    has_flagged = true;
-                }
+    for line in stdout.lines() {
+        if line.trim().is_empty() { continue; }
+        
+        let fields = parse_csv_line(line);
+        if !fields.is_empty() {
+            let process_name = fields[0].trim_matches('"').to_lowercase();
+            if !process_name.is_empty() {
+                set.insert(process_name);
             }
+        }
+    }
 
-            if !has_flagged {
-                let processes_start = std::time::Instant::now();
-                match get_running_process_names() {
-                    Ok(running) => {
-                        let process_check_start = std::time::Instant::now();
-                        has_flagged = flag_protected_apps(app_clone.clone(), running.clone()).unwrap_or(false);
+    let duration = start_time.elapsed();
+    println!("get_running_process_names: completed in {:?} ({} processes)", duration, set.len());
+    Ok(set)
+}
 
-                        if !has_flagged {
-                            has_flagged = flag_blocked_apps(app_clone.clone(), running.clone()).unwrap_or(false);
-                        }
-                        
-                        if !has_flagged && is_dns_protection_on {
-                            has_flagged = flag_proxies_and_dns_bypassers(app_clone.clone(), running.clone()).unwrap_or(false);
-                        }
-                        
-                        if !has_flagged && is_supported_browser_running(running.clone()).unwrap_or(false) {
-                            has_flagged = flag_vpn_proxy(app_clone.clone(), running.clone()).unwrap_or(false);
-                        }
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                if in_quotes {
+                    if matches!(chars.peek(), Some('"')) {
+                        cur.push('"');
+                        let _ = chars.next();
+                    } else {
+                        in_quotes = false;
                     }
-                    Err(e) => eprintln!("protection thread: getting the running processes call failed: {}", e),
+                } else {
+                    in_quotes = true;
                 }
             }
-
-            if has_flagged == false {
-                close_overlay_window(app_clone.clone());
+            ',' if !in_quotes => {
+                out.push(cur.trim().to_string());
+                cur.clear();
             }
+            _ => cur.push(ch),
+        }
+    }
+    out.push(cur.trim().to_string());
+    out
+}
 
-            let loop_duration = loop_start_time.elapsed();
-            if loop_duration > Duration::from_millis(500) {
-                println!("turn_on_settings_and_app_protection: protection loop iteration took {:?}", loop_duration);
-            }
+fn load_block_data(app_handle: &tauri::AppHandle) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    read_json_map(&path)
+}
 
-            std::thread::sleep(interval);
+fn collect_blocked_apps(map: &serde_json::Map<String, serde_json::Value>) -> Vec<BlockedAppRule> {
+    let mut out = Vec::new();
+    if let Some(serde_json::Value::Array(arr)) = map.get("blockedApps") {
+        for item in arr.iter() {
+            match BlockedAppRule::from_value(item) {
+                Some(rule) => out.push(rule),
+                None => println!("collect_blocked_apps: skipping entry (no processName or identity): {}", item),
+            }
         }
-    });
+    }
+    out
+}
 
-    *guard = Some(handle);
-    let _ = create_eagle_recurring_task();
-    
-    Ok(true)
+fn usage_day(now: &chrono::DateTime<chrono::Local>) -> String {
+    now.format("%Y-%m-%d").to_string()
 }
 
-fn extract_vpn_extension(manifest: &serde_json::Value, manifest_text: &str, manifest_path: &PathBuf, browser_name: &str) -> Option<serde_json::Value> {
-    let has_proxy = manifest.get("permissions")
-        .and_then(|p| p.as_array())
-        .map(|a| {
-            a.iter().any(|v| {
-                v.as_str().map(|s| s == "proxy" || s == "webRequest").unwrap_or(false)
-            })
-        })
-        .unwrap_or(false);
+/// Runs `f` on the usage ledger, loading it from disk on first use.
+fn with_app_usage<T>(app_handle: &tauri::AppHandle, f: impl FnOnce(&mut UsageLedger) -> T) -> Result<T, String> {
+    let mut guard = APP_USAGE.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        let path = get_app_file_path(app_handle, USAGE_FILE)?;
+        let ledger = match serde_json::from_value(Value::Object(read_json_map(&path)?)) {
+            Ok(ledger) => ledger,
+            Err(e) => {
+                eprintln!("with_app_usage: ignoring unreadable usage file: {}", e);
+                UsageLedger::default()
+            }
+        };
+        *guard = Some(ledger);
+    }
+    match guard.as_mut() {
+        Some(ledger) => Ok(f(ledger)),
+        None => Err("usage ledger not loaded".to_string()),
+    }
+}
 
-    if !has_proxy {
-        return None;
+fn save_app_usage(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let ledger = with_app_usage(app_handle, |ledger| {
+        ledger.prune();
+        ledger.clone()
+    })?;
+    let path = get_app_file_path(app_handle, USAGE_FILE)?;
+    match serde_json::to_value(&ledger) {
+        Ok(Value::Object(map)) => write_json_map(&path, &map),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("failed to serialize app usage: {}", e)),
     }
+}
 
-    let ext_id = manifest_path.parent()
-        .and_then(|p| p.file_name())
-        .and_then(|n| n.to_str())
-        .unwrap_or("Unknown")
-        .to_string();
+/// Whether a rule's usage is tracked against a daily quota. Usage is
+/// counted under the rule's process name.
+fn is_quota_rule(rule: &BlockedAppRule) -> bool {
+    rule.daily_quota_minutes.is_some() && !rule.process_name.is_empty()
+}
 
-    let name = manifest.get("name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Unknown")
-        .to_string();
-
-    let version = manifest.get("version")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Unknown")
-        .to_string();
+/// Adds `elapsed` to today's usage of every running app that has a quota.
+/// The process monitor's matches are used when it runs, so a renamed
+/// executable is still charged; otherwise apps are matched by image name.
+fn record_app_usage(app_handle: &tauri::AppHandle, running: &HashSet<String>, elapsed: Duration) -> Result<(), String> {
+    if elapsed.is_zero() {
+        return Ok(());
+    }
 
-    let homepage = manifest.get("homepage_url")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
+    let matched = QUOTA_APPS_RUNNING.lock().ok().and_then(|g| g.clone());
+    let candidates: Vec<String> = match matched {
+        Some(matched) => matched.into_iter().collect(),
+        None => {
+            let block_data = load_block_data_cached(app_handle)?;
+            collect_blocked_apps(&block_data)
+                .into_iter()
+                .filter(|r| is_quota_rule(r) && process_matches_running(&r.process_name, running))
+                .map(|r| r.process_name)
+                .collect()
+        }
+    };
+    let mut counted: HashSet<String> = HashSet::new();
+    let names: Vec<String> = candidates.into_iter().filter(|name| counted.insert(usage_key(name))).collect();
+    if names.is_empty() {
+        return Ok(());
+    }
 
-    let description = manifest.get("description")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
+    let day = usage_day(&chrono::Local::now());
+    with_app_usage(app_handle, |ledger| {
+        for name in names.iter() {
+            ledger.record(&day, name, elapsed);
+        }
+    })
+}
 
-    let manifest_lower = manifest_text.to_lowercase();
-    
-    let is_vpn = manifest_lower.contains("veepn") 
-        || manifest_lower.contains("vpn")
-        || homepage.to_lowercase().contains("veepn")
-        || homepage.to_lowercase().contains("vpn")
-        || name.to_lowercase().contains("vpn")
-        || description.to_lowercase().contains("vpn");
+/// Whether a rule blocks its app right now: inside its schedule and, for
+/// apps with a daily quota, once today's quota is used up.
+fn blocked_app_in_force(rule: &BlockedAppRule, now: &chrono::DateTime<chrono::Local>, usage: &UsageLedger) -> bool {
+    if !is_active(&rule.schedules, now) {
+        return false;
+    }
+    match rule.daily_quota() {
+        Some(quota) => is_exhausted(quota, usage.used(&usage_day(now), &rule.process_name)),
+        None => true,
+    }
+}
 
-    if !is_vpn {
-        return None;
+fn blocked_app_overlay(rule: &BlockedAppRule, process_name: &str) -> Value {
+    match rule.daily_quota_minutes {
+        Some(minutes) => json!({
+            "displayName": rule.display_name,
+            "processName": process_name,
+            "code": "quota-exhausted",
+            "quotaMinutes": minutes
+        }),
+        None => json!({
+            "displayName": rule.display_name,
+            "processName": process_name,
+            "code" : "blocked-app"
+        }),
     }
+}
 
-    let permissions = manifest.get("permissions")
-        .and_then(|p| p.as_array())
-        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect::<Vec<_>>())
-        .unwrap_or_default();
+fn process_matches_running(proc_name: &str, running: &std::collections::HashSet<String>) -> bool {
+    running.iter().any(|r| matches_process_name(proc_name, r))
+}
 
-    Some(json!({
-        "id": ext_id,
-        "name": name,
-        "version": version,
-        "homepage_url": homepage,
-        "description": description,
-        "browser": browser_name,
-        "has_proxy_permission": has_proxy,
-        "is_vpn": true,
-        "permissions": permissions,
-        "manifest_path": manifest_path.to_string_lossy().to_string()
-    }))
+fn is_embedded_webview(process_name: &str) -> bool {
+    let p = process_name.to_lowercase();
+    p.contains("msedgewebview2") || p.contains("webview2") || p.contains("bravecrashhandler")
 }
 
-fn run_powershell_hidden(script: &str) -> Result<std::process::Output, String> {
-    let args = [
-        "-NoLogo",
-        "-NoProfile",
-        "-NonInteractive",
-        "-ExecutionPolicy", "Bypass",
-        "-WindowStyle", "Hidden",
-        "-Command",
-        script,
+fn should_flag_window_title(title: &str) -> bool {
+    let title_lower = title.to_lowercase();
+    let flagged_titles = [
+        "task manager", 
+        "task scheduler", 
+        "control panel", 
+        "registry editor", 
+        "hosts",
+        "eagleblocker uninstall", 
+        "apps & features" 
     ];
-    run_hidden_output("powershell.exe", &args)
+    
+    flagged_titles.iter().any(|flagged| title_lower.contains(flagged))
 }
 
-fn detect_vpn_proxy_all_browsers() -> Result<Vec<serde_json::Value>, String> {
-    let ps = r#"
-    $ErrorActionPreference = 'SilentlyContinue'
-    $result = @()
-    $local = $env:LOCALAPPDATA
-    $targets = @(
-        @{ Name='Chrome'; Path=(Join-Path $local 'Google\Chrome\User Data\Default\extensions') },
-        @{ Name='Edge'; Path=(Join-Path $local 'Microsoft\Edge\User Data\Default\extensions') }
-    )
-    foreach($t in $targets){
-        if(Test-Path $t.Path){
-            Get-ChildItem $t.Path -Directory -ErrorAction SilentlyContinue | ForEach-Object {
-                $extId = $_.Name
-                Get-ChildItem $_.FullName -Directory -ErrorAction SilentlyContinue | ForEach-Object {
-                    $mf = Join-Path $_.FullName 'manifest.json'
-                    if(Test-Path $mf){
-                        try {
-                            $j = Get-Content $mf -Raw | ConvertFrom-Json
-                            $perms = @()
-                            if($j.permissions){ $perms = @($j.permissions) }
-                            $hasProxy = $perms -contains 'proxy' -or $perms -contains 'webRequest'
-                            $text = Get-Content $mf -Raw
-                            $name = $j.name
-                            $desc = $j.description
-                            $home = $j.homepage_url
-                            $isVPN = ($text -match '(?i)vpn|veepn') -or ($name -match '(?i)vpn') -or ($desc -match '(?i)vpn') -or ($home -match '(?i)vpn')
-                            if($hasProxy -and $isVPN){
-                                $result += [pscustomobject]@{
-                                    id = $extId
-                                    name = $name
-                                    version = $j.version
-                                    homepage_url = $home
-                                    description = $desc
-                                    permissions = $perms
-                                    browser = $t.Name
-                                    has_proxy_permission = $hasProxy
-                                    is_vpn = $true
-                                    manifest_path = $mf
-                                }
-                            }
-                        } catch {}
-                    }
-                }
-            }
+#[cfg(windows)]
+fn get_idle_millis() -> Result<u64, String> {
+    unsafe {
+        let mut lii = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if !GetLastInputInfo(&mut lii).as_bool() {
+            return Err("GetLastInputInfo failed".into());
         }
+        let now = GetTickCount64();
+        let idle_ms = now.saturating_sub(lii.dwTime as u64);
+        Ok(idle_ms)
     }
+}
 
-    $result | ConvertTo-Json -Compress
-    "#;
+fn is_pc_idle(threshold_ms: u64) -> Result<bool, String> {
+    #[cfg(windows)]
+    {
+        let idle = get_idle_millis()?;
+        return Ok(idle >= threshold_ms);
+    }
+    #[cfg(not(windows))]
+    {
+        Ok(false)
+    }
+}
 
-    if let Ok(out) = run_powershell_hidden(ps) {
-        if out.status.success() {
-            let mut s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            if s.starts_with('\u{feff}') {
-                s = s.trim_start_matches('\u{feff}').to_string();
-            }
-            if s.starts_with('[') {
-                if let Ok(parsed) = serde_json::from_str::<Vec<serde_json::Value>>(&s) {
-                    if !parsed.is_empty() {
-                        return Ok(parsed);
-                    }
-                }
-            } else if s.starts_with('{') {
-                if let Ok(one) = serde_json::from_str::<serde_json::Value>(&s) {
-                    return Ok(vec![one]);
-                }
-            }
+fn flag_protected_apps(app_handle: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
+    for (display, procs) in PROTECTED_SYSTEM_APPS.iter() {
+        if procs.iter().any(|p| running.contains(*p)) {
+            let arguments = serde_json::json!({
+                "displayName": display,
+                "processName": procs[0],
+                "code" : "protected-system-app"
+            });
+
+            let _ = show_overlay(&app_handle, arguments);
+            return Ok(true);
         }
     }
+    
+    Ok(false)
+}
 
-    let base = std::env::var_os("LOCALAPPDATA")
-        .map(PathBuf::from)
-        .ok_or("LOCALAPPDATA not found")?;
-    let appdata_base = std::env::var_os("APPDATA")
-        .map(PathBuf::from)
-        .ok_or("APPDATA not found")?;
-    let mut vpn_extensions = Vec::new();
-    let browser_paths = vec![
-        ("Chrome", base.clone(), "Google\\Chrome\\User Data\\Default\\extensions"),
-        ("Edge", base.clone(), "Microsoft\\Edge\\User Data\\Default\\extensions"),
-    ];
+fn flag_blocked_apps(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
+    // While the process monitor runs it owns blocked-app enforcement,
+    // including rules that need more than the image name.
+    let monitor_running = RUNNING_PROCESSES.lock().map(|g| g.is_some()).unwrap_or(false);
+    if monitor_running {
+        let overlay = BLOCKED_APP_OVERLAY.lock().map_err(|e| e.to_string())?.clone();
+        return match overlay {
+            Some(arguments) => {
+                let _ = show_overlay(&app_clone, arguments);
+                Ok(true)
+            }
+            None => Ok(false),
+        };
+    }
 
-    for (browser_name, base_path, rel_path) in browser_paths {
-        let mut full_path = base_path.clone();
-        full_path.push(rel_path);
-        if !full_path.exists() { continue; }
-        if let Ok(entries) = fs::read_dir(&full_path) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if !path.is_dir() { continue; }
-                if let Ok(version_entries) = fs::read_dir(&path) {
-                    for version_entry in version_entries.flatten() {
-                        let version_path = version_entry.path();
-                        let manifest_path = version_path.join("manifest.json");
-                        if manifest_path.exists() {
-                            if let Ok(manifest_text) = fs::read_to_string(&manifest_path) {
-                                if let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&manifest_text) {
-                                    if let Some(vpn_ext) = extract_vpn_extension(&manifest, &manifest_text, &manifest_path, browser_name) {
-                                        vpn_extensions.push(vpn_ext);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+    if let Ok(block_map) = load_block_data(&app_clone) {
+        let blocked_apps = collect_blocked_apps(&block_map);
+        let now = chrono::Local::now();
+        let usage = with_app_usage(&app_clone, |u| u.clone()).unwrap_or_default();
+        for rule in blocked_apps.iter().filter(|r| blocked_app_in_force(r, &now, &usage)) {
+            if !rule.process_name.is_empty() && process_matches_running(&rule.process_name, &running) {
+                let _ = show_overlay(&app_clone, blocked_app_overlay(rule, &rule.process_name));
+                return Ok(true);
             }
         }
     }
-    Ok(vpn_extensions)
+    Ok(false)
 }
 
-fn perform_sync_recovery(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let app_handle = app_handle.clone();
-    std::thread::spawn(move || {
-        println!("perform_sync_recovery: starting sync/recovery check");
-        let res: Result<(), String> = (|| {
-            let _= create_eagle_recurring_task();
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
 
-            let block_path = get_app_file_path(&app_handle, "blockData.json")?;
-            if !block_path.exists() || is_file_corrupted(&block_path) {
-                restore_default_block_data(&app_handle)?;
-            }
+fn enforcement_notice(rule: &BlockedAppRule, process: &ProcessInfo, action: &str, outcome: &TreeOutcome) -> Value {
+    json!({
+        "displayName": rule.display_name,
+        "processName": process.name,
+        "pid": process.pid,
+        "code": "blocked-app-enforced",
+        "action": action,
+        "outcome": outcome.status(),
+        "error": outcome.first_error().unwrap_or("")
+    })
+}
 
-            let prefs_path = get_app_file_path(&app_handle, "savedPreferences.json")?;
-            if !prefs_path.exists() || is_file_corrupted(&prefs_path) {
-                restore_default_preferences(&app_handle)?;
+/// Carries out the plan for one tick and returns the overlay to show, if any.
+/// A recent kill/suspend report wins over a plain blocked-app overlay.
+fn enforce_blocked_apps(
+    app_handle: &tauri::AppHandle,
+    index: &BlockedProcessIndex,
+    processes: &[ProcessInfo],
+    tracker: &mut EnforcementTracker,
+    kill_deadlines: &mut HashMap<u32, u64>,
+    notice: &mut Option<(Value, std::time::Instant)>,
+) -> Option<Value> {
+    let now = std::time::Instant::now();
+    let blocked = index.blocked();
+    let running: HashSet<u32> = processes.iter().map(|p| p.pid).collect();
+    let plan_input: Vec<BlockedProcess> = blocked
+        .iter()
+        .map(|(process, rule)| BlockedProcess { pid: process.pid, mode: rule.enforcement, kill_after: rule.kill_after() })
+        .collect();
+
+    kill_deadlines.retain(|pid, _| running.contains(pid));
+    let mut overlay: Option<Value> = None;
+
+    for (pid, action) in tracker.plan(&plan_input, &running, now) {
+        if action == EnforcementAction::Resume {
+            if let Err(e) = resume_process(pid) {
+                eprintln!("enforce_blocked_apps: failed to resume {}: {}", pid, e);
             }
+            tracker.mark_resumed(pid);
+            continue;
+        }
 
-            if let Ok(mut guard) = BLOCK_DATA_CACHE.lock() {
-                if guard.is_none() && block_path.exists() {
-                    if let Ok(map) = read_json_map(&block_path) {
-                        *guard = Some(map);
+        let (process, rule) = match blocked.iter().find(|(p, _)| p.pid == pid) {
+            Some(b) => *b,
+            None => continue,
+        };
+
+        match action {
+            EnforcementAction::ShowOverlay { kill_in } => {
+                if overlay.is_none() {
+                    let mut arguments = blocked_app_overlay(rule, &process.name);
+                    arguments["pid"] = json!(process.pid);
+                    if let Some(kill_in) = kill_in {
+                        let kill_at = *kill_deadlines.entry(pid).or_insert_with(|| unix_millis() + kill_in.as_millis() as u64);
+                        arguments["killAt"] = json!(kill_at);
                     }
+                    overlay = Some(arguments);
                 }
             }
+            EnforcementAction::Kill | EnforcementAction::Suspend => {
+                let (name, outcome) = if action == EnforcementAction::Kill {
+                    ("killed", kill_process_tree(pid, processes))
+                } else {
+                    let outcome = suspend_process_tree(pid, processes);
+                    tracker.mark_suspended(pid, &outcome.succeeded);
+                    ("suspended", outcome)
+                };
+                tracker.record_attempt(pid, now);
+
+                println!(
+                    "enforce_blocked_apps: {} {} (pid {}): {} {:?}",
+                    name,
+                    process.name,
+                    pid,
+                    outcome.status(),
+                    outcome.failed
+                );
+                record_audit_event(
+                    app_handle,
+                    "blocked-app-enforced",
+                    json!({
+                        "displayName": rule.display_name,
+                        "processName": process.name,
+                        "exePath": process.exe_path,
+                        "action": name,
+                        "outcome": outcome.status(),
+                        "pids": outcome.succeeded,
+                        "failed": outcome.failed
+                    }),
+                );
+                *notice = Some((enforcement_notice(rule, process, name, &outcome), now));
+            }
+            EnforcementAction::Resume => {}
+        }
+    }
+
+    if matches!(notice, Some((_, at)) if at.elapsed() >= ENFORCEMENT_NOTICE_DURATION) {
+        *notice = None;
+    }
+    match notice.as_ref() {
+        Some((arguments, _)) => Some(arguments.clone()),
+        None => overlay,
+    }
+}
+
+fn run_process_monitor<S: ProcessSource>(app_handle: tauri::AppHandle, mut monitor: ProcessMonitor<S>, stop: Arc<AtomicBool>) {
+    let mut inspector = SystemFileInspector::default();
+    let mut index = BlockedProcessIndex::default();
+    // Quota apps are matched on their own, since they only reach `index`
+    // once the quota is used up but are charged while they run.
+    let mut quota_index = BlockedProcessIndex::default();
+    let mut tracker = EnforcementTracker::default();
+    let mut kill_deadlines: HashMap<u32, u64> = HashMap::new();
+    let mut notice: Option<(Value, std::time::Instant)> = None;
+    let mut shown: Option<Value> = None;
+    let mut rules: Vec<BlockedAppRule> = Vec::new();
+
+    while !stop.load(Ordering::SeqCst) {
+        match monitor.poll() {
+            Ok(events) => {
+                let processes = monitor.running();
+                if let Ok(mut g) = RUNNING_PROCESSES.lock() {
+                    *g = Some(processes.clone());
+                }
+
+                // Keep the previous rules if the block data cannot be read, so
+                // a transient error does not resume suspended apps.
+                match load_block_data_cached(&app_handle) {
+                    Ok(block_map) => rules = collect_blocked_apps(&block_map),
+                    Err(e) => eprintln!("run_process_monitor: failed to load block data: {}", e),
+                }
+                // Rules outside their schedule or under their quota drop out
+                // of the index, which also resumes anything they suspended.
+                let now = chrono::Local::now();
+                let usage = with_app_usage(&app_handle, |u| u.clone()).unwrap_or_default();
+                let active_rules: Vec<BlockedAppRule> =
+                    rules.iter().filter(|r| blocked_app_in_force(r, &now, &usage)).cloned().collect();
+                index.update(active_rules, &events, &processes, &mut inspector);
+                let quota_rules: Vec<BlockedAppRule> =
+                    rules.iter().filter(|r| is_quota_rule(r) && is_active(&r.schedules, &now)).cloned().collect();
+                quota_index.update(quota_rules, &events, &processes, &mut inspector);
+                if let Ok(mut g) = QUOTA_APPS_RUNNING.lock() {
+                    *g = Some(quota_index.blocked().iter().map(|(_, rule)| rule.process_name.clone()).collect());
+                }
+                if let Ok(mut g) = BLOCKED_PIDS.lock() {
+                    *g = index.blocked().iter().map(|(process, _)| process.pid).collect();
+                }
+
+                let overlay = enforce_blocked_apps(
+                    &app_handle,
+                    &index,
+                    &processes,
+                    &mut tracker,
+                    &mut kill_deadlines,
+                    &mut notice,
+                );
+                if let Ok(mut g) = BLOCKED_APP_OVERLAY.lock() {
+                    *g = overlay.clone();
+                }
+
+                // React to launches right away; the protection loop keeps
+                // the overlay up (or closes it) from here on.
+                if overlay.is_some() && overlay != shown {
+                    if let Some(arguments) = overlay.clone() {
+                        let _ = show_overlay(&app_handle, arguments);
+                    }
+                }
+                shown = overlay;
+            }
+            Err(e) => eprintln!("run_process_monitor: poll failed: {}", e),
+        }
+        std::thread::sleep(PROCESS_MONITOR_INTERVAL);
+    }
+
+    for pid in tracker.take_suspended() {
+        if let Err(e) = resume_process(pid) {
+            eprintln!("run_process_monitor: failed to resume {}: {}", pid, e);
+        }
+    }
+    if let Ok(mut g) = BLOCKED_APP_OVERLAY.lock() {
+        *g = None;
+    }
+    if let Ok(mut g) = BLOCKED_PIDS.lock() {
+        g.clear();
+    }
+    if let Ok(mut g) = QUOTA_APPS_RUNNING.lock() {
+        *g = None;
+    }
+    if let Ok(mut g) = RUNNING_PROCESSES.lock() {
+        *g = None;
+    }
+}
+
+fn start_process_monitor_worker(app_handle: tauri::AppHandle) {
+    if let Ok(g) = PROCESS_MONITOR_HANDLE.lock() {
+        if g.is_some() {
+            return;
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Ok(mut gs) = PROCESS_MONITOR_STOP.lock() {
+        *gs = Some(stop.clone());
+    }
+
+    let handle = std::thread::spawn(move || {
+        run_process_monitor(app_handle, ProcessMonitor::new(SysinfoSource::new()), stop);
+    });
+
+    if let Ok(mut gh) = PROCESS_MONITOR_HANDLE.lock() {
+        *gh = Some(handle);
+    }
+}
+
+fn stop_process_monitor_worker() {
+    if let Ok(mut gs) = PROCESS_MONITOR_STOP.lock() {
+        if let Some(flag) = gs.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+
+    if let Ok(mut gh) = PROCESS_MONITOR_HANDLE.lock() {
+        if let Some(h) = gh.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+fn flag_proxies_and_dns_bypassers(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
+    for process_name in UNSUPPORTED_BROWSER_PROCS.iter() {
+        if running.contains(process_name) && !is_embedded_webview(process_name) {
+            let arguments = serde_json::json!({
+                "displayName": process_name,
+                "processName": process_name,
+                "code" : "unsupported_browser"
+            });
+
+            let _ = show_overlay(&app_clone, arguments);
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn is_supported_browser_running(running: HashSet<String>) -> Result<bool, String> {
+    let result = running.contains(&"brave.exe".to_string()) || running.contains(&"chrome.exe".to_string()) || running.contains(&"msedge.exe".to_string());
+    Ok(result)
+}
+
+fn flag_supported_browser_with_vpn_extension(app_clone: tauri::AppHandle, running: HashSet<String>, extensions: Vec<serde_json::Value>) -> Result<bool, String> {
+    let mut browsers: HashSet<String> = HashSet::new();
+
+    for ext in extensions.iter() {
+        let is_vpn = ext.get("is_vpn").and_then(|v| v.as_bool()).unwrap_or(false);
+        let has_proxy = ext.get("has_proxy_permission").and_then(|v| v.as_bool()).unwrap_or(false);
+        if is_vpn && has_proxy {
+            if let Some(bname) = ext.get("browser").and_then(|v| v.as_str()) {
+                browsers.insert(bname.to_string());
+            }
+        }
+    }
+
+    let map_proc = |b: &str| -> Option<(&'static str, &'static str)> {
+        match b {
+            "Chrome" => Some(("Google Chrome", "chrome.exe")),
+            "Edge" => Some(("Microsoft Edge", "msedge.exe")),
+            _ => None,
+        }
+    };
+
+    for b in browsers {
+        if let Some((display, exe)) = map_proc(&b) {
+            if running.contains(&exe.to_lowercase()) {
+                let _ = show_overlay(
+                    &app_clone,
+                    serde_json::json!({
+                        "displayName": display,
+                        "processName": exe,
+                        "code": "browser-with-vpn"
+                    }),
+                );
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn vpn_cache_set(items: Vec<serde_json::Value>) {
+    if let Ok(mut g) = VPN_EXT_CACHE.lock() {
+        *g = Some((items, std::time::Instant::now()));
+    }
+}
+
+fn get_vpn_extensions_cached() -> Vec<serde_json::Value> {
+    let now = std::time::Instant::now();
+    if let Ok(g) = VPN_EXT_CACHE.lock() {
+        if let Some((items, ts)) = g.as_ref() {
+            if now.duration_since(*ts) < VPN_CACHE_TTL {
+                return items.clone();
+            }
+        }
+    }
+    Vec::new()
+}
+
+fn start_vpn_detector_worker() {
+    if let Ok(g) = VPN_WORKER_HANDLE.lock() {
+        if g.is_some() {
+            return;
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Ok(mut gs) = VPN_WORKER_STOP.lock() {
+        *gs = Some(stop.clone());
+    }
+
+    let handle = std::thread::spawn(move || {
+        if let Ok(items) = detect_vpn_proxy_all_browsers() {
+            vpn_cache_set(items);
+        }
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            std::thread::sleep(VPN_CACHE_TTL);
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if let Ok(items) = detect_vpn_proxy_all_browsers() {
+                vpn_cache_set(items);
+            }
+        }
+    });
+
+    if let Ok(mut gh) = VPN_WORKER_HANDLE.lock() {
+        *gh = Some(handle);
+    }
+}
+
+fn stop_vpn_detector_worker() {
+    if let Ok(mut gs) = VPN_WORKER_STOP.lock() {
+        if let Some(flag) = gs.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+    
+    if let Ok(mut gh) = VPN_WORKER_HANDLE.lock() {
+        if let Some(h) = gh.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+fn flag_vpn_proxy(app_clone: tauri::AppHandle, running: HashSet<String>) -> Result<bool, String> {
+    let extensions = get_vpn_extensions_cached();
+    if extensions.is_empty() {
+        return Ok(false);
+    }
+
+    let result = flag_supported_browser_with_vpn_extension(app_clone.clone(), 
+        running.clone(), extensions).unwrap_or(false);
+    Ok(result)
+}
+
+#[tauri::command]
+fn turn_on_settings_and_app_protection(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let start_time = std::time::Instant::now();
+    
+    let mut guard = PROTECTION_HANDLE.lock().map_err(|e| e.to_string())?;
+    if guard.is_some() {
+        return Ok(true);
+    }
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    *PROTECTION_STOP.lock().map_err(|e| e.to_string())? = Some(stop_flag.clone());
+
+    let is_settings_protection_on = read_preferences_for_key(&app_handle.clone(), "blockSettingsSwitch").unwrap_or(false);
+
+    if !is_settings_protection_on {
+        return Ok(false);
+    }
+
+    let app_clone = app_handle.clone();
+    let handle = std::thread::spawn(move || {
+        let interval = Duration::from_secs(2);
+        let sync_interval = Duration::from_secs(90);
+        let mut last_sync = std::time::Instant::now();
+        let mut last_dns_check: Option<std::time::Instant> = None;
+        let mut dns_tampered = false;
+        let mut usage_sampler = UsageSampler::default();
+        let mut last_usage_save = std::time::Instant::now();
+        
+        loop {
+            let loop_start_time = std::time::Instant::now();
+            
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if is_pc_idle(2 * 60 * 1000).unwrap_or(false) {
+                usage_sampler.pause();
+                std::thread::sleep(interval);
+                continue;
+            }
+
+            if last_sync.elapsed() >= sync_interval {
+                let sync_start = std::time::Instant::now();
+                if let Err(e) = perform_sync_recovery(&app_clone) {
+                    eprintln!("protection thread: sync/recovery failed: {}", e);
+                }
+                last_sync = std::time::Instant::now();
+            }
+
+            let is_dns_protection_on = read_preferences_for_key(&app_clone, "enableProtectiveDNS").unwrap_or(false);
+
+            let mut has_flagged = false;
+
+            if !is_dns_protection_on {
+                dns_tampered = false;
+            } else if !matches!(last_dns_check, Some(t) if t.elapsed() < DNS_TAMPER_CHECK_INTERVAL) {
+                dns_tampered = check_dns_tampering(&app_clone, dns_tampered);
+                last_dns_check = Some(std::time::Instant::now());
+            }
+
+            if dns_tampered {
+                let _ = show_overlay(
+                    &app_clone,
+                    serde_json::json!({
+                        "displayName": "Protective DNS",
+                        "processName": "",
+                        "code": "dns-tampered"
+                    }),
+                );
+                has_flagged = true;
+            }
+            
+            if !has_flagged {
+                let titles_start = std::time::Instant::now();
+                if check_active_window_for_flags().unwrap_or(false) {
+                    let arguments = serde_json::json!({
+                        "displayName": UNINSTALL_OVERLAY_DISPLAY,
+                        "processName": "uninstaller",
+                        "code" : "uninstaller-window-detected"
+                    });
+                    let _ = show_overlay(&app_clone, arguments);
+                    has_flagged = true;
+                }
+            }
+
+            if !has_flagged {
+                let processes_start = std::time::Instant::now();
+                match get_running_process_names() {
+                    Ok(running) => {
+                        let process_check_start = std::time::Instant::now();
+                        let elapsed = usage_sampler.sample(std::time::Instant::now());
+                        if let Err(e) = record_app_usage(&app_clone, &running, elapsed) {
+                            eprintln!("protection thread: recording app usage failed: {}", e);
+                        }
+
+                        has_flagged = flag_protected_apps(app_clone.clone(), running.clone()).unwrap_or(false);
+
+                        if !has_flagged {
+                            has_flagged = flag_blocked_apps(app_clone.clone(), running.clone()).unwrap_or(false);
+                        }
+                        
+                        if !has_flagged && is_dns_protection_on {
+                            has_flagged = flag_proxies_and_dns_bypassers(app_clone.clone(), running.clone()).unwrap_or(false);
+                        }
+                        
+                        if !has_flagged && is_supported_browser_running(running.clone()).unwrap_or(false) {
+                            has_flagged = flag_vpn_proxy(app_clone.clone(), running.clone()).unwrap_or(false);
+                        }
+                    }
+                    Err(e) => eprintln!("protection thread: getting the running processes call failed: {}", e),
+                }
+            }
+
+            if has_flagged == false {
+                close_overlay_window(app_clone.clone());
+            }
+
+            if last_usage_save.elapsed() >= USAGE_SAVE_INTERVAL {
+                if let Err(e) = save_app_usage(&app_clone) {
+                    eprintln!("protection thread: saving app usage failed: {}", e);
+                }
+                last_usage_save = std::time::Instant::now();
+            }
+
+            let loop_duration = loop_start_time.elapsed();
+            if loop_duration > Duration::from_millis(500) {
+                println!("turn_on_settings_and_app_protection: protection loop iteration took {:?}", loop_duration);
+            }
+
+            std::thread::sleep(interval);
+        }
+
+        if let Err(e) = save_app_usage(&app_clone) {
+            eprintln!("protection thread: saving app usage failed: {}", e);
+        }
+    });
+
+    *guard = Some(handle);
+    start_process_monitor_worker(app_handle.clone());
+    let _ = create_eagle_recurring_task();
+    
+    Ok(true)
+}
+
+fn extract_vpn_extension(manifest: &serde_json::Value, manifest_text: &str, manifest_path: &PathBuf, browser_name: &str) -> Option<serde_json::Value> {
+    let has_proxy = manifest.get("permissions")
+        .and_then(|p| p.as_array())
+        .map(|a| {
+            a.iter().any(|v| {
+                v.as_str().map(|s| s == "proxy" || s == "webRequest").unwrap_or(false)
+            })
+        })
+        .unwrap_or(false);
+
+    if !has_proxy {
+        return None;
+    }
+
+    let ext_id = manifest_path.parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    let name = manifest.get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    let version = manifest.get("version")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    let homepage = manifest.get("homepage_url")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let description = manifest.get("description")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let manifest_lower = manifest_text.to_lowercase();
+    
+    let is_vpn = manifest_lower.contains("veepn") 
+        || manifest_lower.contains("vpn")
+        || homepage.to_lowercase().contains("veepn")
+        || homepage.to_lowercase().contains("vpn")
+        || name.to_lowercase().contains("vpn")
+        || description.to_lowercase().contains("vpn");
+
+    if !is_vpn {
+        return None;
+    }
+
+    let permissions = manifest.get("permissions")
+        .and_then(|p| p.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    Some(json!({
+        "id": ext_id,
+        "name": name,
+        "version": version,
+        "homepage_url": homepage,
+        "description": description,
+        "browser": browser_name,
+        "has_proxy_permission": has_proxy,
+        "is_vpn": true,
+        "permissions": permissions,
+        "manifest_path": manifest_path.to_string_lossy().to_string()
+    }))
+}
+
+fn run_powershell_hidden(script: &str) -> Result<std::process::Output, String> {
+    let args = [
+        "-NoLogo",
+        "-NoProfile",
+        "-NonInteractive",
+        "-ExecutionPolicy", "Bypass",
+        "-WindowStyle", "Hidden",
+        "-Command",
+        script,
+    ];
+    run_hidden_output("powershell.exe", &args)
+}
+
+fn detect_vpn_proxy_all_browsers() -> Result<Vec<serde_json::Value>, String> {
+    let ps = r#"
+    $ErrorActionPreference = 'SilentlyContinue'
+    $result = @()
+    $local = $env:LOCALAPPDATA
+    $targets = @(
+        @{ Name='Chrome'; Path=(Join-Path $local 'Google\Chrome\User Data\Default\extensions') },
+        @{ Name='Edge'; Path=(Join-Path $local 'Microsoft\Edge\User Data\Default\extensions') }
+    )
+    foreach($t in $targets){
+        if(Test-Path $t.Path){
+            Get-ChildItem $t.Path -Directory -ErrorAction SilentlyContinue | ForEach-Object {
+                $extId = $_.Name
+                Get-ChildItem $_.FullName -Directory -ErrorAction SilentlyContinue | ForEach-Object {
+                    $mf = Join-Path $_.FullName 'manifest.json'
+                    if(Test-Path $mf){
+                        try {
+                            $j = Get-Content $mf -Raw | ConvertFrom-Json
+                            $perms = @()
+                            if($j.permissions){ $perms = @($j.permissions) }
+                            $hasProxy = $perms -contains 'proxy' -or $perms -contains 'webRequest'
+                            $text = Get-Content $mf -Raw
+                            $name = $j.name
+                            $desc = $j.description
+                            $home = $j.homepage_url
+                            $isVPN = ($text -match '(?i)vpn|veepn') -or ($name -match '(?i)vpn') -or ($desc -match '(?i)vpn') -or ($home -match '(?i)vpn')
+                            if($hasProxy -and $isVPN){
+                                $result += [pscustomobject]@{
+                                    id = $extId
+                                    name = $name
+                                    version = $j.version
+                                    homepage_url = $home
+                                    description = $desc
+                                    permissions = $perms
+                                    browser = $t.Name
+                                    has_proxy_permission = $hasProxy
+                                    is_vpn = $true
+                                    manifest_path = $mf
+                                }
+                            }
+                        } catch {}
+                    }
+                }
+            }
+        }
+    }
+
+    $result | ConvertTo-Json -Compress
+    "#;
+
+    if let Ok(out) = run_powershell_hidden(ps) {
+        if out.status.success() {
+            let mut s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+            if s.starts_with('\u{feff}') {
+                s = s.trim_start_matches('\u{feff}').to_string();
+            }
+            if s.starts_with('[') {
+                if let Ok(parsed) = serde_json::from_str::<Vec<serde_json::Value>>(&s) {
+                    if !parsed.is_empty() {
+                        return Ok(parsed);
+                    }
+                }
+            } else if s.starts_with('{') {
+                if let Ok(one) = serde_json::from_str::<serde_json::Value>(&s) {
+                    return Ok(vec![one]);
+                }
+            }
+        }
+    }
+
+    let base = std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .ok_or("LOCALAPPDATA not found")?;
+    let appdata_base = std::env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .ok_or("APPDATA not found")?;
+    let mut vpn_extensions = Vec::new();
+    let browser_paths = vec![
+        ("Chrome", base.clone(), "Google\\Chrome\\User Data\\Default\\extensions"),
+        ("Edge", base.clone(), "Microsoft\\Edge\\User Data\\Default\\extensions"),
+    ];
+
+    for (browser_name, base_path, rel_path) in browser_paths {
+        let mut full_path = base_path.clone();
+        full_path.push(rel_path);
+        if !full_path.exists() { continue; }
+        if let Ok(entries) = fs::read_dir(&full_path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_dir() { continue; }
+                if let Ok(version_entries) = fs::read_dir(&path) {
+                    for version_entry in version_entries.flatten() {
+                        let version_path = version_entry.path();
+                        let manifest_path = version_path.join("manifest.json");
+                        if manifest_path.exists() {
+                            if let Ok(manifest_text) = fs::read_to_string(&manifest_path) {
+                                if let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&manifest_text) {
+                                    if let Some(vpn_ext) = extract_vpn_extension(&manifest, &manifest_text, &manifest_path, browser_name) {
+                                        vpn_extensions.push(vpn_ext);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(vpn_extensions)
+}
+
+fn perform_sync_recovery(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        println!("perform_sync_recovery: starting sync/recovery check");
+        let res: Result<(), String> = (|| {
+            let _= create_eagle_recurring_task();
+
+            let block_path = get_app_file_path(&app_handle, "blockData.json")?;
+            if !block_path.exists() || is_file_corrupted(&block_path) {
+                restore_default_block_data(&app_handle)?;
+            }
+
+            let prefs_path = get_app_file_path(&app_handle, "savedPreferences.json")?;
+            if !prefs_path.exists() || is_file_corrupted(&prefs_path) {
+                restore_default_preferences(&app_handle)?;
+            }
+
+            if let Ok(mut guard) = BLOCK_DATA_CACHE.lock() {
+                if guard.is_none() && block_path.exists() {
+                    if let Ok(map) = read_json_map(&block_path) {
+                        *guard = Some(map);
+                    }
+                }
+            }
+
+            if let Ok(mut guard) = SAVED_PREFERENCES_CACHE.lock() {
+                if guard.is_none() && prefs_path.exists() {
+                    if let Ok(map) = read_json_map(&prefs_path) {
+                        *guard = Some(map);
+                    }
+                }
+            }
+
+            if let Ok(guard) = BLOCK_DATA_CACHE.lock() {
+                if let Some(cached_map) = guard.as_ref() {
+                    match read_json_map(&block_path) {
+                        Ok(stored_map) => {
+                            if &stored_map != cached_map {
+                                write_json_map(&block_path, cached_map)?;
+                            }
+                        }
+                        Err(_) => {
+                            write_json_map(&block_path, cached_map)?;
+                        }
+                    }
+                }
+            }
+
+            if let Ok(guard) = SAVED_PREFERENCES_CACHE.lock() {
+                if let Some(cached_prefs) = guard.as_ref() {
+                    match read_json_map(&prefs_path) {
+                        Ok(stored_prefs) => {
+                            if &stored_prefs != cached_prefs {
+                                write_json_map(&prefs_path, cached_prefs)?;
+                            }
+                        }
+                        Err(_) => {
+                            write_json_map(&prefs_path, cached_prefs)?;
+                        }
+                    }
+                }
+            }
+
+            if let Err(e) = sync_safe_search_addresses(&app_handle) {
+                eprintln!("perform_sync_recovery: safe search refresh failed: {}", e);
+            }
+
+            if let Err(e) = retry_safe_search_removal(&app_handle) {
+                eprintln!("perform_sync_recovery: safe search removal failed: {}", e);
+            }
+
+            if let Err(e) = verify_hosts_integrity(&app_handle) {
+                eprintln!("perform_sync_recovery: hosts integrity check failed: {}", e);
+            }
+
+            Ok(())
+        })();
+
+        if let Err(e) = res {
+            eprintln!("perform_sync_recovery worker failed: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+fn expected_hosts_entries(app_handle: &tauri::AppHandle, block_data: &Map<String, Value>) -> Vec<HostsEntry> {
+    let mut hosts: Vec<String> = Vec::new();
+    if let Some(arr) = block_data.get("blockedWebsites").and_then(|v| v.as_array()) {
+        let now = chrono::Local::now();
+        for item in arr.iter().filter(|item| website_entry_active(item, &now)) {
+            hosts.extend(website_entry_hosts(item));
+        }
+    }
+    hosts.extend(category_hosts_in_use(app_handle, block_data));
+
+    let mut expected: Vec<HostsEntry> = hosts
+        .iter()
+        .flat_map(|host| BLOCK_ADDRESSES.iter().map(move |address| HostsEntry::new(address, host)))
+        .collect();
+
+    if read_preferences_for_key(app_handle, "enforceSafeSearch").unwrap_or(false) {
+        expected.extend(current_safe_search_entries(app_handle));
+    }
+    expected
+}
+
+fn verify_hosts_integrity(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let block_data = load_block_data(app_handle)?;
+    let expected = expected_hosts_entries(app_handle, &block_data);
+
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+    // Versions before the managed section appended the SafeSearch lines to
+    // the end of the file. They are moved into the section once, so they can
+    // be removed again; later passes never take lines outside the section.
+    let migrated = read_preferences_for_key(app_handle, HOSTS_LEGACY_MIGRATED_KEY).unwrap_or(false);
+    let adopted = if migrated { Vec::new() } else { hosts.adopt_legacy_lines() };
+    if !adopted.is_empty() {
+        println!("verify_hosts_integrity: moving {} legacy entries into the managed section", adopted.len());
+    }
+
+    let missing = hosts.restore_missing(&expected);
+    if missing.is_empty() && adopted.is_empty() {
+        if !migrated {
+            mark_hosts_legacy_migrated(app_handle)?;
+        }
+        return Ok(());
+    }
+
+    let missing_lines: Vec<String> = missing.iter().map(|e| e.render()).collect();
+    let adopted_lines: Vec<String> = adopted.iter().map(|e| e.render()).collect();
+    println!("verify_hosts_integrity: {} entries missing from hosts file, restoring", missing.len());
+
+    let result = tauri::async_runtime::block_on(write_hosts_file_elevated(&hosts));
+    record_audit_event(
+        app_handle,
+        "hosts-restored",
+        json!({ "missing": missing_lines, "adopted": adopted_lines, "success": result.is_ok(), "error": result.as_ref().err() }),
+    );
+    result?;
+    if !migrated {
+        mark_hosts_legacy_migrated(app_handle)?;
+    }
+    Ok(())
+}
+
+fn mark_hosts_legacy_migrated(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    // Written directly: save_preference would close any open confirmation dialog.
+    let path = get_app_file_path(app_handle, "savedPreferences.json")?;
+    let mut prefs = read_json_map(&path)?;
+    prefs.insert(HOSTS_LEGACY_MIGRATED_KEY.to_string(), Value::Bool(true));
+    write_json_map(&path, &prefs)
+}
+
+fn restore_default_block_data(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let default_data = serde_json::Map::new();
+    write_json_map(&path, &default_data)?;
+    
+    if let Ok(mut guard) = BLOCK_DATA_CACHE.lock() {
+        *guard = Some(default_data);
+    }
+    
+    Ok(())
+}
+
+fn restore_default_preferences(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "savedPreferences.json")?;
+    let mut default_prefs = serde_json::Map::new();
+
+    default_prefs.insert("delayTimeOut".to_string(), serde_json::Value::Number(serde_json::Number::from(180000u64)));
+    default_prefs.insert("blockSettingsSwitch".to_string(), serde_json::Value::Bool(false));
+    default_prefs.insert("enableProtectiveDNS".to_string(), serde_json::Value::Bool(false));
+    default_prefs.insert("enforceSafeSearch".to_string(), serde_json::Value::Bool(false));
+    
+    write_json_map(&path, &default_prefs)
+}
+
+fn check_task_schedule_exists() -> Result<(), String> {
+    let output = run_hidden_output("schtasks", &["/Query", "/TN", TASK_NAME])
+        .map_err(|e| format!("failed to query task schedule: {}", e))?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err("task schedule not found".into())
+    }
+}
+
+fn is_file_corrupted(path: &std::path::PathBuf) -> bool {
+    match std::fs::read_to_string(path) {
+        Ok(content) => {
+            serde_json::from_str::<serde_json::Value>(&content).is_err()
+        }
+        Err(_) => true,
+    }
+}
+
+fn resolve_prod_exe_path() -> Result<String, String> {
+    let candidates = [
+        r"C:\Program Files\EagleBlocker\eagleblocker.exe",
+        r"C:\Program Files\EagleBlocker\EagleBlocker.exe",
+        r"C:\Program Files (x86)\EagleBlocker\eagleblocker.exe",
+        r"C:\Program Files (x86)\EagleBlocker\EagleBlocker.exe",
+    ];
+    for c in candidates {
+        let p = PathBuf::from(c);
+        if p.exists() {
+            return Ok(p.to_string_lossy().into_owned());
+        }
+    }
+    resolve_app_exe_path()
+}
+
+fn enable_autostart(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let exe = resolve_app_exe_path()?;
+    let quoted = format!(r#""{}""#, exe);
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (key, _) = hkcu.create_subkey(RUN_KEY).map_err(|e| e.to_string())?;
+    key.set_value(RUN_VALUE_NAME, &quoted).map_err(|e| e.to_string())?;
+
+    let _ = save_preference("autoStart".to_string(), serde_json::Value::Bool(true), app_handle);
+    Ok(true)
+}
+
+fn stop_settings_protection_worker() {
+    if let Ok(mut gs) = PROTECTION_STOP.lock() {
+        if let Some(flag) = gs.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+    
+    if let Ok(mut gh) = PROTECTION_HANDLE.lock() {
+        if let Some(h) = gh.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+#[tauri::command]
+fn stop_settings_and_app_protection() -> Result<bool, String> {
+    let _ = delete_all_eagle_tasks();
+    stop_vpn_detector_worker();
+    stop_process_monitor_worker();
+    stop_settings_protection_worker();
+    Ok(true)
+}
+
+fn read_preferences_for_key(app_handle: &tauri::AppHandle, key: &str) -> Result<bool, String> {
+    let path = get_preferences_path(app_handle)?;
+    let prefs = read_preferences_file(&path)?;
+    Ok(prefs.get(key).and_then(|v| v.as_bool()).unwrap_or(false))
+}
+
+#[tauri::command]
+fn close_app(process_name: String, pid: Option<u32>) -> Result<bool, String> {
+    std::thread::spawn(move || {
+        // A PID the monitor matched to a rule is exact even when the
+        // executable was renamed, and lets us take the whole tree down.
+        // Any other PID from the caller is not trusted with a tree kill.
+        let blocked = pid.filter(|pid| BLOCKED_PIDS.lock().map(|g| g.contains(pid)).unwrap_or(false));
+        let processes = RUNNING_PROCESSES.lock().ok().and_then(|g| g.clone());
+        if let (Some(pid), Some(processes)) = (blocked, processes) {
+            if processes.iter().any(|p| p.pid == pid) {
+                let outcome = kill_process_tree(pid, &processes);
+                println!("close_app: killed tree of {} ({}): {}", pid, process_name, outcome.status());
+                return;
+            }
+        }
+
+        let base = process_name.trim().trim_end_matches(".exe").to_string();
+        let target_exe = format!("{}.exe", base);
+        let _ = run_hidden_output("taskkill", &["/F", "/IM", &target_exe]);
+    });
+    Ok(true)
+}
+
+#[tauri::command]
+fn close_invoking_window(window: tauri::Window) -> Result<(), String> {
+    window.close().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn close_overlay_window(app_handle: tauri::AppHandle){
+    close_window(&app_handle, "overlay_window");
+    OVERLAY_OPEN.store(false, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn close_confirmation_dialog(app_handle: tauri::AppHandle){
+    close_window(&app_handle, "confirmDialog");
+}
+
+fn close_window(app_handle: &tauri::AppHandle, given_label: &str){
+    for (label, win) in app_handle.windows() {
+        if label == given_label {
+            let _ = win.close();
+        }
+    }
+}
+
+#[tauri::command]
+fn show_delay_for_priming_deletion(setting_id: String, app_handle: tauri::AppHandle) {
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "show_delay_for_prime_deletion",
+        serde_json::json!({ "settingId": setting_id }),
+    );
+}
+
+fn build_menu() -> Menu {
+    let home = CustomMenuItem::new("menu-home".to_string(), "Home");
+    let block_apps = CustomMenuItem::new("menu-block-apps".to_string(), "Block Apps");
+    let block_websites = CustomMenuItem::new("menu-block-websites".to_string(), "Block Websites");
+    let settings = CustomMenuItem::new("menu-settings".to_string(), "Settings");
+
+    Menu::new()
+        .add_item(home)
+        .add_item(block_apps)
+        .add_item(block_websites)
+        .add_item(settings)
+}
+
+fn extract_exe_from_icon(icon: &str) -> String {
+    let trimmed = icon.trim().trim_matches('"');
+    let first_part = trimmed.split(',').next().unwrap_or(trimmed).trim();
+
+    if let Some(fname) = std::path::Path::new(first_part)
+        .file_name()
+        .and_then(|os| os.to_str())
+    {
+        return fname.to_string();
+    }
+
+    first_part.split_whitespace().last().unwrap_or("").to_string()
+}
+
+fn parse_exe_from_command(cmd: &str) -> String {
+    let s = cmd.trim().trim_matches('"');
+    let first = s.split_whitespace().next().unwrap_or(s);
+    let first = first.split(',').next().unwrap_or(first).trim().trim_matches('"');
+    std::path::Path::new(first)
+        .file_name()
+        .and_then(|os| os.to_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn looks_like_system_displayname(name: &str) -> bool {
+    let n = name.to_lowercase();
+    n.contains("update") || n.contains("redistributable") || n.contains("runtime") ||
+    n.contains("driver") || n.contains("package") || n.contains("patch") ||
+    n.contains("microsoft visual c++") || n.contains(".net")
+}
+
+fn looks_like_windows_path(p: &str) -> bool {
+    let lp = p.to_lowercase();
+    lp.contains(r"\windows\") || lp.contains(r"\system32\") || lp.contains(r"\program files\windowsapps")
+}
+
+fn usable_install_location(dir: &str) -> Option<String> {
+    let dir = dir.trim().trim_matches('"').trim();
+    if dir.is_empty() || looks_like_windows_path(&format!("{}\\", dir)) || !is_specific_install_dir(dir) {
+        return None;
+    }
+    Some(dir.trim_end_matches('\\').to_string())
+}
+
+fn read_uninstall_entries(base: RegKey) -> Vec<(String, String, String)> {
+    let mut out = Vec::new();
+    if let Ok(subs) = base.enum_keys().collect::<Result<Vec<_>, _>>() {
+        for sub in subs {
+            if let Ok(key) = base.open_subkey(&sub) {
+                let display: Option<String> = key.get_value("DisplayName").ok();
+                if let Some(name) = display {
+                    if looks_like_system_displayname(&name) {
+                        continue;
+                    }
+
+                    if let Ok(sysc) = key.get_value::<u32, _>("SystemComponent") {
+                        if sysc == 1 {
+                            continue;
+                        }
+                    }
+
+                    if let Ok(release_type) = key.get_value::<String, _>("ReleaseType") {
+                        if release_type.to_lowercase().contains("update") {
+                            continue;
+                        }
+                    }
+
+                    let mut process = String::new();
+                    let mut install_location = String::new();
+
+                    if let Ok(loc) = key.get_value::<String, _>("InstallLocation") {
+                        install_location = usable_install_location(&loc).unwrap_or_default();
+                        let p = Path::new(&loc);
+                        if p.is_dir() {
+                            if let Ok(mut entries) = std::fs::read_dir(p).map(|r| r.filter_map(|e| e.ok()).collect::<Vec<_>>()) {
+                                entries.sort_by_key(|e| e.file_name());
+                                if let Some(entry) = entries.into_iter().find(|e| {
+                                    e.path().extension().and_then(|x| x.to_str()).map(|ext| ext.eq_ignore_ascii_case("exe")).unwrap_or(false)
+                                }) {
+                                    process = entry.file_name().to_string_lossy().into_owned();
+                                }
+                            }
+                        }
+                    }
+
+                    if process.is_empty() {
+                        if let Ok(icon) = key.get_value::<String, _>("DisplayIcon") {
+                            let exe = extract_exe_from_icon(&icon);
+                            if !exe.is_empty() && !looks_like_windows_path(&icon) {
+                                process = exe;
+                            }
+                        }
+                    }
+
+                    if process.is_empty() {
+                        if let Ok(uninstall) = key.get_value::<String, _>("UninstallString") {
+                            let exe = parse_exe_from_command(&uninstall);
+                            if !exe.is_empty() && !looks_like_windows_path(&uninstall) {
+                                process = exe;
+                            }
+                        }
+                    }
+
+                    if process.is_empty() {
+                        continue;
+                    }
+                    if looks_like_windows_path(&process) || looks_like_system_displayname(&process) {
+                        continue;
+                    }
+
+                    out.push((name, process, install_location));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn collect_app_paths() -> Vec<(String, String, String)> {
+    let mut out = Vec::new();
+    let roots = [
+        (RegKey::predef(HKEY_LOCAL_MACHINE), r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths"),
+        (RegKey::predef(HKEY_CURRENT_USER), r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths"),
+    ];
+
+    for (root, sub) in &roots {
+        if let Ok(k) = root.open_subkey(sub) {
+            if let Ok(keys) = k.enum_keys().collect::<Result<Vec<_>, _>>() {
+                for keyname in keys {
+                    if let Ok(k2) = k.open_subkey(&keyname) {
+                        if let Ok(pathval) = k2.get_value::<String, _>("").map(|s| s) {
+                            let exe_name = std::path::Path::new(&pathval)
+                                .file_name()
+                                .and_then(|os| os.to_str())
+                                .unwrap_or("")
+                                .to_string();
+
+                            let display = if keyname.to_lowercase().ends_with(".exe") {
+                                keyname.trim_end_matches(".exe").to_string()
+                            } else {
+                                keyname.clone()
+                            };
+                            let install_location = std::path::Path::new(pathval.trim_matches('"'))
+                                .parent()
+                                .and_then(|dir| dir.to_str())
+                                .and_then(usable_install_location)
+                                .unwrap_or_default();
+                            if !exe_name.is_empty() {
+                                out.push((display, exe_name, install_location));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[tauri::command]
+async fn get_all_installed_apps() -> Result<Vec<serde_json::Value>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut apps: Vec<(String, String, String)> = Vec::new();
+
+        if let Ok(hklm) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
+            apps.extend(read_uninstall_entries(hklm));
+        }
+
+        if let Ok(hklm32) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey("SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
+            apps.extend(read_uninstall_entries(hklm32));
+        }
+
+        if let Ok(hkcu) = RegKey::predef(HKEY_CURRENT_USER).open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
+            apps.extend(read_uninstall_entries(hkcu));
+        }
+
+        let app_paths = collect_app_paths();
+        apps.extend(app_paths);
+
+        apps.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+        apps.dedup_by(|a, b| a.0.eq_ignore_ascii_case(&b.0));
+
+        let result: Vec<serde_json::Value> = apps.into_iter()
+            .map(|(display, process, install_location)| {
+                json!({
+                    "displayName": display,
+                    "processName": if process.is_empty() { serde_json::Value::String(String::new()) } else { serde_json::Value::String(process) },
+                    "installLocation": if install_location.is_empty() { serde_json::Value::Null } else { serde_json::Value::String(install_location) }
+                })
+            })
+            .collect();
+
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("spawn_blocking join error: {}", e))?
+}
+
+fn handle_delay_changes(setting_id: String, value: Option<serde_json::Value>,app_handle: tauri::AppHandle) -> Result<(), String> {
+    println!("handle_delay_changes: setting_id='{}', value={:?}", setting_id, value);
+
+    if setting_id == DELAY_SETTINGS {
+        let v = value.unwrap_or(serde_json::Value::Null);
+        println!("handle_delay_changes: saving delayTimeOut = {}", v);
+        save_preference(DELAY_SETTINGS.to_string(), v, app_handle.clone())?;
+    }
+    else if let Some(target) = setting_id.strip_prefix(SCHEDULE_SETTING_PREFIX) {
+        apply_pending_schedule(&app_handle, target)?;
+    }
+    else if let Some(target) = setting_id.strip_prefix(QUOTA_SETTING_PREFIX) {
+        apply_pending_quota(&app_handle, target)?;
+    }
+    else if let Some(name) = setting_id.strip_prefix(PROFILE_SWITCH_SETTING_PREFIX) {
+        expire_profile_switch_delay(&app_handle, name)?;
+    }
+    else if let Some(provider_id) = setting_id.strip_prefix(DNS_PROVIDER_SETTING_PREFIX) {
+        apply_pending_dns_provider(&app_handle, provider_id)?;
+    }
+    else if let Some(provider_id) = setting_id.strip_prefix(DNS_SWITCH_SETTING_PREFIX) {
+        let (app, provider_id) = (app_handle.clone(), provider_id.to_string());
+        std::thread::spawn(move || {
+            if let Err(e) = tauri::async_runtime::block_on(apply_pending_dns_switch(&app, &provider_id)) {
+                eprintln!("handle_delay_changes: failed to switch dns provider to '{}': {}", provider_id, e);
+            }
+        });
+    }
+    else if let Some(id) = setting_id.strip_prefix(FOCUS_END_SETTING_PREFIX) {
+        let session_id = id.parse::<u64>().map_err(|e| format!("malformed focus session id '{}': {}", id, e))?;
+        expire_focus_session(&app_handle, session_id)?;
+    }
+    else if setting_id.contains("-->") {
+        let parts: Vec<&str> = setting_id.splitn(2, "-->").collect();
+        if parts.len() == 2 {
+            let key_in_block_data = parts[0];
+            let item = parts[1];
+
+            let path = get_app_file_path(&app_handle, "blockData.json")?;
+            let mut block_data = read_json_map(&path)?;
+
+            let arr_val = block_data
+                .entry(key_in_block_data.to_string())
+                .or_insert(serde_json::Value::Array(Vec::new()));
+
+            if let serde_json::Value::Array(ref mut arr) = arr_val {
+                let exists = arr.iter().any(|v| v.as_str().map(|s| s == item).unwrap_or(false));
+                if !exists {
+                    arr.push(serde_json::Value::String(item.to_string()));
+                    write_json_map(&path, &block_data)?;
+                    println!("handle_delay_changes: appended '{}' to {}", item, key_in_block_data);
+                } else {
+                    println!("handle_delay_changes: '{}' already present in {}", item, key_in_block_data);
+                }
+            } 
+            else {
+                block_data.insert(
+                    key_in_block_data.to_string(),
+                    serde_json::Value::Array(vec![serde_json::Value::String(item.to_string())]),
+                );
+                write_json_map(&path, &block_data)?;
+                println!("handle_delay_changes: created {} with '{}'", key_in_block_data, item);
+            }
+
+            let _ = tauri::Manager::emit_all(
+                &app_handle,
+                "block-data-updated",
+                serde_json::json!({}),
+            );
+
+        } else {
+            eprintln!("handle_delay_changes: malformed setting_id with delimiter: {}", setting_id);
+        }
+    }
+    else {
+        println!("handle_delay_changes: setting '{}' -> saving false", setting_id);
+        save_preference(setting_id.clone(), serde_json::Value::Bool(false), app_handle.clone())?;
+
+        if setting_id == "enableDnsFilter" {
+            stop_dns_filter_after_restoring_dns(app_handle.clone());
+        } else if setting_id == "enforceSafeSearch" {
+            disable_safe_search(app_handle.clone());
+        }
+    }
+
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "turn-off-setting",
+        serde_json::json!({ "settingId": setting_id }),
+    );
+
+    Ok(())
+}
+
+#[tauri::command]
+fn start_countdown_timer(setting_id: String, remaining_time: Option<u64>, target_timeout: Option<u64>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    if let Some((stop_flag, handle, _end_ts)) = ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.remove(&setting_id){
+        println!("start_countdown_timer: stopping existing timer for '{}'", &setting_id);
+        stop_flag.store(true, Ordering::SeqCst);
+        let _ = handle.join();
+    }
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .map_err(|e| e.to_string())?;
+
+    let effective_delay = remaining_time.unwrap_or(get_delay_time_out(app_handle.clone())?);
+
+    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
+    let mut prefs = read_json_map(&path)?;
+    let existing_entry = prefs
+        .get("timerInfo")
+        .and_then(|v| v.as_object())
+        .and_then(|obj| obj.get(&setting_id))
+        .and_then(|v| v.as_object());
+
+    let mut should_persist = remaining_time.is_none();
+    let mut start_ts_to_use = now_ms;
+
+    if let Some(rem) = remaining_time {
+        if let Some(entry) = existing_entry {
+            if let Some(ts) = entry
+                .get("startTimeStamp")
+                .and_then(|v| v.as_u64())
+                .or_else(|| entry.get("startTimeStamp").and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()))
+            {
+                start_ts_to_use = ts;
+            }
+            println!(
+                "start_countdown_timer: resuming '{}' with {} ms remaining (preserving persisted startTimeStamp)",
+                setting_id, rem
+            );
+        } 
+        else {
+            let saved_delay = get_delay_time_out(app_handle.clone()).unwrap_or(effective_delay);
+            start_ts_to_use = now_ms.saturating_sub(saved_delay.saturating_sub(rem));
+            should_persist = true;
+            println!(
+                "start_countdown_timer: resuming '{}' without existing entry; reconstructing startTimeStamp={}",
+                setting_id, start_ts_to_use
+            );
+        }
+    } else {
+        start_ts_to_use = now_ms;
+    }
 
-            if let Ok(mut guard) = SAVED_PREFERENCES_CACHE.lock() {
-                if guard.is_none() && prefs_path.exists() {
-                    if let Ok(map) = read_json_map(&prefs_path) {
-                        *guard = Some(map);
-                    }
-                }
+    if should_persist {
+        let mut timer_info = prefs.get("timerInfo").cloned().unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+        if let serde_json::Value::Object(ref mut obj) = timer_info {
+            let mut m = serde_json::Map::new();
+            m.insert(
+                "startTimeStamp".to_string(),
+                serde_json::Value::Number(serde_json::Number::from(start_ts_to_use)),
+            );
+            if let Some(tn) = target_timeout {
+                m.insert("targetTimeout".to_string(), serde_json::Value::Number(serde_json::Number::from(tn)));
+            } else {
+                m.insert("targetTimeout".to_string(), serde_json::Value::Null);
             }
+            let configured_timeout_at_change = get_delay_time_out(app_handle.clone()).unwrap_or(effective_delay);
+            m.insert(
+                DELAY_TIMEOUT_KEY.to_string(),
+                serde_json::Value::Number(serde_json::Number::from(configured_timeout_at_change)),
+            );
 
-            if let Ok(guard) = BLOCK_DATA_CACHE.lock() {
-                if let Some(cached_map) = guard.as_ref() {
-                    match read_json_map(&block_path) {
-                        Ok(stored_map) => {
-                            if &stored_map != cached_map {
-                                write_json_map(&block_path, cached_map)?;
-                            }
-                        }
-                        Err(_) => {
-                            write_json_map(&block_path, cached_map)?;
+            obj.insert(setting_id.clone(), serde_json::Value::Object(m));
+            prefs.insert("timerInfo".to_string(), serde_json::Value::Object(obj.clone()));
+        }
+        write_json_map(&path, &prefs)?;
+        println!("start_countdown_timer: persisted timerInfo for '{}'", setting_id);
+    } else {
+        println!("start_countdown_timer: skipping persistence for '{}' (resume)", setting_id);
+    }
+
+    let end_ts = match remaining_time {
+        Some(rem) => now_ms.saturating_add(rem),
+        None => start_ts_to_use.saturating_add(effective_delay),
+    };
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let stop_flag_clone = stop_flag.clone();
+    let app_clone = app_handle.clone();
+    let other_clone = app_handle.clone();
+    let sid = setting_id.clone();
+    let tt_clone = target_timeout;
+
+    let handle = std::thread::spawn(move || {
+        loop {
+            if stop_flag_clone.load(Ordering::SeqCst) {
+                println!("timer thread: '{}' received stop signal", sid);
+                break;
+            }
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+            if now >= end_ts {
+                println!("timer thread: '{}' expired", sid);
+
+                let persisted_target_opt: Option<u64> = get_app_file_path(&app_clone, "savedPreferences.json")
+                    .ok()
+                    .and_then(|p| read_json_map(&p).ok())
+                    .and_then(|prefs_read| {
+                        prefs_read.get("timerInfo")
+                            .and_then(|ti| ti.as_object())
+                            .and_then(|map| map.get(&sid))
+                            .and_then(|entry| entry.get("targetTimeout"))
+                            .and_then(|v| v.as_u64())
+                    });
+
+                if let Ok(p) = get_app_file_path(&app_clone, "savedPreferences.json") {
+                    if let Ok(mut prefs2) = read_json_map(&p) {
+                        if let Some(serde_json::Value::Object(ref mut ti)) = prefs2.get_mut("timerInfo") {
+                            ti.remove(&sid);
                         }
+                        let _ = write_json_map(&p, &prefs2);
                     }
                 }
+
+                if let Err(e) = handle_delay_changes(
+                    sid.clone(),
+                    persisted_target_opt.map(|n| serde_json::Value::Number(serde_json::Number::from(n))),
+                    app_clone.clone()
+                ) {
+                    eprintln!("timer thread: '{}' failed to handle changes: {}", sid, e);
+                }
+
+                let payload = match tt_clone {
+                    Some(n) => serde_json::json!({ "settingId": sid, "targetTimeout": n }),
+                    None => serde_json::json!({ "settingId": sid, "targetTimeout": serde_json::Value::Null }),
+                };
+                let _ = tauri::Manager::emit_all(&app_clone, "timer-expired", payload);
+
+                let _ = ACTIVE_TIMERS.lock().map_err(|e| e.to_string()).and_then(|mut m| { m.remove(&sid); Ok(()) });
+                break;
             }
 
-            if let Ok(guard) = SAVED_PREFERENCES_CACHE.lock() {
-                if let Some(cached_prefs) = guard.as_ref() {
-                    match read_json_map(&prefs_path) {
-                        Ok(stored_prefs) => {
-                            if &stored_prefs != cached_prefs {
-                                write_json_map(&prefs_path, cached_prefs)?;
-                            }
-                        }
-                        Err(_) => {
-                            write_json_map(&prefs_path, cached_prefs)?;
-                        }
-                    }
+            let remaining_ms = end_ts.saturating_sub(now);
+            println!("timer thread: '{}' remaining {}s", sid, remaining_ms / 1000);
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    });
+
+    ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.insert(setting_id, (stop_flag, handle, end_ts));
+    let _ = tauri::Manager::emit_all(&other_clone, "timer-updated", serde_json::json!({}));
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_countdown_timer(setting_id: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    println!("ending countdown timer for {}", setting_id);
+    if let Some((stop_flag, handle, _end_ts)) =
+        ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.remove(&setting_id)
+    {
+        stop_flag.store(true, Ordering::SeqCst);
+        let _ = handle.join();
+    }
+
+    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
+    if let Ok(mut prefs) = read_json_map(&path) {
+        if let Some(serde_json::Value::Object(ref mut ti)) = prefs.get_mut("timerInfo") {
+            ti.remove(&setting_id);
+        }
+        let _ = write_json_map(&path, &prefs);
+    }
+
+    let app_clone = app_handle.clone();
+    let _ = tauri::Manager::emit_all(&app_clone, "timer-updated", serde_json::json!({}));
+
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_change_status(setting_id: String, app_handle: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let current_timeout = get_delay_time_out(app_handle.clone())?;
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .map_err(|e| e.to_string())?;
+
+    match ACTIVE_TIMERS.lock() {
+        Ok(map) => {
+            let _keys: Vec<String> = map.keys().cloned().collect();
+
+            if let Some((_, _, end_ts)) = map.get(&setting_id) {
+                let remaining = if *end_ts > now_ms { *end_ts - now_ms } else { 0u64 };
+                let payload = json!({
+                    "currentTimeout": current_timeout,
+                    "isChanging": true,
+                    "timeRemaining": remaining,
+                    "newValue": serde_json::Value::Null,
+                    "delayTimeOutAtTimeOfChange": serde_json::Value::Null
+                });
+                return Ok(payload);
+            }
+
+            for (k, (_stop, _h, end_ts)) in map.iter() {
+                if k.eq_ignore_ascii_case(&setting_id) {
+                    let remaining = if *end_ts > now_ms { *end_ts - now_ms } else { 0u64 };
+
+                    let delay_at_change = get_app_file_path(&app_handle, "savedPreferences.json")
+                        .ok()
+                        .and_then(|p| read_json_map(&p).ok())
+                        .and_then(|prefs_read| {
+                            prefs_read.get("timerInfo")
+                                .and_then(|ti| ti.as_object())
+                                .and_then(|map| map.get(k))
+                                .and_then(|entry| {
+                                    entry.get(DELAY_TIMEOUT_KEY)
+                                         .cloned()
+                                         .or_else(|| entry.get("delayTimeoutAtTimeOfChange").cloned())
+                                })
+                        })
+                        .unwrap_or(serde_json::Value::Null);
+
+                    let payload = json!({
+                        "currentTimeout": current_timeout,
+                        "isChanging": true,
+                        "timeRemaining": remaining,
+                        "newValue": serde_json::Value::Null,
+                        "delayTimeOutAtTimeOfChange": delay_at_change
+                    });
+                    return Ok(payload);
+                }
+            }
+        }
+        Err(e) => eprintln!("get_change_status: failed to lock ACTIVE_TIMERS: {}", e),
+    }
+
+    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
+    let prefs = read_json_map(&path)?;
+
+    if let Some(serde_json::Value::Object(timer_map)) = prefs.get("timerInfo") {
+        let mut matched_key: Option<String> = None;
+        if timer_map.contains_key(&setting_id) {
+            matched_key = Some(setting_id.clone());
+        } else {
+            for k in timer_map.keys() {
+                if k.eq_ignore_ascii_case(&setting_id) {
+                    matched_key = Some(k.clone());
+                    break;
                 }
             }
+        }
+
+        if let Some(key) = matched_key {
+            if let Some(entry) = timer_map.get(&key) {
+                let start_ts = entry
+                    .get("startTimeStamp")
+                    .and_then(|v| v.as_u64())
+                    .or_else(|| entry.get("startTimeStamp").and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()))
+                    .unwrap_or(now_ms);
+
+                 let delay_ms = entry
+                    .get(DELAY_TIMEOUT_KEY)
+                    .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok())))
+                    .or_else(|| {
+                        entry
+                            .get("delayTimeoutAtTimeOfChange")
+                            .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok())))
+                    })
+                    .unwrap_or(current_timeout);
+
+                let end_ts = start_ts.saturating_add(delay_ms);
+                let remaining = if end_ts > now_ms { end_ts - now_ms } else { 0u64 };
 
-            Ok(())
-        })();
+                let new_value = entry
+                    .get("targetTimeout")
+                    .cloned()
+                    .or_else(|| entry.get("newDelayValue").cloned())
+                    .unwrap_or(serde_json::Value::Null);
 
-        if let Err(e) = res {
-            eprintln!("perform_sync_recovery worker failed: {}", e);
+                let delay_at_change = entry
+                    .get("delayTimeOutAtTimeOfChange")
+                    .cloned()
+                    .or_else(|| entry.get("delayTimeoutAtTimeOfChange").cloned())
+                    .unwrap_or(serde_json::Value::Null);
+
+                let payload = json!({
+                    "currentTimeout": current_timeout,
+                    "isChanging": true,
+                    "timeRemaining": remaining,
+                    "newValue": new_value,
+                    "delayTimeOutAtTimeOfChange": delay_at_change
+                });
+
+                return Ok(payload);
+            }
         }
+    }
+
+    let payload = json!({
+        "currentTimeout": current_timeout,
+        "isChanging": false,
+        "delayTimeOutAtTimeOfChange": serde_json::Value::Null
     });
 
-    Ok(())
+    Ok(payload)
 }
 
-fn restore_default_block_data(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let path = get_app_file_path(app_handle, "blockData.json")?;
-    let default_data = serde_json::Map::new();
-    write_json_map(&path, &default_data)?;
-    
-    if let Ok(mut guard) = BLOCK_DATA_CACHE.lock() {
-        *guard = Some(default_data);
+#[tauri::command]
+fn get_delay_change_status(app_handle: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    get_change_status(DELAY_SETTINGS.to_string(), app_handle)
+}
+
+fn menu_id_to_page(id: &str) -> Option<&'static str> {
+    match id {
+      "menu-home" => Some("index.html"),
+      "menu-block-apps" => Some("blockApps.html"),
+      "menu-block-websites" => Some("blockWebsites.html"),
+      "menu-settings" => Some("delaySettings.html"),
+      _ => None,
     }
-    
-    Ok(())
 }
 
-fn restore_default_preferences(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let path = get_app_file_path(app_handle, "savedPreferences.json")?;
-    let mut default_prefs = serde_json::Map::new();
+fn get_subdomain_variant_prefixes(app_handle: &tauri::AppHandle) -> Vec<String> {
+    let prefs = get_app_file_path(app_handle, "savedPreferences.json")
+        .and_then(|p| read_json_map(&p))
+        .unwrap_or_default();
 
-    default_prefs.insert("delayTimeOut".to_string(), serde_json::Value::Number(serde_json::Number::from(180000u64)));
-    default_prefs.insert("blockSettingsSwitch".to_string(), serde_json::Value::Bool(false));
-    default_prefs.insert("enableProtectiveDNS".to_string(), serde_json::Value::Bool(false));
-    default_prefs.insert("enforceSafeSearch".to_string(), serde_json::Value::Bool(false));
-    
-    write_json_map(&path, &default_prefs)
+    match prefs.get(SUBDOMAIN_VARIANTS_KEY).and_then(|v| v.as_array()) {
+        Some(arr) => arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect(),
+        None => DEFAULT_SUBDOMAIN_VARIANTS.iter().map(|s| s.to_string()).collect(),
+    }
 }
 
-fn check_task_schedule_exists() -> Result<(), String> {
-    let output = run_hidden_output("schtasks", &["/Query", "/TN", TASK_NAME])
-        .map_err(|e| format!("failed to query task schedule: {}", e))?;
-    
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err("task schedule not found".into())
-    }
+fn website_entry_domain(item: &Value) -> Option<&str> {
+    item.as_str().or_else(|| item.get("domain").and_then(|v| v.as_str()))
 }
 
-fn is_file_corrupted(path: &std::path::PathBuf) -> bool {
-    match std::fs::read_to_string(path) {
-        Ok(content) => {
-            serde_json::from_str::<serde_json::Value>(&content).is_err()
-        }
-        Err(_) => true,
+fn website_entry_matches(item: &Value, site: &str) -> bool {
+    match website_entry_domain(item) {
+        Some(domain) => domain == site || normalize_domain(domain).map(|d| d == site).unwrap_or(false),
+        None => false,
     }
 }
 
-fn resolve_prod_exe_path() -> Result<String, String> {
-    let candidates = [
-        r"C:\Program Files\EagleBlocker\eagleblocker.exe",
-        r"C:\Program Files\EagleBlocker\EagleBlocker.exe",
-        r"C:\Program Files (x86)\EagleBlocker\eagleblocker.exe",
-        r"C:\Program Files (x86)\EagleBlocker\EagleBlocker.exe",
-    ];
-    for c in candidates {
-        let p = PathBuf::from(c);
-        if p.exists() {
-            return Ok(p.to_string_lossy().into_owned());
-        }
+fn website_entry_hosts(item: &Value) -> Vec<String> {
+    let mut hosts = Vec::new();
+    if let Some(domain) = website_entry_domain(item) {
+        hosts.push(domain.to_string());
     }
-    resolve_app_exe_path()
+    if let Some(variants) = item.get("variants").and_then(|v| v.as_array()) {
+        hosts.extend(variants.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()));
+    }
+    hosts
 }
 
-fn enable_autostart(app_handle: tauri::AppHandle) -> Result<bool, String> {
-    let exe = resolve_app_exe_path()?;
-    let quoted = format!(r#""{}""#, exe);
+/// Hosts of the blocked websites that are inside their schedule right now.
+fn active_website_hosts(block_data: &Map<String, Value>) -> Vec<String> {
+    let now = chrono::Local::now();
+    block_data
+        .get("blockedWebsites")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter(|item| website_entry_active(item, &now)).flat_map(website_entry_hosts).collect())
+        .unwrap_or_default()
+}
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey(RUN_KEY).map_err(|e| e.to_string())?;
-    key.set_value(RUN_VALUE_NAME, &quoted).map_err(|e| e.to_string())?;
+fn entry_schedules(item: &Value) -> Vec<Schedule> {
+    item.get("schedules")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
 
-    let _ = save_preference("autoStart".to_string(), serde_json::Value::Bool(true), app_handle);
-    Ok(true)
+fn website_entry_active(item: &Value, now: &chrono::DateTime<chrono::Local>) -> bool {
+    is_active(&entry_schedules(item), now)
 }
 
-fn stop_settings_protection_worker() {
-    if let Ok(mut gs) = PROTECTION_STOP.lock() {
-        if let Some(flag) = gs.take() {
-            flag.store(true, Ordering::SeqCst);
+/// Adds the hosts entries of websites inside their schedule and removes
+/// those of scheduled websites outside it, keeping hosts that another
+/// active website or a category still blocks.
+async fn sync_scheduled_websites(app_handle: &tauri::AppHandle) -> Result<bool, String> {
+    let block_data = load_block_data(app_handle)?;
+    let entries = match block_data.get("blockedWebsites").and_then(|v| v.as_array()) {
+        Some(arr) => arr.clone(),
+        None => return Ok(false),
+    };
+
+    let now = chrono::Local::now();
+    let mut keep = category_hosts_in_use(app_handle, &block_data);
+    let mut wanted: Vec<String> = Vec::new();
+    let mut unwanted: Vec<String> = Vec::new();
+    for item in entries.iter() {
+        if website_entry_active(item, &now) {
+            keep.extend(website_entry_hosts(item));
+            wanted.extend(website_entry_hosts(item));
+        } else {
+            unwanted.extend(website_entry_hosts(item));
         }
     }
-    
-    if let Ok(mut gh) = PROTECTION_HANDLE.lock() {
-        if let Some(h) = gh.take() {
-            let _ = h.join();
+
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+    let mut changed = false;
+    for host in wanted.iter() {
+        for address in BLOCK_ADDRESSES.iter() {
+            changed |= hosts.add(address, host);
+        }
+    }
+    for host in unwanted.iter().filter(|h| !keep.contains(*h)) {
+        for address in BLOCK_ADDRESSES.iter() {
+            changed |= hosts.remove(address, host);
         }
     }
-}
 
-#[tauri::command]
-fn stop_settings_and_app_protection() -> Result<bool, String> {
-    let _ = delete_all_eagle_tasks();
-    stop_vpn_detector_worker();
-    stop_settings_protection_worker();
-    Ok(true)
-}
+    if changed {
+        // A canceled elevation prompt must not come back every minute.
+        let desired = hosts.render();
+        let attempt = SCHEDULE_SYNC_BACKOFF
+            .lock()
+            .map(|b| b.should_attempt(&desired, std::time::Instant::now()))
+            .unwrap_or(true);
+        if !attempt {
+            println!("sync_scheduled_websites: last update failed, waiting before asking again");
+            return Ok(false);
+        }
 
-fn read_preferences_for_key(app_handle: &tauri::AppHandle, key: &str) -> Result<bool, String> {
-    let path = get_preferences_path(app_handle)?;
-    let prefs = read_preferences_file(&path)?;
-    Ok(prefs.get(key).and_then(|v| v.as_bool()).unwrap_or(false))
+        println!("sync_scheduled_websites: updating hosts file for website schedules");
+        let result = write_hosts_file_elevated(&hosts).await;
+        if let Ok(mut backoff) = SCHEDULE_SYNC_BACKOFF.lock() {
+            match &result {
+                Ok(()) => backoff.record_success(),
+                Err(_) => backoff.record_failure(desired, std::time::Instant::now()),
+            }
+        }
+        result?;
+    }
+    Ok(changed)
 }
 
-#[tauri::command]
-fn close_app(process_name: String) -> Result<bool, String> {
-    std::thread::spawn(move || {
-        let base = process_name.trim().trim_end_matches(".exe").to_string();
-        let target_exe = format!("{}.exe", base);
-        let _ = run_hidden_output("taskkill", &["/F", "/IM", &target_exe]);
-    });
-    Ok(true)
-}
+fn next_schedule_boundary(block_data: &Map<String, Value>) -> Option<chrono::DateTime<chrono::Local>> {
+    let now = chrono::Local::now();
+    let mut schedules: Vec<Vec<Schedule>> = Vec::new();
+    if let Some(arr) = block_data.get("blockedWebsites").and_then(|v| v.as_array()) {
+        schedules.extend(arr.iter().map(entry_schedules));
+    }
+    schedules.extend(collect_blocked_apps(block_data).into_iter().map(|r| r.schedules));
 
-#[tauri::command]
-fn close_invoking_window(window: tauri::Window) -> Result<(), String> {
-    window.close().map_err(|e| e.to_string())
+    schedules
+        .iter()
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| next_boundary(s, &now))
+        .min()
 }
 
-#[tauri::command]
-fn close_overlay_window(app_handle: tauri::AppHandle){
-    close_window(&app_handle, "overlay_window");
-    OVERLAY_OPEN.store(false, Ordering::SeqCst);
+fn start_schedule_worker(app_handle: tauri::AppHandle) {
+    let mut guard = match SCHEDULE_WORKER_HANDLE.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    if guard.is_some() {
+        return;
+    }
+
+    *guard = Some(std::thread::spawn(move || loop {
+        if let Err(e) = tauri::async_runtime::block_on(sync_scheduled_websites(&app_handle)) {
+            eprintln!("start_schedule_worker: failed to sync scheduled websites: {}", e);
+        }
+
+        // Wake just after the next boundary, and at least every so often
+        // in case the clock or the rules changed.
+        let wait = load_block_data(&app_handle)
+            .ok()
+            .and_then(|block_data| next_schedule_boundary(&block_data))
+            .and_then(|next| (next - chrono::Local::now()).to_std().ok())
+            .map(|d| d + Duration::from_secs(1))
+            .unwrap_or(SCHEDULE_MAX_SLEEP)
+            .min(SCHEDULE_MAX_SLEEP);
+        std::thread::sleep(wait);
+    }));
 }
 
-#[tauri::command]
-fn close_confirmation_dialog(app_handle: tauri::AppHandle){
-    close_window(&app_handle, "confirmDialog");
+fn website_result(site: &str, status: &str, error: Option<String>) -> Value {
+    json!({ "site": site, "status": status, "error": error })
 }
 
-fn close_window(app_handle: &tauri::AppHandle, given_label: &str){
-    for (label, win) in app_handle.windows() {
-        if label == given_label {
-            let _ = win.close();
+fn record_blocked_website(block_data: &mut Map<String, Value>, domain: &str, variants: &[String]) {
+    let key = "blockedWebsites";
+    let entry = json!({ "domain": domain, "variants": variants });
+    let arr_val = block_data
+        .entry(key.to_string())
+        .or_insert(Value::Array(Vec::new()));
+
+    if let Value::Array(ref mut arr) = arr_val {
+        match arr.iter().position(|v| website_entry_matches(v, domain)) {
+            Some(idx) => {
+                // Re-adding only adds variants; schedules and other fields stay.
+                if let Some(fields) = blocked_entry_fields(&mut arr[idx], "website") {
+                    let mut merged = fields.get("variants").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                    for variant in variants {
+                        if !merged.iter().any(|v| v.as_str() == Some(variant.as_str())) {
+                            merged.push(json!(variant));
+                        }
+                    }
+                    fields.insert("variants".to_string(), Value::Array(merged));
+                }
+                println!("record_blocked_website: updated '{}' in {}", domain, key);
+            }
+            None => {
+                arr.push(entry);
+                println!("record_blocked_website: appended '{}' to {}", domain, key);
+            }
         }
+    } else {
+        block_data.insert(key.to_string(), Value::Array(vec![entry]));
+        println!("record_blocked_website: created {} with '{}'", key, domain);
     }
 }
 
-#[tauri::command]
-fn show_delay_for_priming_deletion(setting_id: String, app_handle: tauri::AppHandle) {
+async fn apply_website_blocks(sites: Vec<String>, app_handle: &tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let prefixes = get_subdomain_variant_prefixes(app_handle);
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+
+    let mut results = Vec::new();
+    let mut added: Vec<(String, Vec<String>)> = Vec::new();
+
+    for raw in sites.iter() {
+        let domain = match normalize_domain(raw) {
+            Ok(d) => d,
+            Err(e) => {
+                results.push(website_result(raw.trim(), "invalid", Some(e)));
+                continue;
+            }
+        };
+        if added.iter().any(|(d, _)| *d == domain) {
+            results.push(website_result(&domain, "duplicate", None));
+            continue;
+        }
+
+        let variants = expand_subdomain_variants(&domain, &prefixes);
+        let mut has_added = false;
+        for host in std::iter::once(&domain).chain(variants.iter()) {
+            for address in BLOCK_ADDRESSES.iter() {
+                if hosts.add(address, host) {
+                    has_added = true;
+                }
+            }
+        }
+
+        if has_added {
+            results.push(website_result(&domain, "added", None));
+            added.push((domain, variants));
+        } else {
+            println!("apply_website_blocks: hosts already contains entries for '{}'", domain);
+            results.push(website_result(&domain, "exists", None));
+        }
+    }
+
+    if added.is_empty() {
+        return Ok(results);
+    }
+
+    write_hosts_file_elevated(&hosts).await?;
+    println!("apply_website_blocks: hosts updated successfully ({} sites)", added.len());
+
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    for (domain, variants) in added.iter() {
+        record_blocked_website(&mut block_data, domain, variants);
+    }
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(app_handle, &block_data)?;
+
+    let items: Vec<&String> = added.iter().map(|(d, _)| d).collect();
     let _ = tauri::Manager::emit_all(
-        &app_handle,
-        "show_delay_for_prime_deletion",
-        serde_json::json!({ "settingId": setting_id }),
+        app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "blockedWebsites", "items": items }),
     );
+
+    Ok(results)
 }
 
-fn build_menu() -> Menu {
-    let home = CustomMenuItem::new("menu-home".to_string(), "Home");
-    let block_apps = CustomMenuItem::new("menu-block-apps".to_string(), "Block Apps");
-    let block_websites = CustomMenuItem::new("menu-block-websites".to_string(), "Block Websites");
-    let settings = CustomMenuItem::new("menu-settings".to_string(), "Settings");
+async fn apply_website_unblocks(sites: Vec<String>, app_handle: &tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
 
-    Menu::new()
-        .add_item(home)
-        .add_item(block_apps)
-        .add_item(block_websites)
-        .add_item(settings)
+    let mut results = Vec::new();
+    let mut unblocked: Vec<(String, Vec<String>, bool)> = Vec::new();
+
+    for raw in sites.iter() {
+        // Entries that never normalized (written by older versions) can
+        // still be removed by their stored text.
+        let site = normalize_domain(raw).unwrap_or_else(|_| raw.trim().to_string());
+        if site.is_empty() {
+            results.push(website_result(&site, "invalid", Some("empty site".into())));
+            continue;
+        }
+
+        let hosts_to_remove = block_data
+            .get("blockedWebsites")
+            .and_then(|v| v.as_array())
+            .and_then(|arr| arr.iter().find(|v| website_entry_matches(v, &site)))
+            .map(website_entry_hosts)
+            .unwrap_or_else(|| vec![site.clone()]);
+
+        let mut changed = false;
+        if let Some(Value::Array(ref mut arr)) = block_data.get_mut("blockedWebsites") {
+            let before = arr.len();
+            arr.retain(|v| !website_entry_matches(v, &site));
+            changed |= arr.len() != before;
+        }
+        if let Some(Value::Array(ref mut arr)) = block_data.get_mut("allowedForUnblockWebsites") {
+            let before = arr.len();
+            arr.retain(|v| !website_entry_matches(v, &site));
+            changed |= arr.len() != before;
+        }
+        unblocked.push((site, hosts_to_remove, changed));
+    }
+
+    // Hosts that a category or a website still blocked after this batch
+    // needs, e.g. www.reddit.com for an entry of its own, stay in place.
+    let mut keep = category_hosts_in_use(app_handle, &block_data);
+    keep.extend(active_website_hosts(&block_data));
+
+    let mut removed_sites: Vec<String> = Vec::new();
+    let mut removed_hosts: Vec<String> = Vec::new();
+    let mut hosts_changed = false;
+
+    for (site, hosts_to_remove, changed) in unblocked {
+        let mut removed_any = false;
+        for host in hosts_to_remove.iter().filter(|h| !keep.contains(*h)) {
+            let mut removed_host = false;
+            for address in BLOCK_ADDRESSES.iter() {
+                removed_host |= hosts.remove(address, host);
+            }
+            if removed_host {
+                removed_any = true;
+                removed_hosts.push(host.clone());
+            }
+        }
+        if removed_any {
+            hosts_changed = true;
+        } else {
+            println!("apply_website_unblocks: hosts had no entry for '{}'", site);
+        }
+
+        if removed_any || changed {
+            results.push(website_result(&site, "removed", None));
+            removed_sites.push(site);
+        } else {
+            results.push(website_result(&site, "not-found", None));
+        }
+    }
+
+    if removed_sites.is_empty() {
+        return Ok(results);
+    }
+
+    if hosts_changed {
+        write_hosts_file_elevated(&hosts).await?;
+
+        let after = HostsFile::load(HOSTS_PATH).map_err(|e| format!("failed to read hosts after elevation: {}", e))?;
+        let still_present = removed_hosts
+            .iter()
+            .any(|host| BLOCK_ADDRESSES.iter().any(|address| after.contains(address, host)));
+        if still_present {
+            return Err("hosts file still contains entry after elevated update".into());
+        }
+        println!("apply_website_unblocks: hosts updated successfully ({} sites)", removed_sites.len());
+    }
+
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(
+        app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "blockedWebsites", "items": removed_sites }),
+    );
+
+    Ok(results)
+}
+
+#[tauri::command]
+async fn add_block_website(site: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let results = apply_website_blocks(vec![site], &app_handle).await?;
+    let result = results.first().ok_or("empty site")?;
+
+    match result.get("status").and_then(|v| v.as_str()) {
+        Some("added") => Ok(true),
+        Some("invalid") => Err(result.get("error").and_then(|v| v.as_str()).unwrap_or("invalid site").to_string()),
+        _ => Ok(false),
+    }
 }
 
-fn extract_exe_from_icon(icon: &str) -> String {
-    let trimmed = icon.trim().trim_matches('"');
-    let first_part = trimmed.split(',').next().unwrap_or(trimmed).trim();
+#[tauri::command]
+async fn add_block_websites(sites: Vec<String>, app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    apply_website_blocks(sites, &app_handle).await
+}
 
-    if let Some(fname) = std::path::Path::new(first_part)
-        .file_name()
-        .and_then(|os| os.to_str())
-    {
-        return fname.to_string();
-    }
+#[tauri::command]
+async fn remove_block_website(site: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let results = apply_website_unblocks(vec![site], &app_handle).await?;
+    let result = results.first().ok_or("empty site")?;
 
-    first_part.split_whitespace().last().unwrap_or("").to_string()
+    match result.get("status").and_then(|v| v.as_str()) {
+        Some("invalid") => Err(result.get("error").and_then(|v| v.as_str()).unwrap_or("invalid site").to_string()),
+        _ => Ok(true),
+    }
 }
 
-fn parse_exe_from_command(cmd: &str) -> String {
-    let s = cmd.trim().trim_matches('"');
-    let first = s.split_whitespace().next().unwrap_or(s);
-    let first = first.split(',').next().unwrap_or(first).trim().trim_matches('"');
-    std::path::Path::new(first)
-        .file_name()
-        .and_then(|os| os.to_str())
-        .unwrap_or("")
-        .to_string()
+#[tauri::command]
+async fn remove_block_websites(sites: Vec<String>, app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    apply_website_unblocks(sites, &app_handle).await
 }
 
-fn looks_like_system_displayname(name: &str) -> bool {
-    let n = name.to_lowercase();
-    n.contains("update") || n.contains("redistributable") || n.contains("runtime") ||
-    n.contains("driver") || n.contains("package") || n.contains("patch") ||
-    n.contains("microsoft visual c++") || n.contains(".net")
+fn blocked_website_domains(block_data: &Map<String, Value>) -> Vec<String> {
+    block_data
+        .get("blockedWebsites")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(website_entry_domain).map(|s| s.to_string()).collect())
+        .unwrap_or_default()
 }
 
-fn looks_like_windows_path(p: &str) -> bool {
-    let lp = p.to_lowercase();
-    lp.contains(r"\windows\") || lp.contains(r"\system32\") || lp.contains(r"\program files\windowsapps")
+fn read_blocklist_file(path: &str, format: Option<String>) -> Result<ParsedBlocklist, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("failed to read blocklist {}: {}", path, e))?;
+    let format = match format {
+        Some(name) => BlocklistFormat::from_name(&name)?,
+        None => detect_format(&content),
+    };
+    Ok(parse_blocklist(&content, format))
 }
 
-fn read_uninstall_entries(base: RegKey) -> Vec<(String, String)> {
-    let mut out = Vec::new();
-    if let Ok(subs) = base.enum_keys().collect::<Result<Vec<_>, _>>() {
-        for sub in subs {
-            if let Ok(key) = base.open_subkey(&sub) {
-                let display: Option<String> = key.get_value("DisplayName").ok();
-                if let Some(name) = display {
-                    if looks_like_system_displayname(&name) {
-                        continue;
-                    }
+#[tauri::command]
+fn preview_blocklist_import(path: String, format: Option<String>, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let parsed = read_blocklist_file(&path, format)?;
+    let existing: HashSet<String> = blocked_website_domains(&load_block_data(&app_handle)?).into_iter().collect();
 
-                    if let Ok(sysc) = key.get_value::<u32, _>("SystemComponent") {
-                        if sysc == 1 {
-                            continue;
-                        }
-                    }
+    let (already_blocked, to_add): (Vec<String>, Vec<String>) = parsed
+        .domains
+        .iter()
+        .cloned()
+        .partition(|d| existing.contains(d));
 
-                    if let Ok(release_type) = key.get_value::<String, _>("ReleaseType") {
-                        if release_type.to_lowercase().contains("update") {
-                            continue;
-                        }
-                    }
+    let rejected: Vec<Value> = parsed
+        .rejected
+        .iter()
+        .map(|r| json!({ "lineNumber": r.line_number, "line": r.line, "reason": r.reason }))
+        .collect();
+
+    Ok(json!({
+        "format": parsed.format.name(),
+        "toAdd": to_add,
+        "alreadyBlocked": already_blocked,
+        "rejected": rejected,
+        "duplicates": parsed.duplicates
+    }))
+}
 
-                    let mut process = String::new();
+#[tauri::command]
+async fn import_blocklist(path: String, format: Option<String>, app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let parsed = read_blocklist_file(&path, format)?;
+    println!("import_blocklist: importing {} domains from {}", parsed.domains.len(), path);
+    apply_website_blocks(parsed.domains, &app_handle).await
+}
 
-                    if let Ok(loc) = key.get_value::<String, _>("InstallLocation") {
-                        let p = Path::new(&loc);
-                        if p.is_dir() {
-                            if let Ok(mut entries) = std::fs::read_dir(p).map(|r| r.filter_map(|e| e.ok()).collect::<Vec<_>>()) {
-                                entries.sort_by_key(|e| e.file_name());
-                                if let Some(entry) = entries.into_iter().find(|e| {
-                                    e.path().extension().and_then(|x| x.to_str()).map(|ext| ext.eq_ignore_ascii_case("exe")).unwrap_or(false)
-                                }) {
-                                    process = entry.file_name().to_string_lossy().into_owned();
-                                }
-                            }
-                        }
-                    }
+#[tauri::command]
+fn export_blocklist(path: String, format: String, app_handle: tauri::AppHandle) -> Result<usize, String> {
+    let format = BlocklistFormat::from_name(&format)?;
+    let domains = blocked_website_domains(&load_block_data(&app_handle)?);
 
-                    if process.is_empty() {
-                        if let Ok(icon) = key.get_value::<String, _>("DisplayIcon") {
-                            let exe = extract_exe_from_icon(&icon);
-                            if !exe.is_empty() && !looks_like_windows_path(&icon) {
-                                process = exe;
-                            }
-                        }
-                    }
+    fs::write(&path, render_blocklist(&domains, format))
+        .map_err(|e| format!("failed to write blocklist {}: {}", path, e))?;
 
-                    if process.is_empty() {
-                        if let Ok(uninstall) = key.get_value::<String, _>("UninstallString") {
-                            let exe = parse_exe_from_command(&uninstall);
-                            if !exe.is_empty() && !looks_like_windows_path(&uninstall) {
-                                process = exe;
-                            }
-                        }
-                    }
+    println!("export_blocklist: wrote {} domains to {} as {}", domains.len(), path, format.name());
+    Ok(domains.len())
+}
 
-                    if process.is_empty() {
-                        continue;
-                    }
-                    if looks_like_windows_path(&process) || looks_like_system_displayname(&process) {
-                        continue;
-                    }
+fn enabled_block_categories(block_data: &Map<String, Value>) -> Vec<String> {
+    block_data
+        .get("blockedCategories")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
 
-                    out.push((name, process));
-                }
+fn get_category_domains(app_handle: &tauri::AppHandle, category: &BlockCategory) -> Result<Vec<String>, String> {
+    let path = app_handle
+        .path_resolver()
+        .resolve_resource(category.resource)
+        .ok_or_else(|| format!("category list '{}' is not bundled", category.id))?;
+    load_category_domains(&path)
+}
+
+fn category_hosts_in_use(app_handle: &tauri::AppHandle, block_data: &Map<String, Value>) -> HashSet<String> {
+    let mut in_use = HashSet::new();
+    for id in enabled_block_categories(block_data) {
+        if let Some(category) = find_category(&id) {
+            match get_category_domains(app_handle, category) {
+                Ok(domains) => in_use.extend(domains),
+                Err(e) => eprintln!("category_hosts_in_use: {}", e),
             }
         }
     }
-    out
+    in_use
 }
 
-fn collect_app_paths() -> Vec<(String, String)> {
-    let mut out = Vec::new();
-    let roots = [
-        (RegKey::predef(HKEY_LOCAL_MACHINE), r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths"),
-        (RegKey::predef(HKEY_CURRENT_USER), r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths"),
-    ];
+#[tauri::command]
+fn get_block_categories(app_handle: tauri::AppHandle) -> Result<Vec<Value>, String> {
+    let block_data = load_block_data(&app_handle)?;
+    let enabled = enabled_block_categories(&block_data);
+    let primed: Vec<&str> = block_data
+        .get("allowedForDisableCategories")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
 
-    for (root, sub) in &roots {
-        if let Ok(k) = root.open_subkey(sub) {
-            if let Ok(keys) = k.enum_keys().collect::<Result<Vec<_>, _>>() {
-                for keyname in keys {
-                    if let Ok(k2) = k.open_subkey(&keyname) {
-                        if let Ok(pathval) = k2.get_value::<String, _>("").map(|s| s) {
-                            let exe_name = std::path::Path::new(&pathval)
-                                .file_name()
-                                .and_then(|os| os.to_str())
-                                .unwrap_or("")
-                                .to_string();
+    Ok(BLOCK_CATEGORIES
+        .iter()
+        .map(|c| {
+            json!({
+                "id": c.id,
+                "name": c.name,
+                "enabled": enabled.iter().any(|e| e == c.id),
+                "allowedToDisable": primed.contains(&c.id),
+                "domainCount": get_category_domains(&app_handle, c).map(|d| d.len()).unwrap_or(0)
+            })
+        })
+        .collect())
+}
 
-                            let display = if keyname.to_lowercase().ends_with(".exe") {
-                                keyname.trim_end_matches(".exe").to_string()
-                            } else {
-                                keyname.clone()
-                            };
-                            if !exe_name.is_empty() {
-                                out.push((display, exe_name));
-                            }
-                        }
-                    }
-                }
+#[tauri::command]
+async fn enable_block_category(category: String, app_handle: tauri::AppHandle) -> Result<usize, String> {
+    let category = find_category(&category).ok_or_else(|| format!("unknown category '{}'", category))?;
+    let domains = get_category_domains(&app_handle, category)?;
+
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+
+    let mut added = 0;
+    for domain in domains.iter() {
+        let mut has_added = false;
+        for address in BLOCK_ADDRESSES.iter() {
+            if hosts.add(address, domain) {
+                has_added = true;
             }
         }
+        if has_added {
+            added += 1;
+        }
     }
 
-    out
+    if added > 0 {
+        write_hosts_file_elevated(&hosts).await?;
+        println!("enable_block_category: added {} domains for '{}'", added, category.id);
+    }
+
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let arr_val = block_data
+        .entry("blockedCategories".to_string())
+        .or_insert(Value::Array(Vec::new()));
+
+    if let Value::Array(ref mut arr) = arr_val {
+        if !arr.iter().any(|v| v.as_str() == Some(category.id)) {
+            arr.push(Value::String(category.id.to_string()));
+        }
+    } else {
+        *arr_val = Value::Array(vec![Value::String(category.id.to_string())]);
+    }
+
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(&app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "blockedCategories", "item": category.id }),
+    );
+
+    Ok(added)
 }
 
 #[tauri::command]
-async fn get_all_installed_apps() -> Result<Vec<serde_json::Value>, String> {
-    tauri::async_runtime::spawn_blocking(|| {
-        let mut apps: Vec<(String, String)> = Vec::new();
+async fn disable_block_category(category: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let category = find_category(&category).ok_or_else(|| format!("unknown category '{}'", category))?;
 
-        if let Ok(hklm) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
-            apps.extend(read_uninstall_entries(hklm));
-        }
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
 
-        if let Ok(hklm32) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey("SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
-            apps.extend(read_uninstall_entries(hklm32));
-        }
+    let is_primed = block_data
+        .get("allowedForDisableCategories")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().any(|v| v.as_str() == Some(category.id)))
+        .unwrap_or(false);
+    if !is_primed {
+        return Err("category-not-primed-for-disable".into());
+    }
 
-        if let Ok(hkcu) = RegKey::predef(HKEY_CURRENT_USER).open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
-            apps.extend(read_uninstall_entries(hkcu));
+    for key in ["blockedCategories", "allowedForDisableCategories"] {
+        if let Some(Value::Array(ref mut arr)) = block_data.get_mut(key) {
+            arr.retain(|v| v.as_str() != Some(category.id));
         }
+    }
 
-        let app_paths = collect_app_paths();
-        for (d, p) in app_paths { apps.push((d, p)); }
+    let mut in_use = category_hosts_in_use(&app_handle, &block_data);
+    in_use.extend(active_website_hosts(&block_data));
 
-        apps.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
-        apps.dedup_by(|a, b| a.0.eq_ignore_ascii_case(&b.0));
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+    let mut removed_any = false;
+    for domain in get_category_domains(&app_handle, category)?.iter() {
+        if in_use.contains(domain) {
+            continue;
+        }
+        for address in BLOCK_ADDRESSES.iter() {
+            if hosts.remove(address, domain) {
+                removed_any = true;
+            }
+        }
+    }
 
-        let result: Vec<serde_json::Value> = apps.into_iter()
-            .map(|(display, process)| {
-                json!({
-                    "displayName": display,
-                    "processName": if process.is_empty() { serde_json::Value::String(String::new()) } else { serde_json::Value::String(process) }
-                })
-            })
-            .collect();
+    if removed_any {
+        write_hosts_file_elevated(&hosts).await?;
+        println!("disable_block_category: removed hosts entries for '{}'", category.id);
+    }
 
-        Ok(result)
-    })
-    .await
-    .map_err(|e| format!("spawn_blocking join error: {}", e))?
+    write_json_map(&path, &block_data)?;
+    store_block_data_cached(&app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(
+        &app_handle,
+        "block-data-updated",
+        serde_json::json!({ "key": "blockedCategories", "item": category.id }),
+    );
+
+    Ok(true)
 }
 
-fn handle_delay_changes(setting_id: String, value: Option<serde_json::Value>,app_handle: tauri::AppHandle) -> Result<(), String> {
-    println!("handle_delay_changes: setting_id='{}', value={:?}", setting_id, value);
+#[tauri::command]
+fn prime_for_deletion(item_type: String, name: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let kind = item_type.to_lowercase();
+    let key = match kind.as_str() {
+        "website" => "allowedForUnblockWebsites",
+        "category" => "allowedForDisableCategories",
+        "dns-rule" => "allowedForRemoveDnsRules",
+        _ => "allowedForUnblockApps",
+    };
 
-    if setting_id == DELAY_SETTINGS {
-        let v = value.unwrap_or(serde_json::Value::Null);
-        println!("handle_delay_changes: saving delayTimeOut = {}", v);
-        save_preference(DELAY_SETTINGS.to_string(), v, app_handle.clone())?;
-    }
-    else if setting_id.contains("-->") {
-        let parts: Vec<&str> = setting_id.splitn(2, "-->").collect();
-        if parts.len() == 2 {
-            let key_in_block_data = parts[0];
-            let item = parts[1];
+    let setting_id = format!("{}-->{}", key, name);
 
-            let path = get_app_file_path(&app_handle, "blockData.json")?;
-            let mut block_data = read_json_map(&path)?;
+    println!("prime_for_deletion: priming '{}' for deletion (setting_id='{}')", name, setting_id);
 
-            let arr_val = block_data
-                .entry(key_in_block_data.to_string())
-                .or_insert(serde_json::Value::Array(Vec::new()));
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("prime_for_deletion: failed to start timer: {}", e))?;
 
-            if let serde_json::Value::Array(ref mut arr) = arr_val {
-                let exists = arr.iter().any(|v| v.as_str().map(|s| s == item).unwrap_or(false));
-                if !exists {
-                    arr.push(serde_json::Value::String(item.to_string()));
-                    write_json_map(&path, &block_data)?;
-                    println!("handle_delay_changes: appended '{}' to {}", item, key_in_block_data);
-                } else {
-                    println!("handle_delay_changes: '{}' already present in {}", item, key_in_block_data);
-                }
-            } 
-            else {
-                block_data.insert(
-                    key_in_block_data.to_string(),
-                    serde_json::Value::Array(vec![serde_json::Value::String(item.to_string())]),
-                );
-                write_json_map(&path, &block_data)?;
-                println!("handle_delay_changes: created {} with '{}'", key_in_block_data, item);
-            }
+    Ok(true)
+}
 
-            let _ = tauri::Manager::emit_all(
-                &app_handle,
-                "block-data-updated",
-                serde_json::json!({}),
-            );
+/// Changes how a blocked app is enforced. Only changes that keep the rule
+/// at least as strict are accepted; loosening one means unblocking the app
+/// through the delay and adding it again.
+#[tauri::command]
+fn set_blocked_app_enforcement(
+    process_name: String,
+    mode: EnforcementMode,
+    kill_after_seconds: Option<u64>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
 
-        } else {
-            eprintln!("handle_delay_changes: malformed setting_id with delimiter: {}", setting_id);
-        }
+    let apps = match block_data.get_mut("blockedApps") {
+        Some(Value::Array(apps)) => apps,
+        _ => return Err("blocked-app-not-found".to_string()),
+    };
+
+    let entry = apps
+        .iter_mut()
+        .find(|item| match BlockedAppRule::from_value(item) {
+            Some(rule) => rule.process_name.eq_ignore_ascii_case(process_name.trim()),
+            None => false,
+        })
+        .ok_or_else(|| "blocked-app-not-found".to_string())?;
+
+    let mut rule = BlockedAppRule::from_value(entry).ok_or_else(|| "blocked-app-not-found".to_string())?;
+    if !rule.allows_enforcement_change(mode, kill_after_seconds) {
+        return Err("enforcement-change-requires-delay".to_string());
     }
-    else {
-        println!("handle_delay_changes: setting '{}' -> saving false", setting_id);
-        save_preference(setting_id.clone(), serde_json::Value::Bool(false), app_handle.clone())?;
+
+    rule.enforcement = mode;
+    rule.kill_after_seconds = if mode == EnforcementMode::OverlayThenKill { kill_after_seconds } else { None };
+
+    // Legacy string entries become objects here; unknown keys are kept.
+    let mut updated = match entry {
+        Value::Object(existing) => existing.clone(),
+        _ => Map::new(),
+    };
+    if let Value::Object(fields) = serde_json::to_value(&rule).map_err(|e| format!("failed to serialize rule: {}", e))? {
+        updated.remove("killAfterSeconds");
+        updated.extend(fields);
     }
+    *entry = Value::Object(updated);
 
-    let _ = tauri::Manager::emit_all(
-        &app_handle,
-        "turn-off-setting",
-        serde_json::json!({ "settingId": setting_id }),
-    );
+    write_json_map(&path, &block_data)?;
+    println!("set_blocked_app_enforcement: {} -> {:?} ({:?}s)", process_name, mode, kill_after_seconds);
 
+    let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
     Ok(())
 }
 
-#[tauri::command]
-fn start_countdown_timer(setting_id: String, remaining_time: Option<u64>, target_timeout: Option<u64>, app_handle: tauri::AppHandle) -> Result<(), String> {
-    if let Some((stop_flag, handle, _end_ts)) = ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.remove(&setting_id){
-        println!("start_countdown_timer: stopping existing timer for '{}'", &setting_id);
-        stop_flag.store(true, Ordering::SeqCst);
-        let _ = handle.join();
+fn schedule_target_key(kind: &str, name: &str) -> Result<String, String> {
+    match kind {
+        "app" | "website" => Ok(format!("{}:{}", kind, name.trim())),
+        _ => Err(format!("unknown schedule target '{}'", kind)),
     }
+}
 
-    let now_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .map_err(|e| e.to_string())?;
-
-    let effective_delay = remaining_time.unwrap_or(get_delay_time_out(app_handle.clone())?);
-
-    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
-    let mut prefs = read_json_map(&path)?;
-    let existing_entry = prefs
-        .get("timerInfo")
-        .and_then(|v| v.as_object())
-        .and_then(|obj| obj.get(&setting_id))
-        .and_then(|v| v.as_object());
+fn find_blocked_entry<'a>(block_data: &'a mut Map<String, Value>, kind: &str, name: &str) -> Option<&'a mut Value> {
+    let name = name.trim();
+    let key = if kind == "app" { "blockedApps" } else { "blockedWebsites" };
+    match block_data.get_mut(key) {
+        Some(Value::Array(arr)) => arr.iter_mut().find(|item| match kind {
+            "app" => BlockedAppRule::from_value(item).map(|r| r.process_name.eq_ignore_ascii_case(name)).unwrap_or(false),
+            _ => website_entry_domain(item).map(|d| d.eq_ignore_ascii_case(name)).unwrap_or(false),
+        }),
+        _ => None,
+    }
+}
 
-    let mut should_persist = remaining_time.is_none();
-    let mut start_ts_to_use = now_ms;
+/// The entry's fields; legacy string entries become objects here and other
+/// keys are kept.
+fn blocked_entry_fields<'a>(entry: &'a mut Value, kind: &str) -> Option<&'a mut Map<String, Value>> {
+    if let Value::String(name) = entry {
+        *entry = if kind == "app" {
+            json!({ "processName": name, "displayName": name })
+        } else {
+            json!({ "domain": name, "variants": [] })
+        };
+    }
+    entry.as_object_mut()
+}
 
-    if let Some(rem) = remaining_time {
-        if let Some(entry) = existing_entry {
-            if let Some(ts) = entry
-                .get("startTimeStamp")
-                .and_then(|v| v.as_u64())
-                .or_else(|| entry.get("startTimeStamp").and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()))
-            {
-                start_ts_to_use = ts;
-            }
-            println!(
-                "start_countdown_timer: resuming '{}' with {} ms remaining (preserving persisted startTimeStamp)",
-                setting_id, rem
-            );
-        } 
-        else {
-            let saved_delay = get_delay_time_out(app_handle.clone()).unwrap_or(effective_delay);
-            start_ts_to_use = now_ms.saturating_sub(saved_delay.saturating_sub(rem));
-            should_persist = true;
-            println!(
-                "start_countdown_timer: resuming '{}' without existing entry; reconstructing startTimeStamp={}",
-                setting_id, start_ts_to_use
-            );
+fn set_entry_schedules(entry: &mut Value, kind: &str, schedules: &[Schedule]) {
+    if let Some(fields) = blocked_entry_fields(entry, kind) {
+        if schedules.is_empty() {
+            fields.remove("schedules");
+        } else {
+            fields.insert("schedules".to_string(), json!(schedules));
         }
-    } else {
-        start_ts_to_use = now_ms;
     }
+}
 
-    if should_persist {
-        let mut timer_info = prefs.get("timerInfo").cloned().unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
-        if let serde_json::Value::Object(ref mut obj) = timer_info {
-            let mut m = serde_json::Map::new();
-            m.insert(
-                "startTimeStamp".to_string(),
-                serde_json::Value::Number(serde_json::Number::from(start_ts_to_use)),
-            );
-            if let Some(tn) = target_timeout {
-                m.insert("targetTimeout".to_string(), serde_json::Value::Number(serde_json::Number::from(tn)));
-            } else {
-                m.insert("targetTimeout".to_string(), serde_json::Value::Null);
-            }
-            let configured_timeout_at_change = get_delay_time_out(app_handle.clone()).unwrap_or(effective_delay);
-            m.insert(
-                DELAY_TIMEOUT_KEY.to_string(),
-                serde_json::Value::Number(serde_json::Number::from(configured_timeout_at_change)),
-            );
+fn set_entry_quota(entry: &mut Value, minutes: Option<u64>) {
+    if let Some(fields) = blocked_entry_fields(entry, "app") {
+        match minutes {
+            Some(minutes) => fields.insert("dailyQuotaMinutes".to_string(), json!(minutes)),
+            None => fields.remove("dailyQuotaMinutes"),
+        };
+    }
+}
 
-            obj.insert(setting_id.clone(), serde_json::Value::Object(m));
-            prefs.insert("timerInfo".to_string(), serde_json::Value::Object(obj.clone()));
+fn resync_scheduled_websites(app_handle: &tauri::AppHandle) {
+    let app = app_handle.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = tauri::async_runtime::block_on(sync_scheduled_websites(&app)) {
+            eprintln!("resync_scheduled_websites: {}", e);
         }
-        write_json_map(&path, &prefs)?;
-        println!("start_countdown_timer: persisted timerInfo for '{}'", setting_id);
-    } else {
-        println!("start_countdown_timer: skipping persistence for '{}' (resume)", setting_id);
-    }
+    });
+}
 
-    let end_ts = match remaining_time {
-        Some(rem) => now_ms.saturating_add(rem),
-        None => start_ts_to_use.saturating_add(effective_delay),
-    };
+/// Sets when a blocked app ("app") or website ("website") is enforced from
+/// text such as "Mon-Fri 08:00-18:00"; an empty spec means all the time.
+/// A schedule that enforces less than the current one waits for the delay.
+#[tauri::command]
+fn set_block_schedule(kind: String, name: String, spec: String, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let schedules = parse_schedule_spec(&spec)?;
+    let target = schedule_target_key(&kind, &name)?;
 
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    let stop_flag_clone = stop_flag.clone();
-    let app_clone = app_handle.clone();
-    let other_clone = app_handle.clone();
-    let sid = setting_id.clone();
-    let tt_clone = target_timeout;
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let entry = find_blocked_entry(&mut block_data, &kind, &name).ok_or_else(|| "blocked-item-not-found".to_string())?;
+    let current = entry_schedules(entry);
 
-    let handle = std::thread::spawn(move || {
-        loop {
-            if stop_flag_clone.load(Ordering::SeqCst) {
-                println!("timer thread: '{}' received stop signal", sid);
-                break;
-            }
-            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
-            if now >= end_ts {
-                println!("timer thread: '{}' expired", sid);
+    if covers(&schedules, &current) {
+        set_entry_schedules(entry, &kind, &schedules);
+        if let Some(Value::Object(pending)) = block_data.get_mut(PENDING_SCHEDULES_KEY) {
+            pending.remove(&target);
+        }
+        write_json_map(&path, &block_data)?;
+        println!("set_block_schedule: {} -> '{}'", target, format_schedules(&schedules));
 
-                let persisted_target_opt: Option<u64> = get_app_file_path(&app_clone, "savedPreferences.json")
-                    .ok()
-                    .and_then(|p| read_json_map(&p).ok())
-                    .and_then(|prefs_read| {
-                        prefs_read.get("timerInfo")
-                            .and_then(|ti| ti.as_object())
-                            .and_then(|map| map.get(&sid))
-                            .and_then(|entry| entry.get("targetTimeout"))
-                            .and_then(|v| v.as_u64())
-                    });
+        let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
+        if kind == "website" {
+            resync_scheduled_websites(&app_handle);
+        }
+        return Ok(json!({ "applied": true, "schedule": format_schedules(&schedules) }));
+    }
 
-                if let Ok(p) = get_app_file_path(&app_clone, "savedPreferences.json") {
-                    if let Ok(mut prefs2) = read_json_map(&p) {
-                        if let Some(serde_json::Value::Object(ref mut ti)) = prefs2.get_mut("timerInfo") {
-                            ti.remove(&sid);
-                        }
-                        let _ = write_json_map(&p, &prefs2);
-                    }
-                }
+    let pending = block_data
+        .entry(PENDING_SCHEDULES_KEY.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(pending) = pending {
+        pending.insert(target.clone(), json!(schedules));
+    }
+    write_json_map(&path, &block_data)?;
 
-                if let Err(e) = handle_delay_changes(
-                    sid.clone(),
-                    persisted_target_opt.map(|n| serde_json::Value::Number(serde_json::Number::from(n))),
-                    app_clone.clone()
-                ) {
-                    eprintln!("timer thread: '{}' failed to handle changes: {}", sid, e);
-                }
+    let setting_id = format!("{}{}", SCHEDULE_SETTING_PREFIX, target);
+    println!("set_block_schedule: {} loosens the rule, delaying (setting_id='{}')", target, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("set_block_schedule: failed to start timer: {}", e))?;
 
-                let payload = match tt_clone {
-                    Some(n) => serde_json::json!({ "settingId": sid, "targetTimeout": n }),
-                    None => serde_json::json!({ "settingId": sid, "targetTimeout": serde_json::Value::Null }),
-                };
-                let _ = tauri::Manager::emit_all(&app_clone, "timer-expired", payload);
+    Ok(json!({ "applied": false, "schedule": format_schedules(&schedules), "settingId": setting_id }))
+}
 
-                let _ = ACTIVE_TIMERS.lock().map_err(|e| e.to_string()).and_then(|mut m| { m.remove(&sid); Ok(()) });
-                break;
-            }
+#[tauri::command]
+fn get_block_schedule(kind: String, name: String, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let target = schedule_target_key(&kind, &name)?;
+    let mut block_data = load_block_data(&app_handle)?;
+    let pending: Option<Vec<Schedule>> = block_data
+        .get(PENDING_SCHEDULES_KEY)
+        .and_then(|v| v.get(&target))
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+    let entry = find_blocked_entry(&mut block_data, &kind, &name).ok_or_else(|| "blocked-item-not-found".to_string())?;
+
+    Ok(json!({
+        "schedule": format_schedules(&entry_schedules(entry)),
+        "pending": pending.map(|p| format_schedules(&p)),
+        "settingId": format!("{}{}", SCHEDULE_SETTING_PREFIX, target),
+    }))
+}
 
-            let remaining_ms = end_ts.saturating_sub(now);
-            println!("timer thread: '{}' remaining {}s", sid, remaining_ms / 1000);
-            std::thread::sleep(Duration::from_secs(1));
+/// Applies a schedule that was waiting for the delay to run out.
+fn apply_pending_schedule(app_handle: &tauri::AppHandle, target: &str) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+
+    let pending = match block_data.get_mut(PENDING_SCHEDULES_KEY) {
+        Some(Value::Object(pending)) => pending.remove(target),
+        _ => None,
+    };
+    let schedules: Vec<Schedule> = match pending.and_then(|v| serde_json::from_value(v).ok()) {
+        Some(schedules) => schedules,
+        None => {
+            println!("apply_pending_schedule: nothing pending for '{}'", target);
+            return Ok(());
         }
-    });
+    };
 
-    ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.insert(setting_id, (stop_flag, handle, end_ts));
-    let _ = tauri::Manager::emit_all(&other_clone, "timer-updated", serde_json::json!({}));
+    let (kind, name) = target.split_once(':').ok_or_else(|| format!("malformed schedule target '{}'", target))?;
+    match find_blocked_entry(&mut block_data, kind, name) {
+        Some(entry) => set_entry_schedules(entry, kind, &schedules),
+        None => println!("apply_pending_schedule: '{}' is no longer blocked", target),
+    }
+    write_json_map(&path, &block_data)?;
+    println!("apply_pending_schedule: {} -> '{}'", target, format_schedules(&schedules));
+
+    let _ = tauri::Manager::emit_all(app_handle, "block-data-updated", json!({}));
+    if kind == "website" {
+        resync_scheduled_websites(app_handle);
+    }
     Ok(())
 }
 
+/// Sets how many minutes a day a blocked app may run; `None` blocks it
+/// outright. Adding or raising a quota waits for the delay.
 #[tauri::command]
-fn cancel_countdown_timer(setting_id: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
-    println!("ending countdown timer for {}", setting_id);
-    if let Some((stop_flag, handle, _end_ts)) =
-        ACTIVE_TIMERS.lock().map_err(|e| e.to_string())?.remove(&setting_id)
-    {
-        stop_flag.store(true, Ordering::SeqCst);
-        let _ = handle.join();
+fn set_app_quota(process_name: String, minutes: Option<u64>, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let entry = find_blocked_entry(&mut block_data, "app", &process_name).ok_or_else(|| "blocked-app-not-found".to_string())?;
+    let rule = BlockedAppRule::from_value(entry).ok_or_else(|| "blocked-app-not-found".to_string())?;
+    let target = rule.process_name.to_lowercase();
+
+    if rule.allows_quota_change(minutes) {
+        set_entry_quota(entry, minutes);
+        if let Some(Value::Object(pending)) = block_data.get_mut(PENDING_QUOTAS_KEY) {
+            pending.remove(&target);
+        }
+        write_json_map(&path, &block_data)?;
+        println!("set_app_quota: {} -> {:?} minutes", target, minutes);
+
+        let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
+        return Ok(json!({ "applied": true }));
     }
 
-    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
-    if let Ok(mut prefs) = read_json_map(&path) {
-        if let Some(serde_json::Value::Object(ref mut ti)) = prefs.get_mut("timerInfo") {
-            ti.remove(&setting_id);
-        }
-        let _ = write_json_map(&path, &prefs);
+    let pending = block_data
+        .entry(PENDING_QUOTAS_KEY.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(pending) = pending {
+        pending.insert(target.clone(), json!(minutes));
     }
+    write_json_map(&path, &block_data)?;
 
-    let app_clone = app_handle.clone();
-    let _ = tauri::Manager::emit_all(&app_clone, "timer-updated", serde_json::json!({}));
+    let setting_id = format!("{}{}", QUOTA_SETTING_PREFIX, target);
+    println!("set_app_quota: {} loosens the rule, delaying (setting_id='{}')", target, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("set_app_quota: failed to start timer: {}", e))?;
 
-    Ok(true)
+    Ok(json!({ "applied": false, "settingId": setting_id }))
+}
+
+/// Applies a quota that was waiting for the delay to run out.
+fn apply_pending_quota(app_handle: &tauri::AppHandle, target: &str) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+
+    let minutes = match block_data.get_mut(PENDING_QUOTAS_KEY) {
+        Some(Value::Object(pending)) => pending.remove(target).and_then(|v| v.as_u64()),
+        _ => None,
+    };
+    let minutes = match minutes {
+        Some(minutes) => minutes,
+        None => {
+            println!("apply_pending_quota: nothing pending for '{}'", target);
+            return Ok(());
+        }
+    };
+
+    match find_blocked_entry(&mut block_data, "app", target) {
+        Some(entry) => set_entry_quota(entry, Some(minutes)),
+        None => println!("apply_pending_quota: '{}' is no longer blocked", target),
+    }
+    write_json_map(&path, &block_data)?;
+    println!("apply_pending_quota: {} -> {} minutes", target, minutes);
+
+    let _ = tauri::Manager::emit_all(app_handle, "block-data-updated", json!({}));
+    Ok(())
 }
 
+/// Today's usage of every blocked app that has a daily quota.
 #[tauri::command]
-fn get_change_status(setting_id: String, app_handle: tauri::AppHandle) -> Result<serde_json::Value, String> {
-    let current_timeout = get_delay_time_out(app_handle.clone())?;
-    let now_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .map_err(|e| e.to_string())?;
+fn get_usage_today(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let day = usage_day(&chrono::Local::now());
+    let block_data = load_block_data(&app_handle)?;
+    let usage = with_app_usage(&app_handle, |u| u.clone())?;
+    let pending = block_data.get(PENDING_QUOTAS_KEY).cloned().unwrap_or(Value::Null);
 
-    match ACTIVE_TIMERS.lock() {
-        Ok(map) => {
-            let _keys: Vec<String> = map.keys().cloned().collect();
+    let apps: Vec<Value> = collect_blocked_apps(&block_data)
+        .iter()
+        .filter_map(|rule| {
+            let quota = rule.daily_quota()?;
+            let used = usage.used(&day, &rule.process_name);
+            Some(json!({
+                "processName": rule.process_name,
+                "displayName": rule.display_name,
+                "quotaSeconds": quota.as_secs(),
+                "usedSeconds": used.as_secs(),
+                "remainingSeconds": remaining(quota, used).as_secs(),
+                "exhausted": is_exhausted(quota, used),
+                "pendingMinutes": pending.get(rule.process_name.to_lowercase()),
+            }))
+        })
+        .collect();
 
-            if let Some((_, _, end_ts)) = map.get(&setting_id) {
-                let remaining = if *end_ts > now_ms { *end_ts - now_ms } else { 0u64 };
-                let payload = json!({
-                    "currentTimeout": current_timeout,
-                    "isChanging": true,
-                    "timeRemaining": remaining,
-                    "newValue": serde_json::Value::Null,
-                    "delayTimeOutAtTimeOfChange": serde_json::Value::Null
-                });
-                return Ok(payload);
-            }
+    Ok(json!({ "date": day, "apps": apps }))
+}
 
-            for (k, (_stop, _h, end_ts)) in map.iter() {
-                if k.eq_ignore_ascii_case(&setting_id) {
-                    let remaining = if *end_ts > now_ms { *end_ts - now_ms } else { 0u64 };
+fn read_focus_session(block_data: &Map<String, Value>) -> Option<FocusSession> {
+    block_data.get(FOCUS_SESSION_KEY).and_then(|v| serde_json::from_value(v.clone()).ok())
+}
 
-                    let delay_at_change = get_app_file_path(&app_handle, "savedPreferences.json")
-                        .ok()
-                        .and_then(|p| read_json_map(&p).ok())
-                        .and_then(|prefs_read| {
-                            prefs_read.get("timerInfo")
-                                .and_then(|ti| ti.as_object())
-                                .and_then(|map| map.get(k))
-                                .and_then(|entry| {
-                                    entry.get(DELAY_TIMEOUT_KEY)
-                                         .cloned()
-                                         .or_else(|| entry.get("delayTimeoutAtTimeOfChange").cloned())
-                                })
-                        })
-                        .unwrap_or(serde_json::Value::Null);
+#[tauri::command]
+fn save_focus_profile(name: String, apps: Vec<Value>, websites: Vec<String>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("focus-profile-name-required".to_string());
+    }
+    let websites = websites
+        .iter()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| normalize_domain(s))
+        .collect::<Result<Vec<String>, String>>()?;
+    let profile = serde_json::to_value(FocusProfile { apps, websites })
+        .map_err(|e| format!("failed to serialize focus profile: {}", e))?;
 
-                    let payload = json!({
-                        "currentTimeout": current_timeout,
-                        "isChanging": true,
-                        "timeRemaining": remaining,
-                        "newValue": serde_json::Value::Null,
-                        "delayTimeOutAtTimeOfChange": delay_at_change
-                    });
-                    return Ok(payload);
-                }
-            }
-        }
-        Err(e) => eprintln!("get_change_status: failed to lock ACTIVE_TIMERS: {}", e),
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let profiles = block_data
+        .entry(FOCUS_PROFILES_KEY.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(profiles) = profiles {
+        profiles.insert(name.clone(), profile);
     }
+    write_json_map(&path, &block_data)?;
+    println!("save_focus_profile: saved '{}'", name);
 
-    let path = get_app_file_path(&app_handle, "savedPreferences.json")?;
-    let prefs = read_json_map(&path)?;
+    let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
+    Ok(())
+}
 
-    if let Some(serde_json::Value::Object(timer_map)) = prefs.get("timerInfo") {
-        let mut matched_key: Option<String> = None;
-        if timer_map.contains_key(&setting_id) {
-            matched_key = Some(setting_id.clone());
-        } else {
-            for k in timer_map.keys() {
-                if k.eq_ignore_ascii_case(&setting_id) {
-                    matched_key = Some(k.clone());
-                    break;
+#[tauri::command]
+fn get_focus_profiles(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let block_data = load_block_data(&app_handle)?;
+    Ok(block_data.get(FOCUS_PROFILES_KEY).cloned().unwrap_or_else(|| json!({})))
+}
+
+#[tauri::command]
+fn get_focus_session(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let block_data = load_block_data(&app_handle)?;
+    Ok(match read_focus_session(&block_data) {
+        Some(session) => json!({
+            "session": session,
+            "remainingMs": session.remaining_ms(unix_millis()),
+            "endSettingId": format!("{}{}", FOCUS_END_SETTING_PREFIX, session.started_at),
+        }),
+        None => Value::Null,
+    })
+}
+
+/// Blocks the profile's apps and websites for `duration` minutes. The
+/// session can only be ended early through the delay.
+#[tauri::command]
+async fn start_focus_session(duration: u64, profile: String, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    if duration == 0 {
+        return Err("focus-duration-required".to_string());
+    }
+    // Checked up front: a wrapped end time would start an expired session.
+    let duration_ms = duration
+        .checked_mul(60_000)
+        .filter(|ms| unix_millis().checked_add(*ms).is_some())
+        .ok_or_else(|| "focus-duration-invalid".to_string())?;
+
+    let _start = FOCUS_START_LOCK.lock().await;
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let block_data = read_json_map(&path)?;
+    if read_focus_session(&block_data).is_some() {
+        return Err("focus-session-active".to_string());
+    }
+
+    let profile = profile.trim().to_string();
+    let focus_profile: FocusProfile = block_data
+        .get(FOCUS_PROFILES_KEY)
+        .and_then(|profiles| profiles.get(&profile))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .ok_or_else(|| "focus-profile-not-found".to_string())?;
+
+    let apps = apps_to_add(&focus_profile, &collect_blocked_apps(&block_data));
+    let websites = websites_to_add(&focus_profile, &blocked_website_domains(&block_data));
+
+    // Websites first: the hosts update is the step that can be refused.
+    let mut added_websites: Vec<String> = Vec::new();
+    if !websites.is_empty() {
+        for result in apply_website_blocks(websites, &app_handle).await? {
+            if result.get("status").and_then(|v| v.as_str()) == Some("added") {
+                if let Some(site) = result.get("site").and_then(|v| v.as_str()) {
+                    added_websites.push(site.to_string());
                 }
             }
         }
+    }
 
-        if let Some(key) = matched_key {
-            if let Some(entry) = timer_map.get(&key) {
-                let start_ts = entry
-                    .get("startTimeStamp")
-                    .and_then(|v| v.as_u64())
-                    .or_else(|| entry.get("startTimeStamp").and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()))
-                    .unwrap_or(now_ms);
+    let now = unix_millis();
+    let mut block_data = read_json_map(&path)?;
+    let blocked_apps = block_data
+        .entry("blockedApps".to_string())
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::Array(arr) = blocked_apps {
+        for rule in apps.iter() {
+            let mut value = serde_json::to_value(rule).map_err(|e| format!("failed to serialize rule: {}", e))?;
+            mark_added_rule(&mut value, now);
+            arr.push(value);
+        }
+    }
 
-                 let delay_ms = entry
-                    .get(DELAY_TIMEOUT_KEY)
-                    .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok())))
-                    .or_else(|| {
-                        entry
-                            .get("delayTimeoutAtTimeOfChange")
-                            .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok())))
-                    })
-                    .unwrap_or(current_timeout);
+    let session = FocusSession {
+        profile,
+        started_at: now,
+        ends_at: now.saturating_add(duration_ms),
+        added_apps: apps.into_iter().map(|r| r.process_name).collect(),
+        added_websites,
+    };
+    let session_value = serde_json::to_value(&session).map_err(|e| format!("failed to serialize focus session: {}", e))?;
+    block_data.insert(FOCUS_SESSION_KEY.to_string(), session_value.clone());
+    write_json_map(&path, &block_data)?;
+
+    println!(
+        "start_focus_session: '{}' for {} minutes ({} apps, {} websites added)",
+        session.profile,
+        duration,
+        session.added_apps.len(),
+        session.added_websites.len()
+    );
+    record_audit_event(&app_handle, "focus-session-started", session_value.clone());
+    let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
 
-                let end_ts = start_ts.saturating_add(delay_ms);
-                let remaining = if end_ts > now_ms { end_ts - now_ms } else { 0u64 };
+    start_focus_session_worker(app_handle.clone());
+    Ok(session_value)
+}
 
-                let new_value = entry
-                    .get("targetTimeout")
-                    .cloned()
-                    .or_else(|| entry.get("newDelayValue").cloned())
-                    .unwrap_or(serde_json::Value::Null);
+/// Starts the delay after which the current session ends early.
+#[tauri::command]
+fn request_end_focus_session(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let session = read_focus_session(&load_block_data(&app_handle)?).ok_or_else(|| "no-focus-session".to_string())?;
+    let setting_id = format!("{}{}", FOCUS_END_SETTING_PREFIX, session.started_at);
 
-                let delay_at_change = entry
-                    .get("delayTimeOutAtTimeOfChange")
-                    .cloned()
-                    .or_else(|| entry.get("delayTimeoutAtTimeOfChange").cloned())
-                    .unwrap_or(serde_json::Value::Null);
+    println!("request_end_focus_session: delaying end of '{}' (setting_id='{}')", session.profile, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("request_end_focus_session: failed to start timer: {}", e))?;
 
-                let payload = json!({
-                    "currentTimeout": current_timeout,
-                    "isChanging": true,
-                    "timeRemaining": remaining,
-                    "newValue": new_value,
-                    "delayTimeOutAtTimeOfChange": delay_at_change
-                });
+    Ok(json!({ "settingId": setting_id }))
+}
 
-                return Ok(payload);
-            }
+/// Called when the delay for ending a session early runs out. The session
+/// is marked as expired first so the worker retries if removal fails.
+fn expire_focus_session(app_handle: &tauri::AppHandle, session_id: u64) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    let mut session = match read_focus_session(&block_data) {
+        Some(session) if session.started_at == session_id => session,
+        _ => {
+            println!("expire_focus_session: session {} already ended", session_id);
+            return Ok(());
         }
-    }
+    };
 
-    let payload = json!({
-        "currentTimeout": current_timeout,
-        "isChanging": false,
-        "delayTimeOutAtTimeOfChange": serde_json::Value::Null
-    });
+    session.ends_at = session.ends_at.min(unix_millis());
+    let session_value = serde_json::to_value(&session).map_err(|e| format!("failed to serialize focus session: {}", e))?;
+    block_data.insert(FOCUS_SESSION_KEY.to_string(), session_value);
+    write_json_map(&path, &block_data)?;
 
-    Ok(payload)
+    let app = app_handle.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = tauri::async_runtime::block_on(finish_focus_session(&app, session_id)) {
+            eprintln!("expire_focus_session: {}", e);
+        }
+    });
+    start_focus_session_worker(app_handle.clone());
+    Ok(())
 }
 
-#[tauri::command]
-fn get_delay_change_status(app_handle: tauri::AppHandle) -> Result<serde_json::Value, String> {
-    get_change_status(DELAY_SETTINGS.to_string(), app_handle)
-}
+/// Removes what session `session_id` added and clears it. Returns false if
+/// that session is not the current one, so when the delay and the worker
+/// both end it, only the first does.
+async fn finish_focus_session(app_handle: &tauri::AppHandle, session_id: u64) -> Result<bool, String> {
+    let _finish = FOCUS_START_LOCK.lock().await;
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let session = match read_focus_session(&read_json_map(&path)?) {
+        Some(session) if session.started_at == session_id => session,
+        _ => return Ok(false),
+    };
 
-fn menu_id_to_page(id: &str) -> Option<&'static str> {
-    match id {
-      "menu-home" => Some("index.html"),
-      "menu-block-apps" => Some("blockApps.html"),
-      "menu-block-websites" => Some("blockWebsites.html"),
-      "menu-settings" => Some("delaySettings.html"),
-      _ => None,
+    if !session.added_websites.is_empty() {
+        apply_website_unblocks(session.added_websites.clone(), app_handle).await?;
     }
-}
 
-#[tauri::command]
-async fn add_block_website(site: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
-    let site = site.trim();
-    if site.is_empty() {
-        return Err("empty site".into());
+    let mut block_data = read_json_map(&path)?;
+    if let Some(Value::Array(arr)) = block_data.get_mut("blockedApps") {
+        remove_added_rules(arr, session.started_at);
     }
+    block_data.remove(FOCUS_SESSION_KEY);
+    write_json_map(&path, &block_data)?;
 
-    let entry = format!("127.0.0.1 {}", site);
-    let current = std::fs::read_to_string(HOSTS_PATH).unwrap_or_default();
-    let mut has_added = false;
+    println!("finish_focus_session: '{}' ended", session.profile);
+    record_audit_event(app_handle, "focus-session-ended", json!(session));
+    let _ = tauri::Manager::emit_all(app_handle, "block-data-updated", json!({}));
+    Ok(true)
+}
 
-    if !current.contains(&entry) {
-        let mut tmp_path = env::temp_dir();
-        let suffix = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis().to_string())
-            .unwrap_or_else(|_| "tmp".into());
-        tmp_path.push(format!("eagleblocker_hosts_{}.tmp", suffix));
-        let tmp_path_str = tmp_path.to_string_lossy().into_owned();
+/// Ends the current session when it expires, including one restored after
+/// a restart. Exits once no session is left.
+fn start_focus_session_worker(app_handle: tauri::AppHandle) {
+    let mut guard = match FOCUS_SESSION_HANDLE.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    if guard.is_some() {
+        return;
+    }
+
+    *guard = Some(std::thread::spawn(move || loop {
+        let session = match load_block_data(&app_handle) {
+            Ok(block_data) => read_focus_session(&block_data),
+            Err(e) => {
+                eprintln!("start_focus_session_worker: failed to load block data: {}", e);
+                std::thread::sleep(FOCUS_CHECK_INTERVAL);
+                continue;
+            }
+        };
 
-        println!("add_block_website: writing temp hosts to {}", tmp_path_str);
+        let session = match session {
+            Some(session) => session,
+            None => {
+                // Re-check under the lock so a session started meanwhile is
+                // not left without a worker.
+                let mut guard = match FOCUS_SESSION_HANDLE.lock() {
+                    Ok(g) => g,
+                    Err(_) => break,
+                };
+                let started = load_block_data(&app_handle).map(|b| read_focus_session(&b).is_some()).unwrap_or(false);
+                if !started {
+                    *guard = None;
+                    break;
+                }
+                continue;
+            }
+        };
 
-        let mut f = File::create(&tmp_path).map_err(|e| format!("failed to create temp hosts file: {}", e))?;
-        f.write_all(current.as_bytes()).map_err(|e| format!("failed to write current hosts to temp file: {}", e))?;
-        if !current.ends_with('\n') {
-            f.write_all(b"\n").map_err(|e| format!("failed to write newline to temp file: {}", e))?;
+        let now = unix_millis();
+        if session.is_expired(now) {
+            if let Err(e) = tauri::async_runtime::block_on(finish_focus_session(&app_handle, session.started_at)) {
+                eprintln!("start_focus_session_worker: failed to end session: {}", e);
+                std::thread::sleep(FOCUS_CHECK_INTERVAL);
+            }
+            continue;
         }
-        f.write_all(entry.as_bytes()).map_err(|e| format!("failed to append entry to temp file: {}", e))?;
-        f.write_all(b"\r\n").map_err(|e| format!("failed to append newline to temp file: {}", e))?;
+        std::thread::sleep(Duration::from_millis(session.remaining_ms(now)).min(FOCUS_CHECK_INTERVAL));
+    }));
+}
 
-        let cmd = format!("move /Y \"{}\" \"{}\"", tmp_path_str.replace('"', ""), HOSTS_PATH);
-        println!("add_block_website: requesting elevation to run: {}", cmd);
+fn active_profile_name(block_data: &Map<String, Value>) -> String {
+    block_data
+        .get(ACTIVE_PROFILE_KEY)
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_PROFILE)
+        .to_string()
+}
 
-        let _ = run_elevated_command(cmd).await.map_err(|e| {
-            format!("elevated hosts update failed: {}", e)
-        })?;
+fn stored_profile(block_data: &Map<String, Value>, name: &str) -> Option<BlockProfile> {
+    block_data
+        .get(PROFILES_KEY)
+        .and_then(|profiles| profiles.get(name))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
 
-        println!("add_block_website: hosts updated successfully");
-        has_added = true;
-    } else {
-        println!("add_block_website: hosts already contains entry for '{}'", site);
+/// The active profile as it is enforced right now: the top-level lists
+/// plus the current DNS provider and SafeSearch preferences.
+fn live_profile(app_handle: &tauri::AppHandle, block_data: &Map<String, Value>) -> BlockProfile {
+    let list = |key: &str| block_data.get(key).and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    BlockProfile {
+        blocked_apps: list("blockedApps"),
+        blocked_websites: list("blockedWebsites"),
+        dns_provider: Some(get_selected_dns_provider(app_handle).id),
+        enforce_safe_search: read_preferences_for_key(app_handle, "enforceSafeSearch").unwrap_or(false),
     }
+}
 
-    if !has_added {
-        return Ok(false);
+/// Custom providers count as the least strict whatever they claim.
+fn dns_provider_strictness(app_handle: &tauri::AppHandle, provider_id: &str) -> Option<u8> {
+    resolve_dns_provider(app_handle, provider_id).ok().map(|p| trusted_strictness(&p))
+}
+
+#[tauri::command]
+fn get_profiles(app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let block_data = load_block_data(&app_handle)?;
+    let active = active_profile_name(&block_data);
+
+    let mut names: Vec<String> = block_data
+        .get(PROFILES_KEY)
+        .and_then(|v| v.as_object())
+        .map(|profiles| profiles.keys().cloned().collect())
+        .unwrap_or_default();
+    if !names.contains(&active) {
+        names.push(active.clone());
+    }
+    names.sort();
+
+    Ok(json!({
+        "active": active,
+        "profiles": names,
+        "switchSettingPrefix": PROFILE_SWITCH_SETTING_PREFIX,
+    }))
+}
+
+/// Creates a profile, either empty or as a copy of what is enforced now.
+#[tauri::command]
+fn create_profile(name: String, copy_current: bool, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("profile-name-required".to_string());
     }
 
     let path = get_app_file_path(&app_handle, "blockData.json")?;
     let mut block_data = read_json_map(&path)?;
-    let mut should_write = false;
-
-    let key = "blockedWebsites";
-    let arr_val = block_data
-        .entry(key.to_string())
-        .or_insert(Value::Array(Vec::new()));
+    if name == active_profile_name(&block_data) || stored_profile(&block_data, &name).is_some() {
+        return Err("profile-exists".to_string());
+    }
 
-    if let Value::Array(ref mut arr) = arr_val {
-        let exists = arr.iter().any(|v| v.as_str().map(|s| s == site).unwrap_or(false));
-        if !exists {
-            arr.push(Value::String(site.to_string()));
-            should_write = true;
-            println!("add_block_website: appended '{}' to {}", site, key);
-        } else {
-            println!("add_block_website: '{}' already present in {}", site, key);
-        }
-    } else {
-        block_data.insert(
-            key.to_string(),
-            Value::Array(vec![Value::String(site.to_string())]),
-        );
-        should_write = true;
-        println!("add_block_website: created {} with '{}'", key, site);
+    let profile = if copy_current { live_profile(&app_handle, &block_data) } else { BlockProfile::default() };
+    let value = serde_json::to_value(&profile).map_err(|e| format!("failed to serialize profile: {}", e))?;
+    let profiles = block_data
+        .entry(PROFILES_KEY.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(profiles) = profiles {
+        profiles.insert(name.clone(), value);
     }
+    write_json_map(&path, &block_data)?;
+    println!("create_profile: created '{}' (copy_current={})", name, copy_current);
 
-    if should_write {
-        write_json_map(&path, &block_data)?;
-        println!("add_block_website: appended '{}' to {}", site, key);
-        store_block_data_cached(&app_handle, &block_data)?;
+    let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
+    Ok(())
+}
+
+/// Deletes a profile that is not active; it is not enforced, so nothing
+/// is loosened.
+#[tauri::command]
+fn delete_profile(name: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let path = get_app_file_path(&app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    if name == active_profile_name(&block_data) {
+        return Err("profile-active".to_string());
     }
 
-    let _ = tauri::Manager::emit_all(
-        &app_handle,
-        "block-data-updated",
-        serde_json::json!({ "key": key, "item": site }),
-    );
+    if let Some(Value::Object(profiles)) = block_data.get_mut(PROFILES_KEY) {
+        profiles.remove(&name);
+    }
+    write_json_map(&path, &block_data)?;
+    println!("delete_profile: deleted '{}'", name);
 
-    Ok(true)
+    let _ = tauri::Manager::emit_all(&app_handle, "block-data-updated", json!({}));
+    Ok(())
 }
 
+/// Makes `name` the active profile. A profile at least as strict as what
+/// is enforced now applies immediately; anything else waits for the delay.
 #[tauri::command]
-async fn remove_block_website(site: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
-    let site = site.trim();
-    if site.is_empty() {
-        return Err("empty site".into());
+async fn switch_profile(name: String, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let block_data = load_block_data(&app_handle)?;
+    if name == active_profile_name(&block_data) {
+        return Ok(json!({ "applied": true }));
+    }
+    if read_focus_session(&block_data).is_some() {
+        return Err("focus-session-active".to_string());
     }
 
-    println!("remove_block_website: removing {}", site);
+    let target = stored_profile(&block_data, &name).ok_or_else(|| "profile-not-found".to_string())?;
+    let current = live_profile(&app_handle, &block_data);
 
-    let current = std::fs::read_to_string(HOSTS_PATH).map_err(|e| format!("failed to read hosts file: {}", e))?;
+    if is_at_least_as_strict(&target, &current, |id| dns_provider_strictness(&app_handle, id)) {
+        apply_profile_switch(&app_handle, &name).await?;
+        return Ok(json!({ "applied": true }));
+    }
 
-    let pattern = format!(r"(?m)^\s*127\.0\.0\.1\s+{}\b.*\r?\n?", regex::escape(site));
-    let re = Regex::new(&pattern).map_err(|e| e.to_string())?;
-    let new_content = re.replace_all(&current, "").to_string();
+    let setting_id = format!("{}{}", PROFILE_SWITCH_SETTING_PREFIX, name);
+    println!("switch_profile: '{}' is less strict, delaying (setting_id='{}')", name, setting_id);
+    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
+        .map_err(|e| format!("switch_profile: failed to start timer: {}", e))?;
 
-    if new_content == current {
-        println!("remove_block_website: hosts had no entry for '{}', skipping hosts edit", site);
-    } 
-    else {
-        let mut tmp_path = env::temp_dir();
-        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis().to_string()).unwrap_or_else(|_| "tmp".into());
-        tmp_path.push(format!("eagleblocker_hosts_rm_{}.tmp", suffix));
-        let tmp_path_str = tmp_path.to_string_lossy().into_owned();
+    Ok(json!({ "applied": false, "settingId": setting_id }))
+}
 
-        std::fs::write(&tmp_path, new_content.as_bytes()).map_err(|e| format!("failed to write temp hosts file: {}", e))?;
-        let cmd = format!("move /Y \"{}\" \"{}\"", tmp_path_str.replace('"', ""), HOSTS_PATH);
+/// Stores what is enforced now under the active profile and enforces
+/// `name` instead: its lists move to the top level, the hosts file is
+/// updated in one elevated write, then SafeSearch and DNS follow.
+async fn apply_profile_switch(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let _start = FOCUS_START_LOCK.lock().await;
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    if read_focus_session(&block_data).is_some() {
+        return Err("focus-session-active".to_string());
+    }
 
-        if let Err(e) = run_elevated_command(cmd).await {
-            let _ = std::fs::remove_file(&tmp_path);
-            return Err(format!("elevated move failed: {}", e));
+    let previous_name = active_profile_name(&block_data);
+    if previous_name == name {
+        if block_data.remove(PENDING_PROFILE_SWITCH_KEY).is_some() {
+            write_json_map(&path, &block_data)?;
         }
+        return Ok(());
+    }
+    let target = stored_profile(&block_data, name).ok_or_else(|| "profile-not-found".to_string())?;
+    let current = live_profile(app_handle, &block_data);
 
-        let after = std::fs::read_to_string(HOSTS_PATH).map_err(|e| format!("failed to read hosts after elevation: {}", e))?;
-        if re.is_match(&after) {
-            return Err("hosts file still contains entry after elevated update".into());
+    let now = chrono::Local::now();
+    let mut keep = category_hosts_in_use(app_handle, &block_data);
+    let wanted: Vec<String> = target
+        .blocked_websites
+        .iter()
+        .filter(|item| website_entry_active(item, &now))
+        .flat_map(website_entry_hosts)
+        .collect();
+    keep.extend(wanted.iter().cloned());
+
+    let mut hosts = HostsFile::load(HOSTS_PATH)?;
+    let mut hosts_changed = false;
+    for host in current.blocked_websites.iter().flat_map(website_entry_hosts).filter(|h| !keep.contains(h)) {
+        for address in BLOCK_ADDRESSES.iter() {
+            hosts_changed |= hosts.remove(address, &host);
         }
-
-        println!("remove_block_website: hosts updated successfully for '{}'", site);
+    }
+    for host in wanted.iter() {
+        for address in BLOCK_ADDRESSES.iter() {
+            hosts_changed |= hosts.add(address, host);
+        }
+    }
+    if hosts_changed {
+        write_hosts_file_elevated(&hosts).await?;
     }
 
-    let path = get_app_file_path(&app_handle, "blockData.json")?;
-    let mut block_data = read_json_map(&path)?;
-    let mut changed = false;
+    let current_value = serde_json::to_value(&current).map_err(|e| format!("failed to serialize profile: {}", e))?;
+    let profiles = block_data
+        .entry(PROFILES_KEY.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(profiles) = profiles {
+        profiles.insert(previous_name.clone(), current_value);
+    }
 
-    if let Some(serde_json::Value::Array(ref mut arr)) = block_data.get_mut("blockedWebsites") {
-        let before = arr.len();
-        arr.retain(|v| v.as_str().map(|s| s != site).unwrap_or(true));
-        if arr.len() != before {
-            println!("remove_block_website: removed '{}' from blockedWebsites", site);
-            changed = true;
-        } else {
-            println!("remove_block_website: '{}' not found in blockedWebsites", site);
-        }
+    block_data.insert("blockedApps".to_string(), Value::Array(target.blocked_apps.clone()));
+    block_data.insert("blockedWebsites".to_string(), Value::Array(target.blocked_websites.clone()));
+    block_data.insert(ACTIVE_PROFILE_KEY.to_string(), Value::String(name.to_string()));
+    // Primed removals and pending changes were for the previous profile's rules.
+    for key in ["allowedForUnblockApps", "allowedForUnblockWebsites", PENDING_SCHEDULES_KEY, PENDING_QUOTAS_KEY, PENDING_PROFILE_SWITCH_KEY] {
+        block_data.remove(key);
     }
+    write_json_map(&path, &block_data)?;
+    println!("apply_profile_switch: '{}' -> '{}'", previous_name, name);
 
-    if let Some(serde_json::Value::Array(ref mut arr)) = block_data.get_mut("allowedForUnblockWebsites") {
-        let before = arr.len();
-        arr.retain(|v| v.as_str().map(|s| s != site).unwrap_or(true));
-        if arr.len() != before {
-            println!("remove_block_website: removed '{}' from allowedForUnblockWebsites", site);
-            changed = true;
+    if target.enforce_safe_search && !current.enforce_safe_search {
+        if let Err(e) = enable_safe_search(app_handle.clone()).await {
+            eprintln!("apply_profile_switch: failed to enable safe search: {}", e);
         }
+    } else if !target.enforce_safe_search && current.enforce_safe_search {
+        save_preference("enforceSafeSearch".to_string(), Value::Bool(false), app_handle.clone())?;
+        disable_safe_search(app_handle.clone());
     }
 
-    if changed {
-        write_json_map(&path, &block_data)?;
-        store_block_data_cached(&app_handle, &block_data)?;
-        let _ = tauri::Manager::emit_all(
-            &app_handle,
-            "block-data-updated",
-            serde_json::json!({ "key": "blockedWebsites", "item": site }),
-        );
+    if let Some(provider_id) = target.dns_provider.as_ref().filter(|id| Some(*id) != current.dns_provider.as_ref()) {
+        if read_preferences_for_key(app_handle, "enableProtectiveDNS").unwrap_or(false) {
+            let use_local_filter = read_preferences_for_key(app_handle, "enableDnsFilter").unwrap_or(false);
+            // The profile switch already waited for the delay if this loosens DNS.
+            match resolve_dns_provider(app_handle, provider_id) {
+                Ok(provider) => {
+                    if let Err(e) = apply_dns_provider(&provider, use_local_filter, app_handle).await {
+                        eprintln!("apply_profile_switch: failed to switch dns provider: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("apply_profile_switch: {}", e),
+            }
+        } else {
+            save_preference(DNS_PROVIDER_KEY.to_string(), Value::String(provider_id.clone()), app_handle.clone())?;
+        }
     }
 
-    Ok(true)
+    record_audit_event(app_handle, "profile-switched", json!({ "from": previous_name, "to": name }));
+    let _ = tauri::Manager::emit_all(app_handle, "block-data-updated", json!({}));
+    let _ = tauri::Manager::emit_all(app_handle, "preferences-updated", json!({}));
+    Ok(())
 }
 
-#[tauri::command]
-fn prime_for_deletion(item_type: String, name: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
-    let kind = item_type.to_lowercase();
-    let key = if kind == "website" { "allowedForUnblockWebsites" } else { "allowedForUnblockApps" };
+/// Called when the delay for a profile switch runs out. The switch is
+/// stored as pending first so the worker retries it if applying fails.
+fn expire_profile_switch_delay(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    block_data.insert(PENDING_PROFILE_SWITCH_KEY.to_string(), Value::String(name.to_string()));
+    write_json_map(&path, &block_data)?;
 
-    let setting_id = format!("{}-->{}", key, name);
+    start_profile_switch_worker(app_handle.clone());
+    Ok(())
+}
 
-    println!("prime_for_deletion: priming '{}' for deletion (setting_id='{}')", name, setting_id);
+/// Applies the pending profile switch, retrying until it goes through or
+/// the profile is gone. A running focus session postpones it.
+fn start_profile_switch_worker(app_handle: tauri::AppHandle) {
+    let mut guard = match PROFILE_SWITCH_HANDLE.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    if guard.is_some() {
+        return;
+    }
 
-    start_countdown_timer(setting_id.clone(), None, None, app_handle.clone())
-        .map_err(|e| format!("prime_for_deletion: failed to start timer: {}", e))?;
+    *guard = Some(std::thread::spawn(move || loop {
+        let pending = load_block_data(&app_handle)
+            .map(|b| b.get(PENDING_PROFILE_SWITCH_KEY).and_then(|v| v.as_str()).map(|s| s.to_string()));
+        let name = match pending {
+            Ok(Some(name)) => name,
+            Ok(None) => {
+                // Re-check under the lock so a switch stored meanwhile is
+                // not left without a worker.
+                let mut guard = match PROFILE_SWITCH_HANDLE.lock() {
+                    Ok(g) => g,
+                    Err(_) => break,
+                };
+                let stored = load_block_data(&app_handle)
+                    .map(|b| b.contains_key(PENDING_PROFILE_SWITCH_KEY))
+                    .unwrap_or(false);
+                if !stored {
+                    *guard = None;
+                    break;
+                }
+                continue;
+            }
+            Err(e) => {
+                eprintln!("start_profile_switch_worker: failed to load block data: {}", e);
+                std::thread::sleep(PROFILE_SWITCH_RETRY_INTERVAL);
+                continue;
+            }
+        };
 
-    Ok(true)
+        match tauri::async_runtime::block_on(apply_profile_switch(&app_handle, &name)) {
+            Ok(()) => continue,
+            Err(e) if e == "profile-not-found" => {
+                eprintln!("start_profile_switch_worker: profile '{}' no longer exists", name);
+                if let Err(e) = clear_pending_profile_switch(&app_handle, &name) {
+                    eprintln!("start_profile_switch_worker: {}", e);
+                    std::thread::sleep(PROFILE_SWITCH_RETRY_INTERVAL);
+                }
+            }
+            Err(e) => {
+                eprintln!("start_profile_switch_worker: failed to switch to profile '{}', retrying: {}", name, e);
+                std::thread::sleep(PROFILE_SWITCH_RETRY_INTERVAL);
+            }
+        }
+    }));
+}
+
+fn clear_pending_profile_switch(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let path = get_app_file_path(app_handle, "blockData.json")?;
+    let mut block_data = read_json_map(&path)?;
+    if block_data.get(PENDING_PROFILE_SWITCH_KEY).and_then(|v| v.as_str()) == Some(name) {
+        block_data.remove(PENDING_PROFILE_SWITCH_KEY);
+        write_json_map(&path, &block_data)?;
+    }
+    Ok(())
 }
 
 fn reactivate_timers(app_handle: &tauri::AppHandle) -> Result<(), String> {
@@ -2474,6 +5295,8 @@ fn show_overlay(app_handle: &tauri::AppHandle, arguments: serde_json::Value) ->
         let _ = win.show();
         let _ = win.set_focus();
         println!("Overlay window already exists");
+        // The page ignores this unless the arguments changed.
+        let _ = win.emit("overlay-updated", arguments);
         return Ok(());
     }
 
@@ -2497,13 +5320,22 @@ fn show_overlay(app_handle: &tauri::AppHandle, arguments: serde_json::Value) ->
         .unwrap_or("")
         .to_string();
 
-    let url = format!(
+    let mut url = format!(
         "overlayWindow.html?code={}&displayName={}&processName={}",
         percent_encode(&code),
         percent_encode(&display),
         percent_encode(&process)
     );
 
+    for key in ["pid", "action", "outcome", "error", "killAt", "quotaMinutes"] {
+        let value = match obj.and_then(|m| m.get(key)) {
+            Some(Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            _ => continue,
+        };
+        url.push_str(&format!("&{}={}", key, percent_encode(&value)));
+    }
+
     if let Err(e) = tauri::WindowBuilder::new(app_handle, "overlay_window", tauri::WindowUrl::App(url.into()))
         .title("Overlay")
         .fullscreen(false)
@@ -2608,6 +5440,17 @@ fn main() {
             if let Err(e) = reactivate_timers(&app_handle) {
                 eprintln!("reactivate_timers failed during setup: {}", e);
             }
+            start_focus_session_worker(app_handle.clone());
+            start_profile_switch_worker(app_handle.clone());
+
+            if read_preferences_for_key(&app_handle, "enableDnsFilter").unwrap_or(false) {
+                let provider = get_selected_dns_provider(&app_handle);
+                if let Err(e) = start_dns_filter_for(&app_handle, &provider) {
+                    eprintln!("start_dns_filter_for failed during setup: {}", e);
+                }
+            }
+            start_network_watcher(app_handle.clone());
+            start_schedule_worker(app_handle.clone());
             register_page_change_menu_handler(app);
             Ok(())
         })
@@ -2662,15 +5505,48 @@ fn main() {
             get_block_data,
             save_block_data,
             get_all_installed_apps,
+            set_blocked_app_enforcement,
+            set_block_schedule,
+            get_block_schedule,
+            set_app_quota,
+            get_usage_today,
+            save_focus_profile,
+            get_focus_profiles,
+            get_focus_session,
+            start_focus_session,
+            request_end_focus_session,
+            get_profiles,
+            create_profile,
+            delete_profile,
+            switch_profile,
             get_delay_time_out,
             start_countdown_timer,
             cancel_countdown_timer,
             get_delay_change_status,
             stop_settings_and_app_protection,
             add_block_website,
+            add_block_websites,
             get_change_status,
             prime_for_deletion,
             remove_block_website,
+            remove_block_websites,
+            preview_blocklist_import,
+            import_blocklist,
+            export_blocklist,
+            get_block_categories,
+            enable_block_category,
+            disable_block_category,
+            get_dns_filter_status,
+            get_protective_dns_status,
+            refresh_safe_search,
+            get_safe_search_engines,
+            set_safe_search_engine,
+            get_dns_providers,
+            get_audit_log,
+            save_custom_dns_provider,
+            remove_custom_dns_provider,
+            add_dns_rule,
+            remove_dns_rule,
             close_overlay_window,
             show_delay_for_priming_deletion,
             close_invoking_window,
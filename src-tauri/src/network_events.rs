@@ -0,0 +1,138 @@
+use std::sync::mpsc::Sender;
+
+/// Keeps the change notifications registered; dropping it cancels them.
+pub struct NetworkSubscription {
+    #[cfg(windows)]
+    handles: Vec<windows::Win32::Foundation::HANDLE>,
+    #[cfg(windows)]
+    context: *mut Sender<()>,
+}
+
+// The raw handles and context are only touched on registration and in drop.
+unsafe impl Send for NetworkSubscription {}
+
+/// Sends `()` on `changes` whenever an adapter comes or goes, an address
+/// changes, or a resolver is edited in the adapter settings. Changes arrive
+/// in bursts, so callers should let them settle before acting.
+#[cfg(windows)]
+pub fn subscribe(changes: Sender<()>) -> Result<NetworkSubscription, String> {
+    use windows::Win32::Foundation::{BOOLEAN, HANDLE, NO_ERROR};
+    use windows::Win32::NetworkManagement::IpHelper::{NotifyIpInterfaceChange, NotifyUnicastIpAddressChange};
+    use windows::Win32::Networking::WinSock::AF_UNSPEC;
+
+    // Resolvers live in the registry only; no IP Helper event covers them.
+    for key in RESOLVER_KEYS {
+        watch_registry_key(key, changes.clone())?;
+    }
+
+    let mut subscription = NetworkSubscription { handles: Vec::new(), context: Box::into_raw(Box::new(changes)) };
+    let context = Some(subscription.context as *const std::ffi::c_void);
+
+    unsafe {
+        let mut handle = HANDLE::default();
+        let result = NotifyIpInterfaceChange(AF_UNSPEC, Some(on_interface_change), context, BOOLEAN(0), &mut handle);
+        if result != NO_ERROR {
+            return Err(format!("NotifyIpInterfaceChange failed: {}", result.0));
+        }
+        subscription.handles.push(handle);
+
+        let mut handle = HANDLE::default();
+        let result = NotifyUnicastIpAddressChange(AF_UNSPEC, Some(on_address_change), context, BOOLEAN(0), &mut handle);
+        if result != NO_ERROR {
+            return Err(format!("NotifyUnicastIpAddressChange failed: {}", result.0));
+        }
+        subscription.handles.push(handle);
+    }
+
+    Ok(subscription)
+}
+
+#[cfg(not(windows))]
+pub fn subscribe(_changes: Sender<()>) -> Result<NetworkSubscription, String> {
+    Err("network change notifications are only available on Windows".into())
+}
+
+#[cfg(windows)]
+impl Drop for NetworkSubscription {
+    fn drop(&mut self) {
+        use windows::Win32::NetworkManagement::IpHelper::CancelMibChangeNotify2;
+
+        // Cancelling waits for running callbacks, so the context is unused
+        // once every handle is gone.
+        unsafe {
+            for handle in self.handles.drain(..) {
+                let _ = CancelMibChangeNotify2(handle);
+            }
+            drop(Box::from_raw(self.context));
+        }
+    }
+}
+
+#[cfg(windows)]
+const RESOLVER_KEYS: [&str; 2] = [
+    r"SYSTEM\CurrentControlSet\Services\Tcpip\Parameters\Interfaces",
+    r"SYSTEM\CurrentControlSet\Services\Tcpip6\Parameters\Interfaces",
+];
+
+#[cfg(windows)]
+unsafe extern "system" fn on_interface_change(
+    context: *const std::ffi::c_void,
+    _row: *const windows::Win32::NetworkManagement::IpHelper::MIB_IPINTERFACE_ROW,
+    _kind: windows::Win32::NetworkManagement::IpHelper::MIB_NOTIFICATION_TYPE,
+) {
+    notify(context);
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_address_change(
+    context: *const std::ffi::c_void,
+    _row: *const windows::Win32::NetworkManagement::IpHelper::MIB_UNICASTIPADDRESS_ROW,
+    _kind: windows::Win32::NetworkManagement::IpHelper::MIB_NOTIFICATION_TYPE,
+) {
+    notify(context);
+}
+
+#[cfg(windows)]
+unsafe fn notify(context: *const std::ffi::c_void) {
+    if let Some(changes) = (context as *const Sender<()>).as_ref() {
+        let _ = changes.send(());
+    }
+}
+
+/// Blocks on `key` in a thread of its own until the receiver is gone.
+#[cfg(windows)]
+fn watch_registry_key(key: &'static str, changes: Sender<()>) -> Result<(), String> {
+    use windows::core::HSTRING;
+    use windows::Win32::Foundation::{HANDLE, NO_ERROR};
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegNotifyChangeKeyValue, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_NOTIFY,
+        REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME,
+    };
+
+    let mut hkey = HKEY::default();
+    let result = unsafe { RegOpenKeyExW(HKEY_LOCAL_MACHINE, &HSTRING::from(key), 0, KEY_NOTIFY, &mut hkey) };
+    if result != NO_ERROR {
+        return Err(format!("failed to open {}: {}", key, result.0));
+    }
+
+    // HKEY is a raw pointer; it is only used by the thread it moves to.
+    let raw = hkey.0 as usize;
+    std::thread::spawn(move || {
+        let hkey = HKEY(raw as *mut std::ffi::c_void);
+        loop {
+            let filter = REG_NOTIFY_CHANGE_LAST_SET | REG_NOTIFY_CHANGE_NAME;
+            let result = unsafe { RegNotifyChangeKeyValue(hkey, true, filter, HANDLE::default(), false) };
+            if result != NO_ERROR {
+                eprintln!("watch_registry_key: watching {} failed: {}", key, result.0);
+                break;
+            }
+            if changes.send(()).is_err() {
+                break;
+            }
+        }
+        unsafe {
+            let _ = RegCloseKey(hkey);
+        }
+    });
+    Ok(())
+}
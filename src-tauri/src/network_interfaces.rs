@@ -0,0 +1,117 @@
+// netsh translates the state columns, so each is matched against the
+// display languages we know of.
+const ENABLED_STATES: [&str; 6] = ["enabled", "aktiviert", "activé", "habilitado", "abilitato", "ingeschakeld"];
+const DISABLED_STATES: [&str; 6] = ["disabled", "deaktiviert", "désactivé", "deshabilitado", "disabilitato", "uitgeschakeld"];
+const CONNECTED_STATES: [&str; 6] = ["connected", "verbunden", "connecté", "conectado", "connesso", "verbonden"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub enabled: bool,
+    pub connected: bool,
+}
+
+/// Parses `netsh interface show interface`. Columns are separated by two or
+/// more spaces; the name is everything after the third column.
+pub fn parse_interfaces(netsh_output: &str) -> Vec<NetworkInterface> {
+    let mut interfaces = Vec::new();
+    for line in netsh_output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('-') {
+            continue;
+        }
+
+        let columns = split_columns(trimmed, 4);
+        if columns.len() < 4 {
+            continue;
+        }
+
+        let admin = columns[0].to_lowercase();
+        let state = columns[1].to_lowercase();
+        let enabled = ENABLED_STATES.contains(&admin.as_str());
+        if !enabled && !DISABLED_STATES.contains(&admin.as_str()) {
+            continue;
+        }
+
+        interfaces.push(NetworkInterface {
+            name: columns[3].to_string(),
+            enabled,
+            connected: CONNECTED_STATES.contains(&state.as_str()),
+        });
+    }
+    interfaces
+}
+
+pub fn connected_interface_names(netsh_output: &str) -> Vec<String> {
+    parse_interfaces(netsh_output)
+        .into_iter()
+        .filter(|i| i.enabled && i.connected)
+        .map(|i| i.name)
+        .collect()
+}
+
+fn split_columns(line: &str, count: usize) -> Vec<&str> {
+    let mut columns = Vec::new();
+    let mut rest = line;
+    while columns.len() + 1 < count {
+        match rest.find("  ") {
+            Some(idx) => {
+                columns.push(&rest[..idx]);
+                rest = rest[idx..].trim_start();
+            }
+            None => break,
+        }
+    }
+    if !rest.is_empty() {
+        columns.push(rest);
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "\r\nAdmin State    State          Type             Interface Name\r\n-------------------------------------------------------------------------\r\nEnabled        Connected      Dedicated        Ethernet\r\nEnabled        Disconnected   Dedicated        Wi-Fi\r\nDisabled       Disconnected   Dedicated        Ethernet 2\r\nEnabled        Connected      Dedicated        vEthernet (Default Switch)\r\n\r\n";
+
+    const GERMAN: &str = "\r\nAdministratorstatus Status         Typ              Schnittstellenname\r\n-------------------------------------------------------------------------\r\nAktiviert           Verbunden      Dediziert        WLAN\r\nAktiviert           Getrennt       Dediziert        Ethernet\r\nDeaktiviert         Getrennt       Dediziert        Bluetooth-Netzwerkverbindung\r\n";
+
+    const FRENCH: &str = "\nÉtat admin     État           Type             Nom de l'interface\n-------------------------------------------------------------------------\nActivé         Connecté       Dédié            Wi-Fi\nActivé         Déconnecté     Dédié            Ethernet\n";
+
+    fn interface(name: &str, enabled: bool, connected: bool) -> NetworkInterface {
+        NetworkInterface { name: name.to_string(), enabled, connected }
+    }
+
+    #[test]
+    fn parses_english_output() {
+        assert_eq!(
+            parse_interfaces(ENGLISH),
+            vec![
+                interface("Ethernet", true, true),
+                interface("Wi-Fi", true, false),
+                interface("Ethernet 2", false, false),
+                interface("vEthernet (Default Switch)", true, true),
+            ]
+        );
+        assert_eq!(connected_interface_names(ENGLISH), vec!["Ethernet", "vEthernet (Default Switch)"]);
+    }
+
+    #[test]
+    fn parses_localized_output() {
+        assert_eq!(
+            parse_interfaces(GERMAN),
+            vec![
+                interface("WLAN", true, true),
+                interface("Ethernet", true, false),
+                interface("Bluetooth-Netzwerkverbindung", false, false),
+            ]
+        );
+        assert_eq!(connected_interface_names(FRENCH), vec!["Wi-Fi"]);
+    }
+
+    #[test]
+    fn skips_headers_and_noise() {
+        assert!(parse_interfaces("").is_empty());
+        assert!(parse_interfaces("The following command was not found: interface show.").is_empty());
+    }
+}
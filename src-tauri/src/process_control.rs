@@ -0,0 +1,193 @@
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+
+use crate::process_monitor::ProcessInfo;
+
+/// `root` followed by its descendants, parents before children. A process
+/// only counts as a child if it started no earlier than its parent, so
+/// processes holding a recycled parent PID are left alone.
+pub fn process_tree(root: u32, processes: &[ProcessInfo]) -> Vec<u32> {
+    let mut tree = vec![root];
+    let mut seen: HashSet<u32> = HashSet::from([root]);
+    let mut queue: VecDeque<u32> = VecDeque::from([root]);
+
+    while let Some(parent) = queue.pop_front() {
+        let parent_start = processes.iter().find(|p| p.pid == parent).map(|p| p.start_time).unwrap_or(0);
+        for child in processes.iter() {
+            if child.parent_pid == Some(parent) && child.start_time >= parent_start && seen.insert(child.pid) {
+                tree.push(child.pid);
+                queue.push_back(child.pid);
+            }
+        }
+    }
+
+    tree
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeOutcome {
+    pub succeeded: Vec<u32>,
+    pub failed: Vec<(u32, String)>,
+}
+
+impl TreeOutcome {
+    /// "success", "partial" or "failed", as shown by the overlay.
+    pub fn status(&self) -> &'static str {
+        match (self.succeeded.is_empty(), self.failed.is_empty()) {
+            (_, true) => "success",
+            (false, false) => "partial",
+            (true, false) => "failed",
+        }
+    }
+
+    pub fn first_error(&self) -> Option<&str> {
+        self.failed.first().map(|(_, e)| e.as_str())
+    }
+}
+
+fn for_each_in_tree(root: u32, processes: &[ProcessInfo], op: impl Fn(u32) -> Result<(), String>) -> TreeOutcome {
+    let mut outcome = TreeOutcome::default();
+    for pid in process_tree(root, processes) {
+        match op(pid) {
+            Ok(()) => outcome.succeeded.push(pid),
+            Err(e) => outcome.failed.push((pid, e)),
+        }
+    }
+    outcome
+}
+
+/// Terminates `root` and everything it spawned. The root goes first so it
+/// cannot restart the children as they are killed.
+pub fn kill_process_tree(root: u32, processes: &[ProcessInfo]) -> TreeOutcome {
+    for_each_in_tree(root, processes, terminate_process)
+}
+
+pub fn suspend_process_tree(root: u32, processes: &[ProcessInfo]) -> TreeOutcome {
+    for_each_in_tree(root, processes, |pid| set_suspended(pid, true))
+}
+
+pub fn resume_process(pid: u32) -> Result<(), String> {
+    set_suspended(pid, false)
+}
+
+#[cfg(windows)]
+fn terminate_process(pid: u32) -> Result<(), String> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, false, pid).map_err(|e| format!("failed to open process {}: {}", pid, e))?;
+        let result = TerminateProcess(handle, 1);
+        let _ = CloseHandle(handle);
+        result.map_err(|e| format!("failed to terminate process {}: {}", pid, e))
+    }
+}
+
+/// Suspends or resumes every thread of `pid`. Windows keeps a suspend count
+/// per thread, so each suspend must be matched by exactly one resume.
+#[cfg(windows)]
+fn set_suspended(pid: u32, suspend: bool) -> Result<(), String> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows::Win32::System::Threading::{OpenThread, ResumeThread, SuspendThread, THREAD_SUSPEND_RESUME};
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0).map_err(|e| format!("failed to snapshot threads: {}", e))?;
+        let mut entry = THREADENTRY32 { dwSize: std::mem::size_of::<THREADENTRY32>() as u32, ..Default::default() };
+
+        let mut changed = 0;
+        let mut last_error: Option<String> = None;
+        let mut more = Thread32First(snapshot, &mut entry).is_ok();
+        while more {
+            if entry.th32OwnerProcessID == pid {
+                match OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID) {
+                    Ok(thread) => {
+                        let previous = if suspend { SuspendThread(thread) } else { ResumeThread(thread) };
+                        let _ = CloseHandle(thread);
+                        if previous == u32::MAX {
+                            last_error = Some(format!("failed to change thread {}", entry.th32ThreadID));
+                        } else {
+                            changed += 1;
+                        }
+                    }
+                    Err(e) => last_error = Some(format!("failed to open thread {}: {}", entry.th32ThreadID, e)),
+                }
+            }
+            more = Thread32Next(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+
+        match (changed, last_error) {
+            (0, Some(e)) => Err(e),
+            (0, None) => Err(format!("process {} has no threads", pid)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn terminate_process(pid: u32) -> Result<(), String> {
+    Err(format!("terminating process {} is not supported on this platform", pid))
+}
+
+#[cfg(not(windows))]
+fn set_suspended(pid: u32, _suspend: bool) -> Result<(), String> {
+    Err(format!("suspending process {} is not supported on this platform", pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent_pid: Option<u32>, start_time: u64) -> ProcessInfo {
+        ProcessInfo { parent_pid, start_time, ..ProcessInfo::new(pid, &format!("p{}.exe", pid), None) }
+    }
+
+    #[test]
+    fn collects_descendants_parents_first() {
+        let processes = vec![
+            process(300, Some(200), 1_020),
+            process(100, Some(4), 1_000),
+            process(200, Some(100), 1_010),
+            process(210, Some(100), 1_000),
+            process(900, Some(4), 900),
+        ];
+        assert_eq!(process_tree(100, &processes), vec![100, 200, 210, 300]);
+        assert_eq!(process_tree(300, &processes), vec![300]);
+    }
+
+    #[test]
+    fn skips_processes_holding_a_recycled_parent_pid() {
+        // 400 was started by an earlier process 100 that has exited; the
+        // PID now belongs to the blocked app, which started later.
+        let processes = vec![
+            process(400, Some(100), 500),
+            process(410, Some(400), 1_030),
+            process(100, Some(4), 1_000),
+            process(200, Some(100), 1_010),
+        ];
+        assert_eq!(process_tree(100, &processes), vec![100, 200]);
+    }
+
+    #[test]
+    fn stops_on_parent_cycles() {
+        let processes = vec![process(100, Some(200), 1_000), process(200, Some(100), 1_000), process(300, Some(300), 1_000)];
+        assert_eq!(process_tree(100, &processes), vec![100, 200]);
+        assert_eq!(process_tree(300, &processes), vec![300]);
+    }
+
+    #[test]
+    fn reports_tree_status() {
+        let outcome = |succeeded: Vec<u32>, failed: Vec<u32>| TreeOutcome {
+            succeeded,
+            failed: failed.into_iter().map(|pid| (pid, format!("failed {}", pid))).collect(),
+        };
+        assert_eq!(outcome(vec![1, 2], vec![]).status(), "success");
+        assert_eq!(outcome(vec![1], vec![2]).status(), "partial");
+        assert_eq!(outcome(vec![], vec![2, 3]).status(), "failed");
+        assert_eq!(outcome(vec![], vec![2, 3]).first_error(), Some("failed 2"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use sysinfo::{PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub exe_path: Option<String>,
+    pub parent_pid: Option<u32>,
+    /// Seconds since the epoch; used to tell real children from processes
+    /// that inherited a recycled parent PID.
+    pub start_time: u64,
+}
+
+impl ProcessInfo {
+    pub fn new(pid: u32, name: &str, exe_path: Option<String>) -> Self {
+        Self { pid, name: name.trim().to_lowercase(), exe_path, parent_pid: None, start_time: 0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    Started(ProcessInfo),
+    Stopped(ProcessInfo),
+}
+
+pub trait ProcessSource: Send {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, String>;
+}
+
+/// Enumerates processes in-process through `sysinfo`, so polling costs a
+/// snapshot rather than a `tasklist` spawn.
+pub struct SysinfoSource {
+    system: System,
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        Self { system: System::new() }
+    }
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessSource for SysinfoSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, String> {
+        self.system.refresh_processes_specifics(ProcessRefreshKind::new());
+        Ok(self
+            .system
+            .processes()
+            .iter()
+            .map(|(pid, process)| {
+                let exe = process.exe();
+                let exe_path = if exe.as_os_str().is_empty() { None } else { Some(exe.to_string_lossy().into_owned()) };
+                ProcessInfo {
+                    parent_pid: process.parent().map(|p| p.as_u32()),
+                    start_time: process.start_time(),
+                    ..ProcessInfo::new(pid.as_u32(), process.name(), exe_path)
+                }
+            })
+            .collect())
+    }
+}
+
+/// Diffs successive snapshots into start/stop events. The first poll
+/// reports every running process as started.
+pub struct ProcessMonitor<S: ProcessSource> {
+    source: S,
+    known: HashMap<u32, ProcessInfo>,
+}
+
+impl<S: ProcessSource> ProcessMonitor<S> {
+    pub fn new(source: S) -> Self {
+        Self { source, known: HashMap::new() }
+    }
+
+    pub fn poll(&mut self) -> Result<Vec<ProcessEvent>, String> {
+        let snapshot = self.source.snapshot()?;
+        let mut current: HashMap<u32, ProcessInfo> = HashMap::with_capacity(snapshot.len());
+        let mut events = Vec::new();
+
+        for process in snapshot {
+            // A reused PID with a different image is a new process.
+            match self.known.get(&process.pid) {
+                Some(previous) if previous.name == process.name => {}
+                Some(previous) => {
+                    events.push(ProcessEvent::Stopped(previous.clone()));
+                    events.push(ProcessEvent::Started(process.clone()));
+                }
+                None => events.push(ProcessEvent::Started(process.clone())),
+            }
+            current.insert(process.pid, process);
+        }
+
+        for (pid, previous) in self.known.iter() {
+            if !current.contains_key(pid) {
+                events.push(ProcessEvent::Stopped(previous.clone()));
+            }
+        }
+
+        self.known = current;
+        Ok(events)
+    }
+
+    pub fn running(&self) -> Vec<ProcessInfo> {
+        self.known.values().cloned().collect()
+    }
+}
+
+/// Whether a blocked-app entry ("discord" or "Discord.exe") names `running`.
+pub fn matches_process_name(blocked: &str, running: &str) -> bool {
+    let blocked = blocked.trim().to_lowercase();
+    let running = running.trim().to_lowercase();
+    running == blocked || running.trim_end_matches(".exe") == blocked.trim_end_matches(".exe")
+}
+
+#[cfg(test)]
+pub struct MockProcessSource {
+    pub snapshots: std::collections::VecDeque<Vec<ProcessInfo>>,
+}
+
+#[cfg(test)]
+impl ProcessSource for MockProcessSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, String> {
+        self.snapshots.pop_front().ok_or_else(|| "no more snapshots".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(snapshots: Vec<Vec<ProcessInfo>>) -> ProcessMonitor<MockProcessSource> {
+        ProcessMonitor::new(MockProcessSource { snapshots: snapshots.into() })
+    }
+
+    fn sorted(mut events: Vec<ProcessEvent>) -> Vec<ProcessEvent> {
+        events.sort_by_key(|e| match e {
+            ProcessEvent::Stopped(p) => (0, p.pid),
+            ProcessEvent::Started(p) => (1, p.pid),
+        });
+        events
+    }
+
+    #[test]
+    fn first_poll_reports_everything_started() {
+        let shell = ProcessInfo::new(1, "explorer.exe", None);
+        let game = ProcessInfo::new(2, "Game.exe", None);
+        let mut monitor = monitor(vec![vec![shell.clone(), game.clone()]]);
+
+        let events = sorted(monitor.poll().unwrap());
+        assert_eq!(events, vec![ProcessEvent::Started(shell), ProcessEvent::Started(game)]);
+        assert_eq!(monitor.running().len(), 2);
+    }
+
+    #[test]
+    fn later_polls_report_only_changes() {
+        let shell = ProcessInfo::new(1, "explorer.exe", None);
+        let game = ProcessInfo::new(2, "game.exe", None);
+        let chat = ProcessInfo::new(3, "chat.exe", None);
+        let mut monitor = monitor(vec![
+            vec![shell.clone(), game.clone()],
+            vec![shell.clone(), game.clone()],
+            vec![shell.clone(), chat.clone()],
+        ]);
+
+        monitor.poll().unwrap();
+        assert!(monitor.poll().unwrap().is_empty());
+        assert_eq!(sorted(monitor.poll().unwrap()), vec![ProcessEvent::Stopped(game), ProcessEvent::Started(chat)]);
+
+        let mut running: Vec<u32> = monitor.running().iter().map(|p| p.pid).collect();
+        running.sort_unstable();
+        assert_eq!(running, vec![1, 3]);
+    }
+
+    #[test]
+    fn reused_pid_with_new_image_is_a_restart() {
+        let game = ProcessInfo::new(7, "game.exe", None);
+        let other = ProcessInfo::new(7, "notepad.exe", None);
+        let mut monitor = monitor(vec![vec![game.clone()], vec![other.clone()]]);
+
+        monitor.poll().unwrap();
+        assert_eq!(monitor.poll().unwrap(), vec![ProcessEvent::Stopped(game), ProcessEvent::Started(other)]);
+    }
+
+    #[test]
+    fn failed_snapshot_keeps_known_processes() {
+        let game = ProcessInfo::new(2, "game.exe", None);
+        let mut monitor = monitor(vec![vec![game.clone()]]);
+
+        monitor.poll().unwrap();
+        assert!(monitor.poll().is_err());
+        assert_eq!(monitor.running(), vec![game]);
+    }
+
+    #[test]
+    fn matches_names_with_or_without_exe() {
+        assert!(matches_process_name("Discord", "discord.exe"));
+        assert!(matches_process_name("discord.exe", "Discord"));
+        assert!(!matches_process_name("discord", "discordptb.exe"));
+    }
+}
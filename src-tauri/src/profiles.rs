@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app_rules::BlockedAppRule;
+use crate::schedules::{covers, Schedule};
+
+/// The profile in use before any were created.
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// A named set of blocks, stored under `profiles` in blockData.json. The
+/// active profile's lists live at the top level of blockData.json, where
+/// the protection loop and hosts sync read them; its stored copy is
+/// refreshed when switching away from it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProfile {
+    #[serde(default)]
+    pub blocked_apps: Vec<Value>,
+    #[serde(default)]
+    pub blocked_websites: Vec<Value>,
+    /// Protective DNS provider id; `None` keeps the current selection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_provider: Option<String>,
+    #[serde(default)]
+    pub enforce_safe_search: bool,
+}
+
+fn website_domain(item: &Value) -> Option<String> {
+    item.as_str()
+        .or_else(|| item.get("domain").and_then(|v| v.as_str()))
+        .map(|d| d.trim().to_lowercase())
+}
+
+fn website_variants(item: &Value) -> Vec<String> {
+    item.get("variants")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|v| v.trim().to_lowercase()).collect())
+        .unwrap_or_default()
+}
+
+fn website_schedules(item: &Value) -> Vec<Schedule> {
+    item.get("schedules")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn apps_at_least_as_strict(new: &[Value], old: &[Value]) -> bool {
+    let new_rules: Vec<BlockedAppRule> = new.iter().filter_map(BlockedAppRule::from_value).collect();
+    old.iter().filter_map(BlockedAppRule::from_value).all(|old_rule| {
+        new_rules
+            .iter()
+            .filter(|r| r.covers_matches_of(&old_rule))
+            .any(|new_rule| {
+                old_rule.allows_enforcement_change(new_rule.enforcement, new_rule.kill_after_seconds)
+                    && old_rule.allows_quota_change(new_rule.daily_quota_minutes)
+                    && covers(&new_rule.schedules, &old_rule.schedules)
+            })
+    })
+}
+
+fn websites_at_least_as_strict(new: &[Value], old: &[Value]) -> bool {
+    old.iter().all(|old_item| {
+        let domain = match website_domain(old_item) {
+            Some(d) => d,
+            None => return true,
+        };
+        let variants = website_variants(old_item);
+        new.iter()
+            .filter(|item| website_domain(item).as_deref() == Some(domain.as_str()))
+            .filter(|item| {
+                let new_variants = website_variants(item);
+                variants.iter().all(|v| new_variants.contains(v))
+            })
+            .any(|new_item| covers(&website_schedules(new_item), &website_schedules(old_item)))
+    })
+}
+
+/// Whether switching from `old` to `new` blocks at least everything `old`
+/// did, at least as firmly. `dns_strictness` gives a provider's level;
+/// unknown providers count as the least strict, and a profile without a
+/// provider keeps the one in use.
+pub fn is_at_least_as_strict(new: &BlockProfile, old: &BlockProfile, dns_strictness: impl Fn(&str) -> Option<u8>) -> bool {
+    let dns_ok = match (&new.dns_provider, &old.dns_provider) {
+        (Some(new_id), Some(old_id)) => {
+            new_id == old_id || dns_strictness(new_id).unwrap_or(0) >= dns_strictness(old_id).unwrap_or(0)
+        }
+        _ => true,
+    };
+
+    dns_ok
+        && (new.enforce_safe_search || !old.enforce_safe_search)
+        && apps_at_least_as_strict(&new.blocked_apps, &old.blocked_apps)
+        && websites_at_least_as_strict(&new.blocked_websites, &old.blocked_websites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn profile(apps: Value, websites: Value, dns_provider: Option<&str>) -> BlockProfile {
+        BlockProfile {
+            blocked_apps: serde_json::from_value(apps).unwrap(),
+            blocked_websites: serde_json::from_value(websites).unwrap(),
+            dns_provider: dns_provider.map(|d| d.to_string()),
+            enforce_safe_search: false,
+        }
+    }
+
+    fn strictness(id: &str) -> Option<u8> {
+        match id {
+            "family" => Some(2),
+            "security" => Some(1),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn app_rules_must_keep_their_match_fields() {
+        let old = profile(json!([{ "processName": "steam.exe", "sha256": "0f343b0931126a20f133d67c2b018a3b5a7ad6d4a4b2f5a1e3c4d5e6f7a8b9c0" }]), json!([]), None);
+        let name_only = profile(json!(["steam.exe"]), json!([]), None);
+        assert!(!is_at_least_as_strict(&name_only, &old, strictness));
+        assert!(is_at_least_as_strict(&old, &name_only, strictness));
+
+        let by_publisher = profile(json!([{ "publisher": "Valve Corp." }]), json!([]), None);
+        let by_product = profile(json!([{ "publisher": "Valve Corp.", "productName": "Steam" }]), json!([]), None);
+        assert!(is_at_least_as_strict(&by_publisher, &by_product, strictness));
+        assert!(!is_at_least_as_strict(&by_product, &by_publisher, strictness));
+    }
+
+    #[test]
+    fn websites_must_keep_their_variants() {
+        let old = profile(json!([]), json!([{ "domain": "reddit.com", "variants": ["old.reddit.com", "www.reddit.com"] }]), None);
+        let fewer = profile(json!([]), json!([{ "domain": "reddit.com", "variants": ["www.reddit.com"] }]), None);
+        let more = profile(json!([]), json!([{ "domain": "reddit.com", "variants": ["www.reddit.com", "Old.Reddit.com", "i.reddit.com"] }]), None);
+        assert!(!is_at_least_as_strict(&fewer, &old, strictness));
+        assert!(is_at_least_as_strict(&more, &old, strictness));
+        assert!(!is_at_least_as_strict(&profile(json!([]), json!(["reddit.com"]), None), &old, strictness));
+    }
+
+    #[test]
+    fn dns_provider_must_not_get_weaker() {
+        let family = profile(json!([]), json!([]), Some("family"));
+        let security = profile(json!([]), json!([]), Some("security"));
+        let custom = profile(json!([]), json!([]), Some("custom-1"));
+        assert!(is_at_least_as_strict(&family, &security, strictness));
+        assert!(!is_at_least_as_strict(&security, &family, strictness));
+        assert!(!is_at_least_as_strict(&custom, &security, strictness));
+        assert!(is_at_least_as_strict(&custom, &custom, strictness));
+        assert!(is_at_least_as_strict(&profile(json!([]), json!([]), None), &family, strictness));
+    }
+}
@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, ToSocketAddrs};
+
+use crate::hosts_file::HostsEntry;
+
+/// Used for AAAA when an engine has no SafeSearch IPv6 address, so browsers
+/// cannot reach the unfiltered servers over IPv6 and fall back to IPv4.
+pub const BLOCKED_IPV6: &str = "::";
+
+/// One SafeSearch VIP: every host in `hosts` is pinned to whatever `cname`
+/// resolves to. `country_hosts` adds `<name>.<tld>` and `www.<name>.<tld>`
+/// for each listed TLD. Several targets can belong to the same engine.
+pub struct SafeSearchTarget {
+    pub engine: &'static str,
+    pub cname: &'static str,
+    pub hosts: &'static [&'static str],
+    pub country_hosts: Option<(&'static str, &'static [&'static str])>,
+    pub fallback_ipv4: Option<&'static str>,
+    pub fallback_ipv6: Option<&'static str>,
+}
+
+pub struct SafeSearchEngine {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub default_enabled: bool,
+}
+
+pub const SAFE_SEARCH_ENGINES: [SafeSearchEngine; 7] = [
+    SafeSearchEngine { id: "google", name: "Google", default_enabled: true },
+    SafeSearchEngine { id: "youtube", name: "YouTube (strict Restricted Mode)", default_enabled: true },
+    SafeSearchEngine { id: "youtube-moderate", name: "YouTube (moderate Restricted Mode)", default_enabled: false },
+    SafeSearchEngine { id: "bing", name: "Bing", default_enabled: true },
+    SafeSearchEngine { id: "duckduckgo", name: "DuckDuckGo", default_enabled: true },
+    SafeSearchEngine { id: "yandex", name: "Yandex", default_enabled: true },
+    SafeSearchEngine { id: "pixabay", name: "Pixabay", default_enabled: true },
+];
+
+// Strict YouTube comes before moderate so it wins when both are enabled.
+pub const SAFE_SEARCH_TARGETS: [SafeSearchTarget; 8] = [
+    SafeSearchTarget {
+        engine: "google",
+        cname: "forcesafesearch.google.com",
+        hosts: &[],
+        country_hosts: Some(("google", &GOOGLE_TLDS)),
+        fallback_ipv4: Some("216.239.38.120"),
+        fallback_ipv6: Some("2001:4860:4802:32::78"),
+    },
+    SafeSearchTarget {
+        engine: "youtube",
+        cname: "restrict.youtube.com",
+        hosts: &[
+            "www.youtube.com",
+            "m.youtube.com",
+            "youtubei.googleapis.com",
+            "youtube.googleapis.com",
+            "www.youtube-nocookie.com",
+        ],
+        country_hosts: None,
+        fallback_ipv4: Some("216.239.38.120"),
+        fallback_ipv6: Some("2001:4860:4802:32::78"),
+    },
+    SafeSearchTarget {
+        engine: "youtube-moderate",
+        cname: "restrictmoderate.youtube.com",
+        hosts: &[
+            "www.youtube.com",
+            "m.youtube.com",
+            "youtubei.googleapis.com",
+            "youtube.googleapis.com",
+            "www.youtube-nocookie.com",
+        ],
+        country_hosts: None,
+        fallback_ipv4: Some("216.239.38.119"),
+        fallback_ipv6: Some("2001:4860:4802:32::77"),
+    },
+    SafeSearchTarget {
+        engine: "bing",
+        cname: "strict.bing.com",
+        hosts: &["bing.com", "www.bing.com"],
+        country_hosts: None,
+        fallback_ipv4: Some("204.79.197.220"),
+        fallback_ipv6: None,
+    },
+    SafeSearchTarget {
+        engine: "duckduckgo",
+        cname: "safe.duckduckgo.com",
+        hosts: &["duckduckgo.com", "www.duckduckgo.com", "start.duckduckgo.com"],
+        country_hosts: None,
+        fallback_ipv4: None,
+        fallback_ipv6: None,
+    },
+    SafeSearchTarget {
+        engine: "yandex",
+        cname: "familysearch.yandex.ru",
+        hosts: &["yandex.ru", "www.yandex.ru"],
+        country_hosts: None,
+        fallback_ipv4: Some("213.180.193.56"),
+        fallback_ipv6: None,
+    },
+    SafeSearchTarget {
+        engine: "yandex",
+        cname: "familysearch.yandex.com",
+        hosts: &["yandex.com", "www.yandex.com"],
+        country_hosts: None,
+        fallback_ipv4: Some("213.180.204.92"),
+        fallback_ipv6: None,
+    },
+    SafeSearchTarget {
+        engine: "pixabay",
+        cname: "safesearch.pixabay.com",
+        hosts: &["pixabay.com", "www.pixabay.com"],
+        country_hosts: None,
+        fallback_ipv4: None,
+        fallback_ipv6: None,
+    },
+];
+
+/// Country domains Google serves search from; each gets `google.<tld>` and
+/// `www.google.<tld>` pinned to forcesafesearch.google.com.
+pub const GOOGLE_TLDS: [&str; 187] = [
+    "com", "ad", "ae", "com.af", "com.ag", "al", "am", "co.ao", "com.ar", "as", "at", "com.au", "az", "ba",
+    "com.bd", "be", "bf", "bg", "com.bh", "bi", "bj", "com.bn", "com.bo", "com.br", "bs", "bt", "co.bw", "by",
+    "com.bz", "ca", "cat", "cd", "cf", "cg", "ch", "ci", "co.ck", "cl", "cm", "cn", "com.co", "co.cr", "com.cu",
+    "cv", "com.cy", "cz", "de", "dj", "dk", "dm", "com.do", "dz", "com.ec", "ee", "com.eg", "es", "com.et",
+    "fi", "com.fj", "fm", "fr", "ga", "ge", "gg", "com.gh", "com.gi", "gl", "gm", "gr", "com.gt", "gy",
+    "com.hk", "hn", "hr", "ht", "hu", "co.id", "ie", "co.il", "im", "co.in", "iq", "is", "it", "je", "com.jm",
+    "jo", "co.jp", "co.ke", "com.kh", "ki", "kg", "co.kr", "com.kw", "kz", "la", "com.lb", "li", "lk", "co.ls",
+    "lt", "lu", "lv", "com.ly", "co.ma", "md", "me", "mg", "mk", "ml", "com.mm", "mn", "com.mt", "mu", "mv",
+    "mw", "com.mx", "com.my", "co.mz", "com.na", "com.ng", "com.ni", "ne", "nl", "no", "com.np", "nr", "nu",
+    "co.nz", "com.om", "com.pa", "com.pe", "com.pg", "com.ph", "com.pk", "pl", "pn", "com.pr", "ps", "pt",
+    "com.py", "com.qa", "ro", "rs", "ru", "rw", "com.sa", "com.sb", "sc", "se", "com.sg", "sh", "si", "sk",
+    "com.sl", "sn", "so", "sm", "sr", "st", "com.sv", "td", "tg", "co.th", "com.tj", "tl", "tm", "tn", "to",
+    "com.tr", "tt", "com.tw", "co.tz", "com.ua", "co.ug", "co.uk", "com.uy", "co.uz", "com.vc", "co.ve",
+    "co.vi", "com.vn", "vu", "ws", "co.za", "co.zm", "co.zw",
+];
+
+pub fn find_engine(id: &str) -> Option<&'static SafeSearchEngine> {
+    SAFE_SEARCH_ENGINES.iter().find(|e| e.id.eq_ignore_ascii_case(id.trim()))
+}
+
+pub fn target_hosts(target: &SafeSearchTarget) -> Vec<String> {
+    let mut hosts: Vec<String> = target.hosts.iter().map(|h| h.to_string()).collect();
+    if let Some((name, tlds)) = target.country_hosts {
+        hosts.extend(tlds.iter().flat_map(|tld| [format!("{}.{}", name, tld), format!("www.{}.{}", name, tld)]));
+    }
+    hosts
+}
+
+pub fn all_safe_search_hosts() -> HashSet<String> {
+    SAFE_SEARCH_TARGETS.iter().flat_map(target_hosts).collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedTarget {
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>,
+}
+
+pub trait SafeSearchResolver {
+    /// Every address `cname` currently resolves to.
+    fn resolve(&self, cname: &str) -> Result<Vec<IpAddr>, String>;
+}
+
+/// Resolves through the system resolver, i.e. whatever DNS the machine uses.
+pub struct SystemResolver;
+
+impl SafeSearchResolver for SystemResolver {
+    fn resolve(&self, cname: &str) -> Result<Vec<IpAddr>, String> {
+        let addrs = (cname, 0)
+            .to_socket_addrs()
+            .map_err(|e| format!("failed to resolve {}: {}", cname, e))?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+}
+
+fn is_usable_address(ip: &IpAddr) -> bool {
+    !ip.is_unspecified() && !ip.is_loopback()
+}
+
+/// Keeps `pinned` while it is still one of `candidates`, so a VIP served
+/// round-robin does not change the hosts file on every lookup.
+fn pick_address(pinned: Option<&String>, candidates: &[String]) -> Option<String> {
+    match pinned {
+        Some(pinned) if candidates.contains(pinned) => Some(pinned.clone()),
+        _ => candidates.first().cloned(),
+    }
+}
+
+/// Re-resolves `cnames`, starting from the last known good addresses. A
+/// failed lookup, or one answered only with blocked/loopback addresses,
+/// keeps the previous value, as does an answer that still includes it.
+/// Returns the new map and whether anything changed.
+pub fn refresh_addresses(
+    resolver: &dyn SafeSearchResolver,
+    cnames: &[&str],
+    last_known_good: &HashMap<String, ResolvedTarget>,
+) -> (HashMap<String, ResolvedTarget>, bool) {
+    let mut addresses = last_known_good.clone();
+    let mut changed = false;
+
+    for cname in cnames {
+        let answer = match resolver.resolve(cname) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("refresh_addresses: keeping last known address: {}", e);
+                continue;
+            }
+        };
+
+        let usable: Vec<&IpAddr> = answer.iter().filter(|ip| is_usable_address(ip)).collect();
+        let ipv4: Vec<String> = usable.iter().filter(|ip| ip.is_ipv4()).map(|ip| ip.to_string()).collect();
+        let ipv6: Vec<String> = usable.iter().filter(|ip| ip.is_ipv6()).map(|ip| ip.to_string()).collect();
+        if ipv4.is_empty() {
+            eprintln!("refresh_addresses: ignoring unusable answer for {}: {:?}", cname, answer);
+            continue;
+        }
+
+        let previous = addresses.get(*cname);
+        let resolved = ResolvedTarget {
+            ipv4: pick_address(previous.and_then(|p| p.ipv4.as_ref()), &ipv4),
+            ipv6: pick_address(previous.and_then(|p| p.ipv6.as_ref()), &ipv6),
+        };
+
+        if addresses.get(*cname) != Some(&resolved) {
+            addresses.insert(cname.to_string(), resolved);
+            changed = true;
+        }
+    }
+
+    (addresses, changed)
+}
+
+/// Hosts entries for the enabled engines. `resolved` is keyed by CNAME;
+/// targets missing from it use their documented fallback addresses, or are
+/// skipped when there is none. When two targets pin the same host, the
+/// earlier one in `SAFE_SEARCH_TARGETS` wins.
+pub fn build_entries(enabled_engines: &[String], resolved: &HashMap<String, ResolvedTarget>) -> Vec<HostsEntry> {
+    let mut entries: Vec<HostsEntry> = Vec::new();
+    let mut pinned: Vec<String> = Vec::new();
+
+    for target in SAFE_SEARCH_TARGETS.iter() {
+        if !enabled_engines.iter().any(|e| e == target.engine) {
+            continue;
+        }
+
+        let addresses = resolved.get(target.cname);
+        let ipv4 = match addresses.and_then(|r| r.ipv4.clone()).or_else(|| target.fallback_ipv4.map(|s| s.to_string())) {
+            Some(ip) => ip,
+            None => continue,
+        };
+        let ipv6 = addresses
+            .and_then(|r| r.ipv6.clone())
+            .or_else(|| target.fallback_ipv6.map(|s| s.to_string()))
+            .unwrap_or_else(|| BLOCKED_IPV6.to_string());
+
+        for host in target_hosts(target) {
+            if pinned.contains(&host) {
+                continue;
+            }
+            entries.push(HostsEntry::new(&ipv4, &host));
+            entries.push(HostsEntry::new(&ipv6, &host));
+            pinned.push(host);
+        }
+    }
+
+    entries
+}
+
+/// Set in blockData.json while SafeSearch is off but its hosts lines may
+/// still be in place, e.g. because the elevation prompt was canceled.
+pub const PENDING_SAFE_SEARCH_REMOVAL_KEY: &str = "pendingSafeSearchRemoval";
+
+pub fn is_removal_pending(block_data: &Map<String, Value>) -> bool {
+    block_data.get(PENDING_SAFE_SEARCH_REMOVAL_KEY).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Runs a pending removal and clears the marker once nothing is left to
+/// do: `remove` succeeded, or SafeSearch was enforced again meanwhile. A
+/// failed removal stays pending for the next retry. Returns whether the
+/// marker was cleared.
+pub fn settle_pending_removal(
+    block_data: &mut Map<String, Value>,
+    enforced: bool,
+    remove: impl FnOnce() -> Result<bool, String>,
+) -> Result<bool, String> {
+    if !is_removal_pending(block_data) {
+        return Ok(false);
+    }
+    if !enforced {
+        remove()?;
+    }
+    block_data.remove(PENDING_SAFE_SEARCH_REMOVAL_KEY);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Answers from a table; unknown names fail like an offline resolver.
+    #[derive(Default)]
+    struct FakeResolver {
+        answers: RefCell<HashMap<String, Vec<IpAddr>>>,
+    }
+
+    impl FakeResolver {
+        fn set(&self, cname: &str, addresses: &[&str]) {
+            let addresses = addresses.iter().map(|a| a.parse().unwrap()).collect();
+            self.answers.borrow_mut().insert(cname.to_string(), addresses);
+        }
+    }
+
+    impl SafeSearchResolver for FakeResolver {
+        fn resolve(&self, cname: &str) -> Result<Vec<IpAddr>, String> {
+            self.answers.borrow().get(cname).cloned().ok_or_else(|| format!("failed to resolve {}", cname))
+        }
+    }
+
+    const BING: &str = "strict.bing.com";
+
+    fn target(ipv4: &str, ipv6: Option<&str>) -> ResolvedTarget {
+        ResolvedTarget { ipv4: Some(ipv4.to_string()), ipv6: ipv6.map(|ip| ip.to_string()) }
+    }
+
+    fn engines(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn addresses_for<'a>(entries: &'a [HostsEntry], host: &str) -> Vec<&'a str> {
+        entries.iter().filter(|e| e.host == host).map(|e| e.address.as_str()).collect()
+    }
+
+    #[test]
+    fn google_hosts_come_from_the_tld_table() {
+        let google = SAFE_SEARCH_TARGETS.iter().find(|t| t.engine == "google").unwrap();
+        let hosts = target_hosts(google);
+        assert_eq!(hosts.len(), GOOGLE_TLDS.len() * 2);
+        assert!(hosts.contains(&"www.google.co.uk".to_string()));
+        assert!(hosts.contains(&"google.com".to_string()));
+    }
+
+    #[test]
+    fn strict_youtube_wins_over_moderate() {
+        let entries = build_entries(&engines(&["youtube-moderate", "youtube"]), &HashMap::new());
+        assert_eq!(addresses_for(&entries, "www.youtube.com"), vec!["216.239.38.120", "2001:4860:4802:32::78"]);
+    }
+
+    #[test]
+    fn uses_resolved_addresses_then_fallbacks() {
+        let mut resolved = HashMap::new();
+        resolved.insert("strict.bing.com".to_string(), ResolvedTarget { ipv4: Some("204.79.197.221".into()), ipv6: None });
+        resolved.insert("safe.duckduckgo.com".to_string(), ResolvedTarget { ipv4: Some("52.142.124.215".into()), ipv6: None });
+
+        let entries = build_entries(&engines(&["bing", "duckduckgo", "pixabay", "yandex"]), &resolved);
+        assert_eq!(addresses_for(&entries, "www.bing.com"), vec!["204.79.197.221", BLOCKED_IPV6]);
+        assert_eq!(addresses_for(&entries, "duckduckgo.com"), vec!["52.142.124.215", BLOCKED_IPV6]);
+        assert_eq!(addresses_for(&entries, "yandex.com"), vec!["213.180.204.92", BLOCKED_IPV6]);
+        // No resolved address and no documented fallback: nothing to pin.
+        assert!(addresses_for(&entries, "pixabay.com").is_empty());
+        assert!(addresses_for(&entries, "www.google.com").is_empty());
+    }
+
+    #[test]
+    fn first_lookup_pins_the_first_usable_address() {
+        let resolver = FakeResolver::default();
+        resolver.set(BING, &["127.0.0.1", "204.79.197.220", "204.79.197.221", "::1", "2620:1ec:33::10"]);
+
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &HashMap::new());
+        assert!(changed);
+        assert_eq!(addresses[BING], target("204.79.197.220", Some("2620:1ec:33::10")));
+    }
+
+    #[test]
+    fn round_robin_answers_keep_the_pinned_address() {
+        let resolver = FakeResolver::default();
+        let mut known = HashMap::new();
+        known.insert(BING.to_string(), target("204.79.197.221", None));
+
+        resolver.set(BING, &["204.79.197.220", "204.79.197.221"]);
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &known);
+        assert!(!changed);
+        assert_eq!(addresses, known);
+
+        resolver.set(BING, &["204.79.197.222", "204.79.197.220"]);
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &known);
+        assert!(changed);
+        assert_eq!(addresses[BING], target("204.79.197.222", None));
+    }
+
+    #[test]
+    fn failed_or_blocked_lookups_keep_last_known_good() {
+        let resolver = FakeResolver::default();
+        let mut known = HashMap::new();
+        known.insert(BING.to_string(), target("204.79.197.220", None));
+
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &known);
+        assert!(!changed);
+        assert_eq!(addresses, known);
+
+        resolver.set(BING, &["0.0.0.0", "::"]);
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &known);
+        assert!(!changed);
+        assert_eq!(addresses, known);
+
+        // Nothing known yet and nothing usable: the fallback stays in charge.
+        let (addresses, changed) = refresh_addresses(&resolver, &[BING], &HashMap::new());
+        assert!(!changed);
+        assert!(addresses.is_empty());
+    }
+
+    fn pending() -> Map<String, Value> {
+        let mut block_data = Map::new();
+        block_data.insert(PENDING_SAFE_SEARCH_REMOVAL_KEY.to_string(), Value::Bool(true));
+        block_data
+    }
+
+    #[test]
+    fn failed_removal_stays_pending_until_it_succeeds() {
+        let mut block_data = pending();
+        let result = settle_pending_removal(&mut block_data, false, || Err("elevation canceled by user".into()));
+        assert_eq!(result, Err("elevation canceled by user".to_string()));
+        assert!(is_removal_pending(&block_data));
+
+        assert_eq!(settle_pending_removal(&mut block_data, false, || Ok(true)), Ok(true));
+        assert!(!is_removal_pending(&block_data));
+
+        // Nothing pending: the hosts file is left alone.
+        let result = settle_pending_removal(&mut block_data, false, || panic!("removal must not run"));
+        assert_eq!(result, Ok(false));
+    }
+
+    #[test]
+    fn enforcing_again_drops_the_pending_removal() {
+        let mut block_data = pending();
+        let result = settle_pending_removal(&mut block_data, true, || panic!("removal must not run"));
+        assert_eq!(result, Ok(true));
+        assert!(!is_removal_pending(&block_data));
+    }
+}
@@ -0,0 +1,465 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDateTime, TimeZone, Timelike};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const FULL_DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+
+/// A weekly window during which a rule is enforced, in local wall-clock
+/// time. A rule with no schedules is enforced all the time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    /// Days the window starts on ("mon" .. "sun"); empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// "HH:MM". An end at or before the start runs past midnight into the
+    /// next day; "24:00" ends at midnight.
+    pub start: String,
+    pub end: String,
+}
+
+struct Window {
+    days: Vec<u32>,
+    start: u32,
+    length: u32,
+}
+
+fn parse_minutes(time: &str) -> Result<u32, String> {
+    let (h, m) = time.trim().split_once(':').ok_or_else(|| format!("invalid time '{}'", time))?;
+    let h: u32 = h.parse().map_err(|_| format!("invalid time '{}'", time))?;
+    let m: u32 = m.parse().map_err(|_| format!("invalid time '{}'", time))?;
+    if m >= 60 || h > 24 || (h == 24 && m != 0) {
+        return Err(format!("invalid time '{}'", time));
+    }
+    Ok(h * 60 + m)
+}
+
+fn format_minutes(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn day_index(day: &str) -> Option<u32> {
+    let day = day.trim().to_lowercase();
+    DAY_NAMES
+        .iter()
+        .position(|d| *d == day)
+        .or_else(|| FULL_DAY_NAMES.iter().position(|d| *d == day))
+        .map(|i| i as u32)
+}
+
+impl Schedule {
+    fn window(&self) -> Result<Window, String> {
+        let start = parse_minutes(&self.start)?;
+        let end = parse_minutes(&self.end)?;
+        if start >= MINUTES_PER_DAY {
+            return Err(format!("invalid start time '{}'", self.start));
+        }
+
+        let days = if self.days.is_empty() {
+            (0..7).collect()
+        } else {
+            self.days
+                .iter()
+                .map(|d| day_index(d).ok_or_else(|| format!("invalid day '{}'", d)))
+                .collect::<Result<Vec<u32>, String>>()?
+        };
+
+        let length = if end > start { end - start } else { end + MINUTES_PER_DAY - start };
+        Ok(Window { days, start, length })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.window().map(|_| ())
+    }
+}
+
+/// The valid windows, or `None` when there are none; a rule whose
+/// schedules are all unusable is enforced all the time rather than never.
+fn windows(schedules: &[Schedule]) -> Option<Vec<Window>> {
+    let windows: Vec<Window> = schedules.iter().filter_map(|s| s.window().ok()).collect();
+    if windows.is_empty() {
+        None
+    } else {
+        Some(windows)
+    }
+}
+
+fn minute_of_week(local: &NaiveDateTime) -> u32 {
+    local.weekday().num_days_from_monday() * MINUTES_PER_DAY + local.hour() * 60 + local.minute()
+}
+
+fn window_contains(window: &Window, minute: u32) -> bool {
+    window.days.iter().any(|day| {
+        let start = day * MINUTES_PER_DAY + window.start;
+        (minute + MINUTES_PER_WEEK - start) % MINUTES_PER_WEEK < window.length
+    })
+}
+
+/// Evaluated on the wall clock, so "08:00-18:00" means 08:00 local on both
+/// sides of a DST change.
+pub fn is_active_local(schedules: &[Schedule], local: &NaiveDateTime) -> bool {
+    match windows(schedules) {
+        Some(windows) => {
+            let minute = minute_of_week(local);
+            windows.iter().any(|w| window_contains(w, minute))
+        }
+        None => true,
+    }
+}
+
+pub fn is_active<Tz: TimeZone>(schedules: &[Schedule], now: &DateTime<Tz>) -> bool {
+    is_active_local(schedules, &now.naive_local())
+}
+
+/// The instant a local wall-clock time happens. Times skipped by a
+/// spring-forward gap happen when the gap ends; times repeated by a
+/// fall-back happen the first time round.
+pub fn resolve_local<Tz: TimeZone>(tz: &Tz, local: &NaiveDateTime) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(local) {
+        LocalResult::Single(t) => Some(t),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest),
+        LocalResult::None => (1..=24 * 60).find_map(|m| tz.from_local_datetime(&(*local + Duration::minutes(m))).earliest()),
+    }
+}
+
+/// The next instant after `now` at which a window starts or ends. Windows
+/// can overlap, so activity does not necessarily change there; callers
+/// re-evaluate rather than flip state.
+pub fn next_boundary<Tz: TimeZone>(schedules: &[Schedule], now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let windows = windows(schedules)?;
+    let tz = now.timezone();
+    let today = now.naive_local().date();
+
+    let mut next: Option<DateTime<Tz>> = None;
+    for offset in -1..=8 {
+        let date = today + Duration::days(offset);
+        let weekday = date.weekday().num_days_from_monday();
+        let midnight = date.and_hms_opt(0, 0, 0)?;
+
+        for window in windows.iter().filter(|w| w.days.contains(&weekday)) {
+            let start = midnight + Duration::minutes(window.start as i64);
+            let end = start + Duration::minutes(window.length as i64);
+            for local in [start, end] {
+                if let Some(t) = resolve_local(&tz, &local) {
+                    if t > *now && next.as_ref().map(|n| t < *n).unwrap_or(true) {
+                        next = Some(t);
+                    }
+                }
+            }
+        }
+    }
+    next
+}
+
+/// Which minutes of the week (Monday 00:00 first) the schedules enforce.
+pub fn weekly_coverage(schedules: &[Schedule]) -> Vec<bool> {
+    match windows(schedules) {
+        Some(windows) => (0..MINUTES_PER_WEEK).map(|m| windows.iter().any(|w| window_contains(w, m))).collect(),
+        None => vec![true; MINUTES_PER_WEEK as usize],
+    }
+}
+
+/// Whether `new` enforces at least whenever `old` did, i.e. switching to it
+/// does not loosen the rule.
+pub fn covers(new: &[Schedule], old: &[Schedule]) -> bool {
+    let new = weekly_coverage(new);
+    weekly_coverage(old).iter().zip(new.iter()).all(|(old, new)| !*old || *new)
+}
+
+static SPEC_ENTRY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?P<days>.*?)\s*(?:after\s+(?P<after>\d{1,2}:\d{2})|before\s+(?P<before>\d{1,2}:\d{2})|(?P<start>\d{1,2}:\d{2})\s*-\s*(?P<end>\d{1,2}:\d{2})|(?P<allday>all\s+day))$",
+    )
+    .expect("valid schedule regex")
+});
+
+fn parse_days(spec: &str) -> Result<Vec<String>, String> {
+    let spec = spec.split_whitespace().collect::<Vec<_>>().join(" ").replace(" - ", "-").replace(" -", "-").replace("- ", "-");
+    let spec = spec.trim_end_matches(':').trim();
+    match spec {
+        "" | "daily" | "every day" | "everyday" => return Ok(Vec::new()),
+        "weekdays" => return Ok(DAY_NAMES[..5].iter().map(|d| d.to_string()).collect()),
+        "weekends" => return Ok(DAY_NAMES[5..].iter().map(|d| d.to_string()).collect()),
+        _ => {}
+    }
+
+    let mut days: Vec<u32> = Vec::new();
+    for part in spec.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = day_index(from).ok_or_else(|| format!("invalid day '{}'", from))?;
+                let to = day_index(to).ok_or_else(|| format!("invalid day '{}'", to))?;
+                let mut day = from;
+                loop {
+                    days.push(day);
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days.push(day_index(part).ok_or_else(|| format!("invalid day '{}'", part))?),
+        }
+    }
+
+    days.sort_unstable();
+    days.dedup();
+    if days.len() == 7 {
+        return Ok(Vec::new());
+    }
+    Ok(days.into_iter().map(|d| DAY_NAMES[d as usize].to_string()).collect())
+}
+
+/// Parses text such as "Mon-Fri 08:00-18:00; daily after 22:00". Entries
+/// are separated by ';' or new lines. An empty spec means always enforced.
+pub fn parse_schedule_spec(spec: &str) -> Result<Vec<Schedule>, String> {
+    let mut schedules = Vec::new();
+    for entry in spec.split([';', '\n']).map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let normalized = entry.to_lowercase().replace(['\u{2013}', '\u{2014}'], "-");
+        let caps = SPEC_ENTRY
+            .captures(&normalized)
+            .ok_or_else(|| format!("could not read schedule '{}'", entry))?;
+
+        let (start, end) = if let Some(at) = caps.name("after") {
+            (parse_minutes(at.as_str())?, MINUTES_PER_DAY)
+        } else if let Some(at) = caps.name("before") {
+            (0, parse_minutes(at.as_str())?)
+        } else if caps.name("allday").is_some() {
+            (0, MINUTES_PER_DAY)
+        } else {
+            let start = caps.name("start").map(|m| m.as_str()).unwrap_or_default();
+            let end = caps.name("end").map(|m| m.as_str()).unwrap_or_default();
+            (parse_minutes(start)?, parse_minutes(end)?)
+        };
+
+        let schedule = Schedule {
+            days: parse_days(caps.name("days").map(|m| m.as_str()).unwrap_or_default())?,
+            start: format_minutes(start),
+            end: format_minutes(end),
+        };
+        schedule.validate()?;
+        schedules.push(schedule);
+    }
+    Ok(schedules)
+}
+
+fn format_days(days: &[String]) -> String {
+    let mut indexes: Vec<u32> = days.iter().filter_map(|d| day_index(d)).collect();
+    indexes.sort_unstable();
+    indexes.dedup();
+
+    let title = |i: u32| {
+        let name = DAY_NAMES[i as usize];
+        format!("{}{}", name[..1].to_uppercase(), &name[1..])
+    };
+
+    match indexes.as_slice() {
+        [] | [0, 1, 2, 3, 4, 5, 6] => "Daily".to_string(),
+        [first, .., last] if (last - first) as usize + 1 == indexes.len() && indexes.len() > 2 => {
+            format!("{}-{}", title(*first), title(*last))
+        }
+        _ => indexes.iter().map(|i| title(*i)).collect::<Vec<_>>().join(","),
+    }
+}
+
+/// The inverse of `parse_schedule_spec`, for showing a rule's schedules.
+pub fn format_schedules(schedules: &[Schedule]) -> String {
+    schedules
+        .iter()
+        .map(|s| format!("{} {}-{}", format_days(&s.days), s.start, s.end))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+const SYNC_RETRY_BASE: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+const SYNC_RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Remembers the hosts content a failed elevated write wanted, so a
+/// dismissed elevation prompt is not shown again on every wake-up. The same
+/// content is retried after a delay that doubles with each failure; new
+/// content (a boundary passed or the rules changed) is tried right away.
+#[derive(Debug, Default)]
+pub struct SyncBackoff {
+    failed: Option<FailedSync>,
+}
+
+#[derive(Debug)]
+struct FailedSync {
+    desired: String,
+    failures: u32,
+    retry_at: Instant,
+}
+
+impl SyncBackoff {
+    pub fn should_attempt(&self, desired: &str, now: Instant) -> bool {
+        match &self.failed {
+            Some(failed) => failed.desired != desired || now >= failed.retry_at,
+            None => true,
+        }
+    }
+
+    pub fn record_failure(&mut self, desired: String, now: Instant) {
+        let failures = match &self.failed {
+            Some(failed) if failed.desired == desired => failed.failures.saturating_add(1),
+            _ => 1,
+        };
+        let delay = SYNC_RETRY_BASE.saturating_mul(1 << (failures - 1).min(16)).min(SYNC_RETRY_MAX);
+        self.failed = Some(FailedSync { desired, failures, retry_at: now + delay });
+    }
+
+    pub fn record_success(&mut self) {
+        self.failed = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDate};
+    use chrono_tz::America::New_York;
+
+    fn schedule(days: &[&str], start: &str, end: &str) -> Schedule {
+        Schedule { days: days.iter().map(|d| d.to_string()).collect(), start: start.into(), end: end.into() }
+    }
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d).unwrap().and_hms_opt(h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn overnight_window_runs_past_midnight() {
+        // 2026-10-19 is a Monday.
+        let nightly = vec![schedule(&[], "22:00", "06:00")];
+        assert!(is_active_local(&nightly, &local(2026, 10, 19, 23, 30)));
+        assert!(is_active_local(&nightly, &local(2026, 10, 20, 5, 59)));
+        assert!(!is_active_local(&nightly, &local(2026, 10, 20, 6, 0)));
+        assert!(!is_active_local(&nightly, &local(2026, 10, 19, 21, 59)));
+    }
+
+    #[test]
+    fn sunday_night_window_wraps_into_monday() {
+        let sunday = vec![schedule(&["sun"], "22:00", "02:00")];
+        assert!(is_active_local(&sunday, &local(2026, 10, 25, 23, 0)));
+        assert!(is_active_local(&sunday, &local(2026, 10, 26, 1, 59)));
+        assert!(!is_active_local(&sunday, &local(2026, 10, 26, 2, 0)));
+        assert!(!is_active_local(&sunday, &local(2026, 10, 24, 23, 0)));
+    }
+
+    #[test]
+    fn no_usable_schedule_means_always_active() {
+        assert!(is_active_local(&[], &local(2026, 10, 19, 12, 0)));
+        assert!(is_active_local(&[schedule(&[], "25:00", "26:00")], &local(2026, 10, 19, 12, 0)));
+        assert!(next_boundary(&[], &New_York.from_utc_datetime(&local(2026, 10, 19, 12, 0))).is_none());
+    }
+
+    #[test]
+    fn is_active_uses_the_wall_clock_of_the_offset() {
+        let office = vec![schedule(&["mon", "tue", "wed", "thu", "fri"], "08:00", "18:00")];
+        let offset = FixedOffset::east_opt(5 * 3600).unwrap();
+        // 04:00 UTC is 09:00 at +05:00.
+        let now = offset.from_utc_datetime(&local(2026, 10, 19, 4, 0));
+        assert!(is_active(&office, &now));
+        let evening = offset.from_utc_datetime(&local(2026, 10, 19, 14, 0));
+        assert!(!is_active(&office, &evening));
+    }
+
+    #[test]
+    fn boundary_in_spring_forward_gap_moves_to_gap_end() {
+        // 02:00-03:00 does not exist in New York on 2026-03-08.
+        let early = vec![schedule(&[], "02:30", "04:00")];
+        let now = New_York.from_local_datetime(&local(2026, 3, 8, 0, 0)).unwrap();
+        let next = next_boundary(&early, &now).unwrap();
+        assert_eq!(next.naive_local(), local(2026, 3, 8, 3, 0));
+
+        let resolved = resolve_local(&New_York, &local(2026, 3, 8, 2, 30)).unwrap();
+        assert_eq!(resolved.naive_local(), local(2026, 3, 8, 3, 0));
+    }
+
+    #[test]
+    fn boundary_in_fall_back_overlap_uses_first_occurrence() {
+        // 01:00-02:00 happens twice in New York on 2026-11-01.
+        let early = vec![schedule(&[], "01:30", "05:00")];
+        let now = New_York.from_local_datetime(&local(2026, 11, 1, 0, 0)).unwrap();
+        let next = next_boundary(&early, &now).unwrap();
+        assert_eq!(next.naive_utc(), local(2026, 11, 1, 5, 30));
+
+        let after_first = next + Duration::minutes(1);
+        let following = next_boundary(&early, &after_first).unwrap();
+        assert_eq!(following.naive_local(), local(2026, 11, 1, 5, 0));
+    }
+
+    #[test]
+    fn covers_compares_weekly_minutes() {
+        let weekdays = vec![schedule(&["mon", "tue", "wed", "thu", "fri"], "08:00", "18:00")];
+        let monday = vec![schedule(&["mon"], "09:00", "17:00")];
+        assert!(covers(&weekdays, &monday));
+        assert!(!covers(&monday, &weekdays));
+        assert!(covers(&[], &weekdays));
+        assert!(!covers(&weekdays, &[]));
+        assert!(covers(&[schedule(&[], "00:00", "24:00")], &[]));
+    }
+
+    #[test]
+    fn spec_round_trips_through_format() {
+        let parsed = parse_schedule_spec("Mon-Fri 08:00-18:00; daily after 22:00\nSat, Sunday before 09:00").unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                schedule(&["mon", "tue", "wed", "thu", "fri"], "08:00", "18:00"),
+                schedule(&[], "22:00", "24:00"),
+                schedule(&["sat", "sun"], "00:00", "09:00"),
+            ]
+        );
+
+        let formatted = format_schedules(&parsed);
+        assert_eq!(formatted, "Mon-Fri 08:00-18:00; Daily 22:00-24:00; Sat,Sun 00:00-09:00");
+        assert_eq!(parse_schedule_spec(&formatted).unwrap(), parsed);
+    }
+
+    #[test]
+    fn day_names_must_match_exactly() {
+        assert!(parse_schedule_spec("Monday 08:00-09:00").is_ok());
+        assert!(parse_schedule_spec("month 08:00-09:00").is_err());
+        assert!(schedule(&["sunny"], "08:00", "09:00").validate().is_err());
+        assert!(schedule(&["Tue"], "08:00", "09:00").validate().is_ok());
+    }
+
+    #[test]
+    fn failed_sync_backs_off_until_the_content_changes() {
+        let start = Instant::now();
+        let mut backoff = SyncBackoff::default();
+        assert!(backoff.should_attempt("a", start));
+
+        backoff.record_failure("a".into(), start);
+        assert!(!backoff.should_attempt("a", start + SYNC_RETRY_BASE / 2));
+        assert!(backoff.should_attempt("b", start));
+        assert!(backoff.should_attempt("a", start + SYNC_RETRY_BASE));
+
+        backoff.record_success();
+        assert!(backoff.should_attempt("a", start));
+    }
+
+    #[test]
+    fn repeated_failures_double_the_delay_up_to_the_cap() {
+        let start = Instant::now();
+        let mut backoff = SyncBackoff::default();
+        backoff.record_failure("a".into(), start);
+        backoff.record_failure("a".into(), start);
+        assert!(!backoff.should_attempt("a", start + SYNC_RETRY_BASE));
+        assert!(backoff.should_attempt("a", start + SYNC_RETRY_BASE * 2));
+
+        for _ in 0..40 {
+            backoff.record_failure("a".into(), start);
+        }
+        assert!(backoff.should_attempt("a", start + SYNC_RETRY_MAX));
+
+        // Failing on different content starts over.
+        backoff.record_failure("b".into(), start);
+        assert!(backoff.should_attempt("b", start + SYNC_RETRY_BASE));
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Longest gap between two samples that still counts in full. Longer gaps
+/// (sleep, a stalled loop) only count this much.
+pub const MAX_SAMPLE_GAP: Duration = Duration::from_secs(10);
+/// Days of history kept in the usage file, today included.
+pub const RETAINED_DAYS: usize = 7;
+
+/// Seconds of use per app per local day ("YYYY-MM-DD").
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsageLedger {
+    days: BTreeMap<String, HashMap<String, u64>>,
+}
+
+/// The key an app is counted under, so "Steam.exe" and "steam" share a budget.
+pub fn usage_key(process_name: &str) -> String {
+    process_name.trim().to_lowercase().trim_end_matches(".exe").to_string()
+}
+
+impl UsageLedger {
+    pub fn record(&mut self, day: &str, process_name: &str, elapsed: Duration) {
+        let used = self.days.entry(day.to_string()).or_default().entry(usage_key(process_name)).or_insert(0);
+        *used += elapsed.as_secs();
+    }
+
+    pub fn used(&self, day: &str, process_name: &str) -> Duration {
+        let seconds = self.days.get(day).and_then(|apps| apps.get(&usage_key(process_name))).copied().unwrap_or(0);
+        Duration::from_secs(seconds)
+    }
+
+    /// Drops all but the most recent `RETAINED_DAYS` days. Day keys sort
+    /// chronologically.
+    pub fn prune(&mut self) {
+        while self.days.len() > RETAINED_DAYS {
+            let oldest = self.days.keys().next().cloned();
+            if let Some(day) = oldest {
+                self.days.remove(&day);
+            }
+        }
+    }
+}
+
+pub fn remaining(quota: Duration, used: Duration) -> Duration {
+    quota.saturating_sub(used)
+}
+
+pub fn is_exhausted(quota: Duration, used: Duration) -> bool {
+    used >= quota
+}
+
+/// Turns protection-loop ticks into elapsed time. Whole seconds are handed
+/// out and the remainder carried, so a 2s loop does not round usage away.
+#[derive(Debug, Default)]
+pub struct UsageSampler {
+    last: Option<Instant>,
+    carry: Duration,
+}
+
+impl UsageSampler {
+    pub fn sample(&mut self, now: Instant) -> Duration {
+        let elapsed = match self.last {
+            Some(last) => now.saturating_duration_since(last).min(MAX_SAMPLE_GAP),
+            None => Duration::ZERO,
+        };
+        self.last = Some(now);
+
+        let total = self.carry + elapsed;
+        let whole = Duration::from_secs(total.as_secs());
+        self.carry = total - whole;
+        whole
+    }
+
+    /// Forgets the last sample, e.g. while the user is idle.
+    pub fn pause(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_carries_fractions_of_a_second() {
+        let mut sampler = UsageSampler::default();
+        let start = Instant::now();
+        assert_eq!(sampler.sample(start), Duration::ZERO);
+        assert_eq!(sampler.sample(start + Duration::from_millis(1500)), Duration::from_secs(1));
+        assert_eq!(sampler.sample(start + Duration::from_millis(3000)), Duration::from_secs(2));
+        assert_eq!(sampler.sample(start + Duration::from_millis(3700)), Duration::ZERO);
+        assert_eq!(sampler.sample(start + Duration::from_millis(4000)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn sampler_clamps_long_gaps() {
+        let mut sampler = UsageSampler::default();
+        let start = Instant::now();
+        sampler.sample(start);
+        assert_eq!(sampler.sample(start + Duration::from_secs(600)), MAX_SAMPLE_GAP);
+
+        // Nothing is counted for the time spent paused.
+        sampler.pause();
+        assert_eq!(sampler.sample(start + Duration::from_secs(900)), Duration::ZERO);
+        assert_eq!(sampler.sample(start + Duration::from_secs(902)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn ledger_shares_keys_and_keeps_recent_days() {
+        let mut ledger = UsageLedger::default();
+        ledger.record("2026-10-01", "Steam.exe", Duration::from_secs(30));
+        ledger.record("2026-10-01", "steam", Duration::from_secs(15));
+        assert_eq!(ledger.used("2026-10-01", "STEAM.EXE"), Duration::from_secs(45));
+
+        for day in 2..=8 {
+            ledger.record(&format!("2026-10-{:02}", day), "steam", Duration::from_secs(day));
+        }
+        ledger.prune();
+        assert_eq!(ledger.used("2026-10-01", "steam"), Duration::ZERO);
+        assert_eq!(ledger.used("2026-10-02", "steam"), Duration::from_secs(2));
+        assert_eq!(ledger.used("2026-10-08", "steam"), Duration::from_secs(8));
+
+        ledger.record("2026-10-10", "steam", Duration::from_secs(1));
+        ledger.prune();
+        assert_eq!(ledger.used("2026-10-02", "steam"), Duration::ZERO);
+        assert_eq!(ledger.used("2026-10-03", "steam"), Duration::from_secs(3));
+    }
+}
@@ -50,7 +50,7 @@ function getAllInstalledApps() {
 function createEmptyRow(message) {
   const row = document.createElement('tr');
   const cell = document.createElement('td');
-  cell.colSpan = 2;
+  cell.colSpan = 6;
   cell.textContent = message;
   row.appendChild(cell);
   return row;
@@ -85,6 +85,15 @@ function processBlockedAppsAndRenderTable(list) {
         const nameCell = document.createElement('td');
         nameCell.textContent = item.displayName || 'Unknown App';
 
+        const enforcementCell = document.createElement('td');
+        enforcementCell.appendChild(createEnforcementSelect(item));
+
+        const quotaCell = document.createElement('td');
+        quotaCell.appendChild(createQuotaButton(item));
+
+        const scheduleCell = document.createElement('td');
+        scheduleCell.appendChild(createScheduleButton('app', item.processName));
+
         const deleteCell = document.createElement('td');
         const deleteButton = createDeleteButton(item, index, item?.isAllowedForDelete);
         deleteCell.appendChild(deleteButton);
@@ -105,12 +114,139 @@ function processBlockedAppsAndRenderTable(list) {
         row.appendChild(iconCell);
 
         row.appendChild(nameCell);
+        row.appendChild(enforcementCell);
+        row.appendChild(quotaCell);
+        row.appendChild(scheduleCell);
         row.appendChild(deleteCell);
 
         tbody.appendChild(row);
     });
 }
 
+const ENFORCEMENT_MODES = [
+    { value: 'overlay', label: 'Show overlay' },
+    { value: 'overlay-then-kill', label: 'Overlay, then close after 30s' },
+    { value: 'kill', label: 'Close immediately' },
+    { value: 'suspend', label: 'Pause the app' }
+];
+
+function createEnforcementSelect(item) {
+    const select = document.createElement('select');
+    ENFORCEMENT_MODES.forEach(mode => {
+        const option = document.createElement('option');
+        option.value = mode.value;
+        option.textContent = mode.label;
+        select.appendChild(option);
+    });
+    select.value = item?.enforcement || 'overlay';
+
+    select.onchange = () => {
+        invoke('set_blocked_app_enforcement', { processName: item.processName, mode: select.value })
+            .catch(error => {
+                if (error === 'enforcement-change-requires-delay') {
+                    alert('To make blocking less strict, unblock the app and add it again.');
+                } else {
+                    console.error('Failed to change enforcement:', error);
+                }
+                select.value = item?.enforcement || 'overlay';
+            });
+    };
+
+    return select;
+}
+
+function formatMinutes(seconds) {
+    return Math.floor(seconds / 60) + ' min';
+}
+
+function createQuotaButton(item) {
+    const button = document.createElement('button');
+    button.textContent = 'No daily time';
+    button.style.padding = '5px 10px';
+    button.style.borderRadius = '5px';
+    button.style.cursor = 'pointer';
+
+    const processName = item.processName;
+    const refresh = () => invoke('get_usage_today')
+        .then(usage => {
+            const entry = (usage.apps || []).find(app => app.processName === processName);
+            if (!entry) {
+                button.textContent = 'No daily time';
+                return;
+            }
+            button.textContent = entry.exhausted
+                ? `Used up (${formatMinutes(entry.quotaSeconds)}/day)`
+                : `${formatMinutes(entry.remainingSeconds)} of ${formatMinutes(entry.quotaSeconds)} left`;
+            if (entry.pendingMinutes !== null && entry.pendingMinutes !== undefined) {
+                button.textContent += ` (changing to ${entry.pendingMinutes} min)`;
+            }
+        })
+        .catch(error => console.error('Failed to load usage:', error));
+    refresh();
+
+    button.onclick = () => {
+        const current = item?.dailyQuotaMinutes ?? '';
+        const answer = prompt('Minutes per day this app may run before it is blocked.\nLeave empty to block it all the time.', current);
+        if (answer === null) return;
+
+        const trimmed = answer.trim();
+        const minutes = trimmed === '' ? null : Number(trimmed);
+        if (minutes !== null && (!Number.isInteger(minutes) || minutes < 0)) {
+            alert('Please enter a whole number of minutes.');
+            return;
+        }
+
+        invoke('set_app_quota', { processName, minutes })
+            .then(result => {
+                if (!result.applied) {
+                    alert('More daily time makes blocking less strict, so it will apply once your delay runs out.');
+                }
+                refresh();
+            })
+            .catch(error => alert('Could not set daily time: ' + error));
+    };
+
+    return button;
+}
+
+function createScheduleButton(kind, name) {
+    const button = document.createElement('button');
+    button.textContent = 'Always';
+    button.style.padding = '5px 10px';
+    button.style.borderRadius = '5px';
+    button.style.cursor = 'pointer';
+
+    const refresh = () => invoke('get_block_schedule', { kind, name })
+        .then(status => {
+            button.textContent = status.schedule || 'Always';
+            if (status.pending !== null && status.pending !== undefined) {
+                button.textContent += ' (changing to ' + (status.pending || 'Always') + ')';
+            }
+        })
+        .catch(error => console.error('Failed to load schedule:', error));
+    refresh();
+
+    button.onclick = async () => {
+        const status = await invoke('get_block_schedule', { kind, name }).catch(() => null);
+        const spec = prompt(
+            'Block only during these times, e.g. "Mon-Fri 08:00-18:00; daily after 22:00".\nLeave empty to block all the time.',
+            status?.schedule || ''
+        );
+        if (spec === null) return;
+
+        invoke('set_block_schedule', { kind, name, spec })
+            .then(result => {
+                if (!result.applied) {
+                    alert('This schedule blocks less than the current one, so it will apply once your delay runs out.');
+                }
+                refresh();
+            })
+            .catch(error => alert('Could not set schedule: ' + error));
+    };
+
+    return button;
+}
+
 function getButtonTextAndColour(isAllowedToDelete){
     if(isAllowedToDelete){
         return {
@@ -295,6 +431,7 @@ function renderAppSearchModal() {
                     checkbox.type = 'checkbox';
                     checkbox.dataset.processName = app.processName;
                     checkbox.dataset.displayName = app.displayName;
+                    checkbox.dataset.installLocation = app.installLocation || '';
                     selectCell.appendChild(checkbox);
 
                     const nameCell = document.createElement('td');
@@ -329,9 +466,12 @@ function renderAppSearchModal() {
                         checkboxes.forEach(cb => {
                             const processName = cb?.dataset?.processName;
                             const displayName = cb?.dataset?.displayName;
+                            const installLocation = cb?.dataset?.installLocation;
 
                             if (processName && !updatedList.some(app => app.processName === processName)) {
-                                updatedList.push({ processName, displayName });
+                                const rule = { processName, displayName };
+                                if (installLocation) rule.installLocation = installLocation;
+                                updatedList.push(rule);
                             }
                         });
 
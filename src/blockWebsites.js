@@ -58,12 +58,16 @@ function removeWebsite(index){
     if(!list || index < 0 || index > list.length){
         return;
     }
-    const site = list[index];
+    const site = siteName(list[index]);
     invoke('remove_block_website', {site})
         .then(() => window.location.reload())
         .catch(error => console.log(error));
 }
 
+function siteName(item){
+    return typeof item === 'string' ? item : item?.domain;
+}
+
 function initializeListener(){
     listen("block-data-updated", () => renderTable());
 }
@@ -73,7 +77,7 @@ init();
 function createEmptyRow(message) {
     const row = document.createElement('tr');
     const cell = document.createElement('td');
-    cell.colSpan = 3;
+    cell.colSpan = 4;
     cell.textContent = message;
     row.appendChild(cell);
     return row;
@@ -87,7 +91,7 @@ function createDataRow(item, index, isAllowedToDelete) {
     icon.setAttribute('data-timr', '1');
 
     if (!isAllowedToDelete) {
-        const key = "allowedForUnblockWebsites-->" + item;
+        const key = "allowedForUnblockWebsites-->" + siteName(item);
         invoke("get_change_status", { settingId: key })
             .then(statusChange => {
                 if (statusChange && statusChange.isChanging) {
@@ -98,7 +102,10 @@ function createDataRow(item, index, isAllowedToDelete) {
     iconCell.appendChild(icon);
 
     const nameCell = document.createElement('td');
-    nameCell.textContent = typeof item === 'string' ? item : item.displayName;
+    nameCell.textContent = siteName(item);
+
+    const scheduleCell = document.createElement('td');
+    scheduleCell.appendChild(createScheduleButton('website', siteName(item)));
 
     const deleteCell = document.createElement('td');
     const deleteButton = createDeleteButton(item, index, isAllowedToDelete);
@@ -106,11 +113,50 @@ function createDataRow(item, index, isAllowedToDelete) {
 
     row.appendChild(iconCell);
     row.appendChild(nameCell);
+    row.appendChild(scheduleCell);
     row.appendChild(deleteCell);
 
     return row;
 }
 
+function createScheduleButton(kind, name) {
+    const button = document.createElement('button');
+    button.textContent = 'Always';
+    button.style.padding = '5px 10px';
+    button.style.borderRadius = '5px';
+    button.style.cursor = 'pointer';
+
+    const refresh = () => invoke('get_block_schedule', { kind, name })
+        .then(status => {
+            button.textContent = status.schedule || 'Always';
+            if (status.pending !== null && status.pending !== undefined) {
+                button.textContent += ' (changing to ' + (status.pending || 'Always') + ')';
+            }
+        })
+        .catch(error => console.error('Failed to load schedule:', error));
+    refresh();
+
+    button.onclick = async () => {
+        const status = await invoke('get_block_schedule', { kind, name }).catch(() => null);
+        const spec = prompt(
+            'Block only during these times, e.g. "Mon-Fri 08:00-18:00; daily after 22:00".\nLeave empty to block all the time.',
+            status?.schedule || ''
+        );
+        if (spec === null) return;
+
+        invoke('set_block_schedule', { kind, name, spec })
+            .then(result => {
+                if (!result.applied) {
+                    alert('This schedule blocks less than the current one, so it will apply once your delay runs out.');
+                }
+                refresh();
+            })
+            .catch(error => alert('Could not set schedule: ' + error));
+    };
+
+    return button;
+}
+
 function createDeleteButton(item, index, isAllowedToDelete) {
     const button = document.createElement('button');
     const { label, color } = getButtonTextAndColour(isAllowedToDelete);
@@ -127,7 +173,7 @@ function createDeleteButton(item, index, isAllowedToDelete) {
         if (isAllowedToDelete) {
             removeItem(index);
         } else {
-            const key = "allowedForUnblockWebsites-->" + item;
+            const key = "allowedForUnblockWebsites-->" + siteName(item);
             invoke("get_change_status", {settingId: key})
                 .then(statusChange => {
                     if(statusChange.isChanging){
@@ -136,7 +182,7 @@ function createDeleteButton(item, index, isAllowedToDelete) {
                     else{
                         const payload = {
                             itemType: "website",
-                            name: item
+                            name: siteName(item)
                         }
                         invoke("prime_for_deletion", payload).then(() => {
                             const row = button.closest('tr');
@@ -164,7 +210,7 @@ async function renderTable() {
             else{
                 const allowedWebsitesForDeletions = new Set(Array.from(blockData.allowedForUnblockWebsites ?? []));
                 Array.from(blockedWebsites).forEach((item, index) => {
-                    const isAllowedToDelete = allowedWebsitesForDeletions.has(item);
+                    const isAllowedToDelete = allowedWebsitesForDeletions.has(siteName(item));
                     const row = createDataRow(item, index, isAllowedToDelete);
                     tbody.appendChild(row);
                 });
@@ -1,7 +1,7 @@
 const { invoke } = window.__TAURI__.tauri;
 
-const radioButtons = document.querySelectorAll('input[name="approvalOption"]');
 const modal = document.querySelector('.modal-content');
+const providerOptions = document.getElementById('providerOptions');
 const cancelBtn = document.getElementById('cancelBtn');
 const confirmBtn = document.getElementById('confirmBtn');
 
@@ -10,20 +10,44 @@ cancelBtn.addEventListener('click', () => {
     closeModal();
 });
 
-radioButtons.forEach(radio => {
-    radio.addEventListener('change', (event) => confirmBtn.disabled = !event.target.checked);
-});
+const renderProviders = async () => {
+    const providers = await invoke('get_dns_providers');
+    providers.sort((a, b) => b.strictness - a.strictness);
+
+    providers.forEach(provider => {
+        const wrapper = document.createElement('div');
+        const label = document.createElement('label');
+        const radio = document.createElement('input');
+        radio.type = 'radio';
+        radio.name = 'approvalOption';
+        radio.value = provider.id;
+        radio.addEventListener('change', (event) => confirmBtn.disabled = !event.target.checked);
+        label.appendChild(radio);
+        label.append(` ${provider.name}${provider.builtin ? '' : ' (custom)'}`);
+
+        const description = document.createElement('p');
+        description.textContent = provider.description;
 
-const turnOnDNS = (isStrict) => invoke('turn_on_dns', {isStrict});
+        wrapper.appendChild(label);
+        wrapper.appendChild(description);
+        providerOptions.appendChild(wrapper);
+    });
+};
+
+const turnOnDNS = (providerId) => invoke('turn_on_dns', {providerId});
 
 confirmBtn.addEventListener('click', async () => {
-    const selectedOption = document.querySelector('input[name="approvalOption"]:checked').value;
-    const isStrict = (selectedOption === "strict");
-    await turnOnDNS(isStrict);
+    const providerId = document.querySelector('input[name="approvalOption"]:checked').value;
+    const result = await turnOnDNS(providerId);
+    if (result && !result.applied) {
+        alert('This provider is less strict than the current one, so the switch will happen once your delay runs out.');
+    }
     modal.style.display = 'none';
     closeModal();
 });
 
 function closeModal(){
     invoke('close_invoking_window');
-}
\ No newline at end of file
+}
+
+renderProviders();
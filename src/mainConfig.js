@@ -70,15 +70,28 @@ function init(){
     initializeEnableProtectiveDns();
     initializeSettingsAndAppProtection();
     initializeSafeSearchProtection();
+    initializeFocusSession();
+    initializeProfiles();
     listenForRefresh();
     listenForTimerUpdate();
     initTooltips();
 
     listen("block-data-updated", () => window.location.reload());
+    listen("network-changed", () => reloadIfDnsStateChanged());
 
     Array.from(KEYS).map(key => showTimerIcon(key));
 }
 
+async function reloadIfDnsStateChanged(){
+    const protectiveDnsSwitch = document.getElementById("enableProtectiveDNS");
+    if(!protectiveDnsSwitch){
+        return;
+    }
+    if(protectiveDnsSwitch.checked !== await isProtectiveDnsOn("enableProtectiveDNS")){
+        window.location.reload();
+    }
+}
+
 function showTimerIcon(key){
     return invoke('get_change_status', {settingId: key})
         .then(status => {
@@ -261,6 +274,123 @@ async function initializeSafeSearchProtection(){
     });
 }
 
+async function initializeProfiles(){
+    const select = document.getElementById('profileSelect');
+    const newButton = document.getElementById('newProfile');
+    if (!select) return;
+
+    const state = await invoke('get_profiles').catch(() => null);
+    if (!state) return;
+
+    state.profiles.forEach(name => {
+        const option = document.createElement('option');
+        option.value = name;
+        option.textContent = name;
+        select.appendChild(option);
+
+        invoke('get_change_status', { settingId: state.switchSettingPrefix + name })
+            .then(status => {
+                if (status && status.isChanging) {
+                    option.textContent = name + ' (switching)';
+                    showElement('timer-profile');
+                }
+            })
+            .catch(() => {});
+    });
+    select.value = state.active;
+
+    select.onchange = async () => {
+        const name = select.value;
+        const settingId = state.switchSettingPrefix + name;
+        const pending = await invoke('get_change_status', { settingId }).catch(() => null);
+        if (pending && pending.isChanging) {
+            openConfirmationDialog(settingId);
+            select.value = state.active;
+            return;
+        }
+
+        invoke('switch_profile', { name })
+            .then(result => {
+                if (!result.applied) {
+                    alert(`'${name}' blocks less than the current profile, so it will apply once your delay runs out.`);
+                    select.value = state.active;
+                    showElement('timer-profile');
+                }
+            })
+            .catch(error => {
+                alert('Could not switch profile: ' + error);
+                select.value = state.active;
+            });
+    };
+
+    newButton.onclick = () => {
+        const name = prompt('New profile name:');
+        if (!name) return;
+        const copyCurrent = confirm('Start from the current blocks? Choose Cancel to start empty.');
+        invoke('create_profile', { name, copyCurrent })
+            .catch(error => alert('Could not create profile: ' + error));
+    };
+}
+
+async function initializeFocusSession(){
+    const button = document.getElementById('focusSessionButton');
+    const status = document.getElementById('focusStatus');
+    const editButton = document.getElementById('editFocusProfile');
+    if (!button) return;
+
+    const current = await invoke('get_focus_session').catch(() => null);
+    if (current) {
+        const minutes = Math.ceil(current.remainingMs / 60000);
+        status.textContent = `(${current.session.profile}, ${minutes} min left)`;
+        button.textContent = 'End early';
+
+        const change = await invoke('get_change_status', { settingId: current.endSettingId }).catch(() => null);
+        if (change && change.isChanging) showElement('timer-focusSession');
+
+        button.onclick = async () => {
+            const pending = await invoke('get_change_status', { settingId: current.endSettingId }).catch(() => null);
+            if (pending && pending.isChanging) {
+                openConfirmationDialog(current.endSettingId);
+                return;
+            }
+            invoke('request_end_focus_session')
+                .then(() => showElement('timer-focusSession'))
+                .catch(error => alert('Could not end the session: ' + error));
+        };
+    } else {
+        button.onclick = () => {
+            const profile = prompt('Focus profile to use:', 'focus');
+            if (!profile) return;
+            const duration = Number(prompt('Minutes to focus for:', '25'));
+            if (!Number.isInteger(duration) || duration <= 0) return;
+
+            invoke('start_focus_session', { duration, profile })
+                .then(() => window.location.reload())
+                .catch(error => alert('Could not start the session: ' + error));
+        };
+    }
+
+    editButton.onclick = async () => {
+        const name = prompt('Focus profile name:', current?.session?.profile || 'focus');
+        if (!name) return;
+        const profiles = await invoke('get_focus_profiles').catch(() => ({}));
+        const existing = profiles[name] || { apps: [], websites: [] };
+        const appNames = existing.apps.map(app => typeof app === 'string' ? app : app.processName);
+
+        const apps = prompt('Apps to block (process names, comma separated):', appNames.join(', '));
+        if (apps === null) return;
+        const websites = prompt('Websites to block (comma separated):', existing.websites.join(', '));
+        if (websites === null) return;
+
+        const split = text => text.split(',').map(item => item.trim()).filter(item => item);
+        // Keep the full rule for apps that were already in the profile.
+        const appEntries = split(apps).map(appName =>
+            existing.apps.find(app => (typeof app === 'string' ? app : app.processName) === appName) || appName);
+        invoke('save_focus_profile', { name, apps: appEntries, websites: split(websites) })
+            .catch(error => alert('Could not save the profile: ' + error));
+    };
+}
+
 function isSafeSearchEnabled(){
     return invoke('is_safe_search_enabled');
 }
@@ -1,13 +1,57 @@
 const { listen, emit } = window.__TAURI__.event;
 const { invoke } = window.__TAURI__.tauri;
 let appInfo;
+let killCountdown;
 
 document.addEventListener('DOMContentLoaded', async () => {
     initializeCloseButton();
     setUpAppInfo();
     hideElement('hidden');
+    listenForUpdates();
 });
 
+function listenForUpdates(){
+    listen('overlay-updated', (event) => {
+        const payload = event?.payload || {};
+        const params = new URLSearchParams();
+        Object.entries(payload).forEach(([key, value]) => {
+            if (value !== null && value !== undefined && value !== '') params.set(key, String(value));
+        });
+
+        const current = new URLSearchParams(window.location.search);
+        current.sort();
+        params.sort();
+        if (current.toString() !== params.toString()) {
+            window.location.search = params.toString();
+        }
+    });
+}
+
+function startKillCountdown(killAt, name, paragraph){
+    const render = () => {
+        const seconds = Math.max(0, Math.ceil((killAt - Date.now()) / 1000));
+        paragraph.textContent =
+                `'${name}' is blocked and will be closed automatically in ${seconds} second${seconds === 1 ? '' : 's'}. ` +
+                "Save your work now, or click close to close it immediately.";
+        if (seconds === 0) clearInterval(killCountdown);
+    };
+    render();
+    killCountdown = setInterval(render, 1000);
+}
+
+function describeEnforcement(name, action, outcome, error){
+    const verb = action === 'suspended' ? 'paused' : 'closed';
+    if (outcome === 'success') {
+        return `'${name}' is blocked and was ${verb} automatically.`;
+    }
+    if (outcome === 'partial') {
+        return `'${name}' is blocked and was ${verb}, but some of its helper processes could not be. ` +
+            "We will keep trying.";
+    }
+    return `'${name}' is blocked but could not be ${verb} automatically` + (error ? ` (${error})` : '') +
+        ". We will keep trying; please close it yourself.";
+}
+
 function hideButton(){
     const closeButton = document.getElementById('closeBtn');
     if (closeButton) closeButton.style.display = 'none';
@@ -22,7 +66,8 @@ function setUpAppInfo(){
     appInfo = {
         code: params.get('code') || '',
         displayName: params.get('displayName') || '',
-        processName: params.get('processName') || params.get('procName') || params.get('process') || ''
+        processName: params.get('processName') || params.get('procName') || params.get('process') || '',
+        pid: params.get('pid') ? Number(params.get('pid')) : null
     };
 
     const name = appInfo.displayName || appInfo.processName || 'the application';
@@ -61,6 +106,26 @@ function setUpAppInfo(){
                 "We detected that you are using an unsupported browser. " +
                 "Please close the browser to allow this overlay to close automatically.";
     }
+    else if(code === "dns-tampered"){
+        paragraph.textContent =
+                "We noticed that the DNS settings on this computer were changed away from protective DNS. " +
+                "We are restoring them now; please approve the prompt if one appears. This event has been recorded.";
+        hideButton();
+    }
+    else if((code === "blocked-app" || code === "quota-exhausted") && params.get('killAt')){
+        startKillCountdown(Number(params.get('killAt')), name, paragraph);
+    }
+    else if(code === "quota-exhausted"){
+        const minutes = Number(params.get('quotaMinutes') || 0);
+        paragraph.textContent =
+                `You have used today's ${minutes} minute${minutes === 1 ? '' : 's'} for '${name}'. ` +
+                "It will be available again tomorrow; please close it to allow this overlay to close automatically.";
+    }
+    else if(code === "blocked-app-enforced"){
+        paragraph.textContent = describeEnforcement(
+                name, params.get('action'), params.get('outcome'), params.get('error'));
+        hideButton();
+    }
     else if(code === "browser-with-vpn"){
         paragraph.textContent =
                 "We noticed a supported browser with a vpn extension running. " +
@@ -96,7 +161,7 @@ function initializeCloseButton(){
     }
 
     closeButton.addEventListener('click', () => {
-        invoke('close_app', { processName: appInfo.processName });
+        invoke('close_app', { processName: appInfo.processName, pid: appInfo.pid });
 
         const loadingIcon = document.getElementById('loading');
         if(loadingIcon){